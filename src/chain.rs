@@ -0,0 +1,237 @@
+//! Generating a full production chain for a target item, back to raw resources
+
+use crate::{
+    Connection, ItemOrFluidId, Node, NodeId, SocketPos,
+    cost::{self, CostModel},
+    nodes,
+    state::{Preset, Recipe, RecipeFilter, RecipeId, ResearchState},
+    utils::{Point2, Vec2},
+};
+use rustc_hash::FxHashMap;
+use std::collections::VecDeque;
+
+/// Chains are cut off at this many nodes, anything that is still missing is left unconnected
+const MAX_NODES: usize = 200;
+const COLUMN_WIDTH: f32 = 200.0;
+const ROW_HEIGHT: f32 = 150.0;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum RecipePreference {
+    /// The recipe that is named after the item, or the first recipe that makes it
+    #[default]
+    Default,
+    /// The recipe with the lowest raw resource cost
+    Cheapest,
+}
+
+impl RecipePreference {
+    pub fn next(self) -> Self {
+        match self {
+            RecipePreference::Default => RecipePreference::Cheapest,
+            RecipePreference::Cheapest => RecipePreference::Default,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ChainSettings {
+    pub preference: RecipePreference,
+    /// Recipes that the user picked for an item, these are used regardless of `preference`
+    pub pinned: FxHashMap<ItemOrFluidId, RecipeId>,
+    /// Helper recipes like barreling are skipped unless they are let through. The recipe
+    /// selector shares this.
+    pub filter: RecipeFilter,
+}
+
+pub struct Chain {
+    pub nodes: Vec<Node>,
+    pub connections: Vec<Connection>,
+}
+
+struct Builder<'a> {
+    preset: &'a Preset,
+    research: &'a ResearchState,
+    settings: &'a ChainSettings,
+    /// Only with [`RecipePreference::Cheapest`], it solves the cost of each item when it is first
+    /// needed
+    costs: Option<CostModel<'a>>,
+    next_id: usize,
+    nodes: Vec<Node>,
+    connections: Vec<Connection>,
+    /// The node output that supplies each item in the chain
+    producers: FxHashMap<ItemOrFluidId, SocketPos>,
+}
+
+/// Build a chain that supplies `rate` of `target` per second. The demand node is placed at
+/// `position`, and the rest of the chain to the left of it. Node ids start at `first_id`.
+pub fn build(
+    preset: &Preset,
+    research: &ResearchState,
+    settings: &ChainSettings,
+    first_id: NodeId,
+    position: Point2,
+    target: ItemOrFluidId,
+    rate: f32,
+) -> Chain {
+    let mut builder = Builder {
+        preset,
+        research,
+        settings,
+        costs: (settings.preference == RecipePreference::Cheapest)
+            .then(|| CostModel::new(preset, research)),
+        next_id: first_id.0,
+        nodes: Vec::new(),
+        connections: Vec::new(),
+        producers: FxHashMap::default(),
+    };
+
+    let demand = builder.add_node(|id| nodes::demand_node(id, position, target.clone(), rate));
+    let mut queue = VecDeque::from([(demand, 0, target)]);
+    while let Some((consumer, input_index, item_or_fluid)) = queue.pop_front() {
+        let producer = match builder.producers.get(&item_or_fluid) {
+            Some(producer) => Some(*producer),
+            None if builder.nodes.len() < MAX_NODES => {
+                builder.add_producer(&item_or_fluid, &mut queue)
+            }
+            None => None,
+        };
+        if let Some(producer) = producer {
+            builder.connections.push(Connection::new(
+                producer,
+                (consumer, input_index, true).into(),
+            ));
+        }
+    }
+
+    builder.layout(position);
+    Chain {
+        nodes: builder.nodes,
+        connections: builder.connections,
+    }
+}
+
+impl Builder<'_> {
+    fn add_node(&mut self, node: impl FnOnce(NodeId) -> Node) -> NodeId {
+        let id = NodeId(self.next_id);
+        self.next_id += 1;
+        self.nodes.push(node(id));
+        id
+    }
+
+    /// Add a node that produces `item_or_fluid`, and queue up its inputs. Returns `None` if
+    /// nothing can produce it, in which case it has to come from outside the factory.
+    fn add_producer(
+        &mut self,
+        item_or_fluid: &ItemOrFluidId,
+        queue: &mut VecDeque<(NodeId, usize, ItemOrFluidId)>,
+    ) -> Option<SocketPos> {
+        if !matches!(
+            item_or_fluid,
+            ItemOrFluidId::Item(_) | ItemOrFluidId::Fluid(_)
+        ) {
+            return None;
+        }
+
+        let preset = self.preset;
+        let id = if let Some(resource) = preset
+            .resources_producing(item_or_fluid)
+            .min_by(|a, b| a.name.cmp(&b.name))
+        {
+            let productivity = self.research.mining_productivity;
+            self.add_node(|id| {
+                nodes::resource_node(preset, id, Point2::ZERO, resource, productivity)
+            })
+        } else {
+            let recipe = self.pick_recipe(item_or_fluid)?;
+            self.add_node(|id| nodes::recipe_node(preset, id, Point2::ZERO, recipe))
+        };
+
+        let node = self.nodes.last().unwrap();
+        for (socket_index, output) in node.outputs.iter().enumerate() {
+            self.producers
+                .entry(output.item_or_fluid.clone())
+                .or_insert((id, socket_index, false).into());
+        }
+        for (socket_index, input) in node.inputs.iter().enumerate() {
+            queue.push_back((id, socket_index, input.item_or_fluid.clone()));
+        }
+        self.producers.get(item_or_fluid).copied()
+    }
+
+    /// Pick the recipe to make `item_or_fluid` with, out of the unlocked recipes. Recipes that
+    /// consume what they make are skipped, as those would never end.
+    fn pick_recipe(&mut self, item_or_fluid: &ItemOrFluidId) -> Option<RecipeId> {
+        if let Some(pinned) = self.settings.pinned.get(item_or_fluid) {
+            return Some(*pinned);
+        }
+
+        let mut candidates: Vec<&Recipe> = self
+            .preset
+            .recipes_producing(item_or_fluid)
+            .filter(|r| self.research.recipe_lock(self.preset, r.id).is_none())
+            .filter(|r| self.settings.filter.allows(r.hidden, r.class))
+            .filter(|r| {
+                !r.ingredients
+                    .iter()
+                    .any(|i| &i.item_or_fluid == item_or_fluid)
+            })
+            .collect();
+        candidates.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let recipe = match &mut self.costs {
+            Some(model) => candidates
+                .iter()
+                .map(|r| {
+                    let cost = cost::recipe_cost(r, item_or_fluid, |i| model.cost(i));
+                    (r, cost.map_or(f32::MAX, |c| c.cost))
+                })
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(r, _)| r),
+            None => candidates
+                .iter()
+                .find(|r| r.name == item_or_fluid.name())
+                .or_else(|| {
+                    candidates.iter().find(|r| {
+                        r.results.first().map(|p| &p.item_or_fluid) == Some(item_or_fluid)
+                    })
+                })
+                .or(candidates.first()),
+        };
+        recipe.map(|r| r.id)
+    }
+
+    /// Place every node in a column by its distance to the demand node, which is the first node
+    fn layout(&mut self, position: Point2) {
+        let index: FxHashMap<NodeId, usize> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, n)| (n.id, i))
+            .collect();
+
+        // Longest path from the demand node, bounded so loops can't keep this going
+        let mut depths = vec![0usize; self.nodes.len()];
+        for _ in 0..self.nodes.len() {
+            let mut changed = false;
+            for connection in &self.connections {
+                let producer = index[&connection.src.node_id];
+                let consumer = index[&connection.dst.node_id];
+                if depths[producer] < depths[consumer] + 1 && depths[consumer] < self.nodes.len() {
+                    depths[producer] = depths[consumer] + 1;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        let mut rows: FxHashMap<usize, usize> = FxHashMap::default();
+        for (node, depth) in self.nodes.iter_mut().zip(depths) {
+            let row = rows.entry(depth).or_default();
+            node.position =
+                position + Vec2::new(-(depth as f32) * COLUMN_WIDTH, *row as f32 * ROW_HEIGHT);
+            *row += 1;
+        }
+    }
+}
@@ -0,0 +1,373 @@
+//! Estimating how expensive items are in terms of raw resources.
+//!
+//! The cost of an item is the optimum of an LP over every unlocked recipe that leads up to it,
+//! minimising the raw resources plus a little crafting time per unit. Byproducts are either used
+//! or thrown away, whichever is cheaper, and loops are balanced like in a real factory.
+
+use crate::{
+    ItemOrFluidId,
+    solver::simplex::{Problem, Relation, SolveError, Var},
+    state::{Preset, Recipe, ResearchState},
+};
+use itertools::Itertools;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::collections::VecDeque;
+
+/// Cost of a second of crafting time in the LP, so it prefers faster recipes when the raw cost is
+/// the same
+const CRAFTING_TIME_COST: f64 = 0.0001;
+
+/// The cost of a single unit of an item or fluid
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct RawCost {
+    /// Total raw resources needed
+    pub cost: f32,
+    /// Total crafting time in seconds at crafting speed 1
+    pub crafting_time: f32,
+}
+
+impl std::fmt::Display for RawCost {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.2} raw, {:.1}s", self.cost, self.crafting_time)
+    }
+}
+
+/// The exact amount of every raw resource that goes into a single unit of an item or fluid
+#[derive(Clone, Debug, Default)]
+pub struct RawBreakdown {
+    /// The sum of `resources`, and the crafting time of every recipe on the way
+    pub cost: RawCost,
+    /// Sorted by amount, the largest first
+    pub resources: Vec<(ItemOrFluidId, f32)>,
+}
+
+/// The unlocked recipes, with the LPs that have been solved so far
+pub struct CostModel<'a> {
+    recipes: Vec<&'a Recipe>,
+    /// Indices into `recipes` of the recipes that make each item
+    producers: FxHashMap<&'a ItemOrFluidId, Vec<usize>>,
+    mined: FxHashSet<&'a ItemOrFluidId>,
+    solved: FxHashMap<ItemOrFluidId, Option<RawBreakdown>>,
+}
+
+impl<'a> CostModel<'a> {
+    pub fn new(preset: &'a Preset, research: &ResearchState) -> Self {
+        let mut recipes: Vec<&Recipe> = preset
+            .recipes
+            .values()
+            .filter(|r| research.recipe_lock(preset, r.id).is_none())
+            .collect();
+        // The map has no order, but the LP should pick the same recipes every time
+        recipes.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut producers: FxHashMap<&ItemOrFluidId, Vec<usize>> = FxHashMap::default();
+        for (index, recipe) in recipes.iter().enumerate() {
+            for product in recipe.results.iter().map(|p| &p.item_or_fluid).unique() {
+                producers.entry(product).or_default().push(index);
+            }
+        }
+        let mined = preset
+            .resources
+            .values()
+            .flat_map(|r| r.results.iter().map(|p| &p.item_or_fluid))
+            .collect();
+
+        Self {
+            recipes,
+            producers,
+            mined,
+            solved: FxHashMap::default(),
+        }
+    }
+
+    /// Things that are mined, or that no unlocked recipe makes, like water. They cost 1.
+    fn is_raw(&self, item_or_fluid: &ItemOrFluidId) -> bool {
+        self.mined.contains(item_or_fluid) || !self.producers.contains_key(item_or_fluid)
+    }
+
+    /// The cheapest way to make a single unit of `target`, `None` if it can't be made from raw
+    /// resources
+    pub fn breakdown(&mut self, target: &ItemOrFluidId) -> Option<&RawBreakdown> {
+        if !self.solved.contains_key(target) {
+            let breakdown = self.solve(target);
+            self.solved.insert(target.clone(), breakdown);
+        }
+        self.solved[target].as_ref()
+    }
+
+    pub fn cost(&mut self, item_or_fluid: &ItemOrFluidId) -> Option<RawCost> {
+        self.breakdown(item_or_fluid).map(|b| b.cost)
+    }
+
+    /// Solve the LP over every unlocked recipe that `target` can be made with, directly or
+    /// through its ingredients. Recipes outside of that can't make it any cheaper.
+    fn solve(&self, target: &ItemOrFluidId) -> Option<RawBreakdown> {
+        if self.is_raw(target) {
+            return Some(RawBreakdown {
+                cost: RawCost {
+                    cost: 1.0,
+                    crafting_time: 0.0,
+                },
+                resources: vec![(target.clone(), 1.0)],
+            });
+        }
+
+        let mut items: Vec<&ItemOrFluidId> = Vec::new();
+        let mut seen: FxHashSet<&ItemOrFluidId> = FxHashSet::default();
+        let mut used_recipes: Vec<usize> = Vec::new();
+        let mut used: FxHashSet<usize> = FxHashSet::default();
+        let mut queue = VecDeque::from([target]);
+        seen.insert(target);
+        while let Some(item) = queue.pop_front() {
+            items.push(item);
+            if self.is_raw(item) {
+                continue;
+            }
+            for &index in &self.producers[item] {
+                if !used.insert(index) {
+                    continue;
+                }
+                used_recipes.push(index);
+                // Byproducts don't need a constraint, they can always be thrown away
+                for ingredient in &self.recipes[index].ingredients {
+                    if seen.insert(&ingredient.item_or_fluid) {
+                        queue.push_back(&ingredient.item_or_fluid);
+                    }
+                }
+            }
+        }
+
+        let mut problem = Problem::default();
+        let recipe_vars: Vec<Var> = used_recipes
+            .iter()
+            .map(|&i| problem.add_var(CRAFTING_TIME_COST * self.recipes[i].energy_required as f64))
+            .collect();
+        let supply_vars: FxHashMap<&ItemOrFluidId, Var> = items
+            .iter()
+            .filter(|i| self.is_raw(i))
+            .map(|i| (*i, problem.add_var(1.0)))
+            .collect();
+
+        for item in &items {
+            // net production + supply >= demand
+            let mut coefficients: Vec<(Var, f64)> = Vec::new();
+            for (&index, var) in used_recipes.iter().zip(&recipe_vars) {
+                let recipe = self.recipes[index];
+                let produced: f32 = recipe
+                    .results
+                    .iter()
+                    .filter(|p| &p.item_or_fluid == *item)
+                    .map(|p| p.amount)
+                    .sum();
+                let consumed: f32 = recipe
+                    .ingredients
+                    .iter()
+                    .filter(|i| &i.item_or_fluid == *item)
+                    .map(|i| i.amount)
+                    .sum();
+                if produced != consumed {
+                    coefficients.push((*var, (produced - consumed) as f64));
+                }
+            }
+            if let Some(supply) = supply_vars.get(item) {
+                coefficients.push((*supply, 1.0));
+            }
+            let demand = if *item == target { 1.0 } else { 0.0 };
+            problem.add_constraint(coefficients, Relation::GreaterOrEqual, demand);
+        }
+
+        let solution = match problem.solve() {
+            Ok(solution) => solution,
+            // Every recipe for it needs something that only it can make
+            Err(SolveError::Infeasible) => return None,
+            Err(e) => {
+                println!("Failed to solve raw cost of {target:?}: {e:?}");
+                return None;
+            }
+        };
+
+        let mut resources: Vec<(ItemOrFluidId, f32)> = supply_vars
+            .iter()
+            .map(|(item, var)| ((*item).clone(), solution.value(*var) as f32))
+            .filter(|(_, amount)| *amount > 1e-6)
+            .collect();
+        resources.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        let crafting_time = used_recipes
+            .iter()
+            .zip(&recipe_vars)
+            .map(|(&i, var)| self.recipes[i].energy_required * solution.value(*var) as f32)
+            .sum();
+        Some(RawBreakdown {
+            cost: RawCost {
+                cost: resources.iter().map(|(_, amount)| amount).sum(),
+                crafting_time,
+            },
+            resources,
+        })
+    }
+}
+
+/// The breakdown of every item and fluid that the unlocked recipes use or make, and of everything
+/// that is mined. Things that can't be made from raw resources are left out.
+pub fn raw_costs(
+    preset: &Preset,
+    research: &ResearchState,
+) -> FxHashMap<ItemOrFluidId, RawBreakdown> {
+    let mut model = CostModel::new(preset, research);
+    let all: FxHashSet<ItemOrFluidId> = model
+        .recipes
+        .iter()
+        .flat_map(|r| {
+            let ingredients = r.ingredients.iter().map(|i| &i.item_or_fluid);
+            ingredients.chain(r.results.iter().map(|p| &p.item_or_fluid))
+        })
+        .chain(model.mined.iter().copied())
+        .cloned()
+        .collect();
+    for item_or_fluid in &all {
+        model.breakdown(item_or_fluid);
+    }
+    model
+        .solved
+        .into_iter()
+        .filter_map(|(item_or_fluid, breakdown)| Some((item_or_fluid, breakdown?)))
+        .collect()
+}
+
+/// The raw cost of a single unit of `product` when it is made with `recipe`, if `cost_of` knows
+/// the cost of all ingredients. Byproducts are credited at their own cost, but not their crafting
+/// time.
+pub fn recipe_cost(
+    recipe: &Recipe,
+    product: &ItemOrFluidId,
+    mut cost_of: impl FnMut(&ItemOrFluidId) -> Option<RawCost>,
+) -> Option<RawCost> {
+    let amount: f32 = recipe
+        .results
+        .iter()
+        .filter(|p| &p.item_or_fluid == product)
+        .map(|p| p.amount)
+        .sum();
+    if amount <= 0.0 {
+        return None;
+    }
+
+    let mut total = RawCost {
+        cost: 0.0,
+        crafting_time: recipe.energy_required,
+    };
+    for ingredient in &recipe.ingredients {
+        let cost = cost_of(&ingredient.item_or_fluid)?;
+        total.cost += cost.cost * ingredient.amount;
+        total.crafting_time += cost.crafting_time * ingredient.amount;
+    }
+    for byproduct in recipe
+        .results
+        .iter()
+        .filter(|p| &p.item_or_fluid != product)
+    {
+        if let Some(cost) = cost_of(&byproduct.item_or_fluid) {
+            total.cost -= cost.cost * byproduct.amount;
+        }
+    }
+    Some(RawCost {
+        cost: total.cost.max(0.0) / amount,
+        crafting_time: total.crafting_time / amount,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+
+    fn recipe(id: u64, name: &str, ingredients: &[(&str, f32)], results: &[(&str, f32)]) -> Value {
+        let stacks = |stacks: &[(&str, f32)]| {
+            stacks
+                .iter()
+                .map(
+                    |(name, amount)| json!({ "item_or_fluid": { "Item": name }, "amount": amount }),
+                )
+                .collect::<Vec<_>>()
+        };
+        json!({
+            "id": id,
+            "name": name,
+            "category": "crafting",
+            "hidden": false,
+            "class": "Normal",
+            "energy_required": 1.0,
+            "enabled": true,
+            "ingredients": stacks(ingredients),
+            "results": stacks(results),
+        })
+    }
+
+    /// Ore is mined, and plates are made from it directly, or from ore with slag on the side that
+    /// is turned into more plates
+    fn preset() -> Preset {
+        let recipes = [
+            recipe(1, "plate", &[("ore", 1.0)], &[("plate", 1.0)]),
+            recipe(
+                2,
+                "refining",
+                &[("ore", 1.0)],
+                &[("plate", 1.0), ("slag", 1.0)],
+            ),
+            recipe(3, "slag-plate", &[("slag", 2.0)], &[("plate", 1.0)]),
+            recipe(4, "gear", &[("plate", 2.0)], &[("gear", 1.0)]),
+        ];
+        serde_json::from_value(json!({
+            "name": "test",
+            "groups": [],
+            "items": {},
+            "fluids": {},
+            "recipes": recipes
+                .into_iter()
+                .map(|r| (r["id"].to_string(), r))
+                .collect::<serde_json::Map<_, _>>(),
+            "crafting_machines": {},
+            "boilers": {},
+            "generators": {},
+            "reactors": {},
+            "resources": {
+                "ore": {
+                    "name": "ore",
+                    "category": "basic-solid",
+                    "mining_time": 1.0,
+                    "results": [{ "item_or_fluid": { "Item": "ore" }, "amount": 1.0 }],
+                    "required_fluid": null,
+                    "fluid_amount": 0.0,
+                },
+            },
+            "mining_drills": {},
+            "pumpable_fluids": [],
+            "technologies": {},
+            "labs": {},
+            "belts": [],
+            "inserters": [],
+            "pipe_throughput": null,
+            "cargo_wagon_slots": null,
+            "fluid_wagon_capacity": null,
+        }))
+        .unwrap()
+    }
+
+    fn item(name: &str) -> ItemOrFluidId {
+        ItemOrFluidId::Item(name.to_string())
+    }
+
+    #[test]
+    fn byproducts_are_used() {
+        let preset = preset();
+        let research = ResearchState::default();
+        let costs = raw_costs(&preset, &research);
+
+        // Refining gives 1.5 plates per ore once the slag is turned into plates too
+        let plate = &costs[&item("plate")];
+        assert!((plate.cost.cost - 2.0 / 3.0).abs() < 1e-4);
+        assert_eq!(plate.resources.len(), 1);
+        assert!((costs[&item("gear")].cost.cost - 4.0 / 3.0).abs() < 1e-4);
+        assert_eq!(costs[&item("ore")].cost.cost, 1.0);
+    }
+}
@@ -0,0 +1,49 @@
+//! Sub-factories, nodes that contain a graph of their own
+
+use crate::{Graph, InOutput, Node, SocketPos};
+
+/// An open socket of a sub-factory graph together with the socket inside that it stands for
+pub type Socket = (SocketPos, InOutput);
+
+/// The sockets of a sub-factory are the unconnected sockets of its graph, at their solved rates.
+/// Sockets that don't move anything yet are kept, so the sockets only change when the graph
+/// does.
+pub fn sockets(graph: &Graph) -> (Vec<Socket>, Vec<Socket>) {
+    // Sorted so the sockets keep their order when the graph is changed
+    let mut nodes: Vec<&Node> = graph.nodes.values().collect();
+    nodes.sort_by_key(|n| n.id);
+
+    let mut inputs = Vec::new();
+    let mut outputs = Vec::new();
+    for node in nodes {
+        for (input, sockets, open) in [
+            (true, &node.inputs, &mut inputs),
+            (false, &node.outputs, &mut outputs),
+        ] {
+            for (socket_index, socket) in sockets.iter().enumerate() {
+                let pos = SocketPos {
+                    node_id: node.id,
+                    socket_index,
+                    input,
+                };
+                if graph.connections.iter().any(|c| c.has_socket(pos)) {
+                    continue;
+                }
+                open.push((
+                    pos,
+                    InOutput::new(socket.item_or_fluid.clone(), socket.rate)
+                        .with_temperature(socket.temperature),
+                ));
+            }
+        }
+    }
+    (inputs, outputs)
+}
+
+/// Where each socket of a sub-factory with these sockets went in `sockets`, by the socket inside
+/// that it stands for. `None` for sockets that are gone.
+pub fn remap(old: &[SocketPos], sockets: &[Socket]) -> Vec<Option<usize>> {
+    old.iter()
+        .map(|pos| sockets.iter().position(|(new, _)| new == pos))
+        .collect()
+}
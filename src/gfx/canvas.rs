@@ -1,25 +1,52 @@
 use super::{Color, Paint};
-use crate::utils::Point2;
+use crate::utils::{Point2, Rectangle, Vec2};
 
 pub struct Canvas<'a> {
-    pub rect: sdl3::rect::Rect,
+    /// The world point at the top left of the window
+    pub offset: Point2,
+    /// UI pixels per world unit
+    pub zoom: f32,
     pub window_size: Point2,
     pub canvas: &'a mut sdl3::render::Canvas<sdl3::video::Window>,
 }
 impl Canvas<'_> {
+    pub const MIN_ZOOM: f32 = 0.1;
+    pub const MAX_ZOOM: f32 = 4.0;
+
     pub(crate) fn clear_color(&mut self, color: Color) {
         self.canvas.set_draw_color(color);
         self.canvas.clear();
     }
 
-    pub fn translate(&mut self, by: Point2) {
-        self.rect.set_x(self.rect.x() + by.x as i32);
+    /// Move the view by `by` UI pixels
+    pub fn translate(&mut self, by: Vec2) {
+        self.offset -= by * (1.0 / self.zoom);
     }
 
-    pub fn scale(&mut self, zoom: Point2) {}
+    /// Zoom by `factor`, keeping the world point under `ui` in place
+    pub fn zoom_at(&mut self, ui: Point2, factor: f32) {
+        let world = self.ui_to_world(ui);
+        self.zoom = (self.zoom * factor).clamp(Self::MIN_ZOOM, Self::MAX_ZOOM);
+        self.offset = Point2::new(world.x - ui.x / self.zoom, world.y - ui.y / self.zoom);
+    }
 
     pub fn ui_to_world(&self, ui: Point2) -> Point2 {
-        ui
+        Point2::new(
+            ui.x / self.zoom + self.offset.x,
+            ui.y / self.zoom + self.offset.y,
+        )
+    }
+
+    pub fn world_to_ui(&self, world: Point2) -> Point2 {
+        Point2::new(
+            (world.x - self.offset.x) * self.zoom,
+            (world.y - self.offset.y) * self.zoom,
+        )
+    }
+
+    pub fn world_rect_to_ui(&self, world: Rectangle) -> Rectangle {
+        let Point2 { x, y } = self.world_to_ui(Point2::new(world.x, world.y));
+        Rectangle::new(x, y, world.width * self.zoom, world.height * self.zoom)
     }
 
     pub fn draw_rounded(
@@ -29,6 +56,7 @@ impl Canvas<'_> {
         border_paint: Paint,
         radius: f64,
     ) {
+        let rectangle = self.world_rect_to_ui(rectangle);
         self.canvas.set_draw_color(bg_paint.color);
         self.canvas
             .fill_rect(sdl3::rect::Rect::new(
@@ -53,6 +53,18 @@ impl DrawUiCtx<'_, '_> {
             .unwrap();
     }
 
+    /// Draw an image darkened, for things that are unavailable
+    pub(crate) fn draw_image_greyed(&mut self, image_path: &str, rect: crate::utils::Rectangle) {
+        let texture = self.image_cache.get_texture(image_path);
+        let target = FRect::new(rect.x, rect.y, rect.width, rect.height);
+        texture.set_color_mod(0x60, 0x60, 0x60);
+        self.canvas
+            .canvas
+            .copy(texture, None, Some(target))
+            .unwrap();
+        texture.set_color_mod(0xFF, 0xFF, 0xFF);
+    }
+
     pub(crate) fn draw_progress_bar(
         &mut self,
         point: Point2,
@@ -94,6 +106,16 @@ impl DrawUiCtx<'_, '_> {
             .unwrap();
     }
 
+    pub(crate) fn draw_line(&mut self, from: Point2, to: Point2, paint: Paint) {
+        self.canvas.canvas.set_draw_color(paint.color);
+        self.canvas.canvas.draw_line(from, to).unwrap();
+    }
+
+    pub(crate) fn draw_border(&mut self, rect: crate::utils::Rectangle, paint: Paint) {
+        self.canvas.canvas.set_draw_color(paint.color);
+        self.canvas.canvas.draw_rect(rect.into()).unwrap();
+    }
+
     pub(crate) fn draw_fill_border(
         &mut self,
         rect: crate::utils::Rectangle,
@@ -1,6 +1,9 @@
 use super::{Canvas, Color, Paint, font_cache::FontCache, image_cache::ImageCache};
-use crate::utils::{Point2, Vec2};
-use sdl3::render::FPoint;
+use crate::{
+    ui::utils::bezier_point,
+    utils::{Point2, Rectangle, Vec2},
+};
+use sdl3::render::{FPoint, FRect};
 
 pub struct DrawWorldCtx<'a, 'b> {
     pub canvas: &'a mut Canvas<'b>,
@@ -10,7 +13,25 @@ pub struct DrawWorldCtx<'a, 'b> {
     pub world_mouse: Point2,
     pub window_size: Point2,
 }
+/// Everything is drawn in world coordinates, and moved and scaled by the camera of the canvas
 impl DrawWorldCtx<'_, '_> {
+    /// UI pixels per world unit
+    pub fn zoom(&self) -> f32 {
+        self.canvas.zoom
+    }
+
+    /// The part of the world that is in the window
+    pub fn visible_world(&self) -> Rectangle {
+        let top_left = self.canvas.ui_to_world(Point2::ZERO);
+        let bottom_right = self.canvas.ui_to_world(self.window_size);
+        Rectangle::new(
+            top_left.x,
+            top_left.y,
+            bottom_right.x - top_left.x,
+            bottom_right.y - top_left.y,
+        )
+    }
+
     pub(crate) fn draw_rounded(
         &mut self,
         rectangle: crate::utils::Rectangle,
@@ -22,10 +43,47 @@ impl DrawWorldCtx<'_, '_> {
             .draw_rounded(rectangle, bg_paint, border_paint, radius);
     }
 
+    /// The font size of `paint` is in world units, so text shrinks when zooming out
+    pub fn fill_text_centered(&mut self, rect: Rectangle, label: &str, paint: Paint) {
+        let rect = self.canvas.world_rect_to_ui(rect);
+        let font_size = paint.font_size.unwrap_or(14) as f32 * self.canvas.zoom;
+        let paint = paint.with_font_size(font_size.round().max(1.0) as u16);
+        let texture = self.font_cache.get_texture(label, paint);
+        let target = FRect::new(
+            rect.x + (rect.width - texture.width() as f32) / 2.0,
+            rect.y + (rect.height - texture.height() as f32) / 2.0,
+            texture.width() as f32,
+            texture.height() as f32,
+        );
+        self.canvas
+            .canvas
+            .copy(texture, None, Some(target))
+            .unwrap();
+    }
+
+    pub(crate) fn draw_image(&mut self, image_path: &str, rect: Rectangle) {
+        let rect = self.canvas.world_rect_to_ui(rect);
+        let texture = self.image_cache.get_texture(image_path);
+        self.canvas
+            .canvas
+            .copy(texture, None, Some(rect.into()))
+            .unwrap();
+    }
+
+    pub(crate) fn draw_line(&mut self, from: Point2, to: Point2, paint: Paint) {
+        let from = self.canvas.world_to_ui(from);
+        let to = self.canvas.world_to_ui(to);
+        self.canvas.canvas.set_draw_color(paint.color);
+        self.canvas.canvas.draw_line(from, to).unwrap();
+    }
+
     pub(crate) fn clear_color(&mut self, color: Color) {
         self.canvas.clear_color(color);
     }
 
+    /// The cubic curve with the control points `from + bezier_curve_factor_1` and
+    /// `to + bezier_curve_factor_2`, cut into short lines. It is the curve that `bezier_point`
+    /// samples, so labels and flow dots stay on it.
     pub(crate) fn draw_bezier(
         &mut self,
         line_color: &Paint,
@@ -34,18 +92,21 @@ impl DrawWorldCtx<'_, '_> {
         to: Point2,
         bezier_curve_factor_2: Option<Vec2>,
     ) {
+        const SEGMENTS: usize = 24;
+        let bezier_curve_factor_2 = bezier_curve_factor_2.unwrap_or(Vec2::ZERO);
+        let lines: Vec<FPoint> = (0..=SEGMENTS)
+            .map(|i| {
+                let point = bezier_point(
+                    from,
+                    bezier_curve_factor_1,
+                    to,
+                    bezier_curve_factor_2,
+                    i as f32 / SEGMENTS as f32,
+                );
+                self.canvas.world_to_ui(point).into()
+            })
+            .collect();
         self.canvas.canvas.set_draw_color(line_color.color);
-        let mut lines: [FPoint; 4] = [
-            from.into(),
-            (from + bezier_curve_factor_1).into(),
-            to.into(),
-            to.into(),
-        ];
-        let mut n = 3;
-        if let Some(bezier_curve_factor_2) = bezier_curve_factor_2 {
-            n = 4;
-            lines[2] = (to + bezier_curve_factor_2).into();
-        }
-        self.canvas.canvas.draw_lines(&lines[..n]).unwrap();
+        self.canvas.canvas.draw_lines(&lines[..]).unwrap();
     }
 }
@@ -18,18 +18,24 @@ impl EventCtx<'_, '_> {
     }
 
     pub fn zoom_at_mouse(&mut self, zoom: f32) {
-        let pt = self.world_mouse;
-        self.canvas.translate(pt);
-        self.canvas.scale(Point2::spread(1.0 + (zoom / 10.0)));
-        self.canvas.translate(-pt);
+        self.canvas
+            .zoom_at(self.ui_mouse, (1.0 + zoom / 10.0).max(0.5));
+        self.world_mouse = self.canvas.ui_to_world(self.ui_mouse);
         self.redraw = true;
     }
 
+    /// Move the view by `relative` UI pixels
     pub fn translate_by(&mut self, relative: Vec2) {
-        self.canvas.translate(relative.into());
+        self.canvas.translate(relative);
+        self.world_mouse = self.canvas.ui_to_world(self.ui_mouse);
         self.redraw = true;
     }
 
+    /// Convert a distance moved by the mouse to a distance in the world
+    pub fn ui_to_world_delta(&self, delta: Vec2) -> Vec2 {
+        delta * (1.0 / self.canvas.zoom)
+    }
+
     pub fn redraw(&mut self) {
         self.redraw = true;
     }
@@ -38,7 +38,7 @@ impl ImageCache {
         self.get_texture(&path);
     }
 
-    pub fn get_texture(&mut self, path: &str) -> &Texture {
+    pub fn get_texture(&mut self, path: &str) -> &mut Texture<'static> {
         self.textures_to_load.remove(path);
         self.textures.entry(path.to_string()).or_insert_with(|| {
             let mut png = png::Decoder::new(std::fs::File::open(path).unwrap())
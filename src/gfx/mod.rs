@@ -98,7 +98,8 @@ pub fn run(window: &mut Window, app: &mut crate::ui::app::App) {
     );
 
     let mut canvas = Canvas {
-        rect: window.canvas.viewport(),
+        offset: Point2::ZERO,
+        zoom: 1.0,
         window_size,
         canvas: &mut window.canvas,
     };
@@ -124,6 +125,7 @@ pub fn run(window: &mut Window, app: &mut crate::ui::app::App) {
                     keycode: Some(keycode),
                     ..
                 } => app.key_down(&mut event_ctx, keycode),
+                sdl3::event::Event::TextInput { text, .. } => app.text_input(&mut event_ctx, &text),
                 sdl3::event::Event::MouseMotion {
                     x, y, xrel, yrel, ..
                 } => {
@@ -141,22 +143,30 @@ pub fn run(window: &mut Window, app: &mut crate::ui::app::App) {
                     app.mouse_down(&mut event_ctx, mouse_btn);
                 }
                 sdl3::event::Event::MouseButtonUp {
-                    mouse_btn, x, y, ..
+                    mouse_btn,
+                    clicks,
+                    x,
+                    y,
+                    ..
                 } => {
                     mouse = Point2::new(x, y);
                     event_ctx.ui_mouse = mouse;
                     event_ctx.world_mouse = event_ctx.canvas.ui_to_world(mouse);
                     app.mouse_up(&mut event_ctx, mouse_btn);
+                    if clicks == 2 {
+                        app.mouse_double_click(&mut event_ctx, mouse_btn);
+                    }
                 }
                 sdl3::event::Event::MouseWheel { x, y, .. } => {
                     event_ctx.ui_mouse = mouse;
                     event_ctx.world_mouse = event_ctx.canvas.ui_to_world(mouse);
-                    event_ctx.zoom_at_mouse(x as f32);
+                    app.mouse_scroll(&mut event_ctx, Vec2::new(x, y));
                 }
                 _ => {}
             }
         }
 
+        let world_mouse = canvas.ui_to_world(mouse);
         let mut draw_ctx = DrawWorldCtx {
             canvas: &mut canvas,
             font_cache: &mut window.font_cache,
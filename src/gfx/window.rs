@@ -23,6 +23,7 @@ impl Window {
             .unwrap();
 
         let canvas = window.into_canvas();
+        video_subsystem.text_input().start(canvas.window());
         let font_cache = FontCache::init(Box::leak(Box::new(canvas.texture_creator())));
         let image_cache = ImageCache::init(Box::leak(Box::new(canvas.texture_creator())));
         Self {
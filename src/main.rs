@@ -1,12 +1,22 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use rustc_hash::FxHashMap;
 use serde_json::Value;
-use state::Preset;
+use state::{Preset, RecipeId, ResearchState};
 use std::io::Write;
 use utils::{Point2, Vec2};
 
+mod chain;
+mod cost;
 mod factorio;
+mod factory;
 mod gfx;
+mod nodes;
+mod reachability;
+mod science;
+mod solver;
 mod state;
+mod template;
+mod throughput;
 mod ui;
 mod utils;
 
@@ -19,6 +29,19 @@ struct Cli {
     /// Convert `preset/NAME/script-output/data-raw-dump.json` to `preset/NAME/preset.json`
     #[arg(long)]
     convert_data_raw_dump: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Report the items, fluids and recipes that can't be made from raw resources
+    Reachability {
+        /// File with the researched technologies, separated by commas or whitespace. Without it
+        /// everything counts as researched.
+        #[arg(long)]
+        research: Option<std::path::PathBuf>,
+    },
 }
 
 fn main() {
@@ -71,6 +94,22 @@ fn main() {
     }
 
     let preset = Preset::load(PRESET_NAME);
+    if let Some(Command::Reachability { research }) = &cli.command {
+        let mut state = ResearchState::default();
+        if let Some(path) = research {
+            let list = match std::fs::read_to_string(path) {
+                Ok(list) => list,
+                Err(e) => {
+                    eprintln!("Failed to read {}: {e}", path.display());
+                    std::process::exit(1);
+                }
+            };
+            state.import(&preset, &list);
+        }
+        reachability::analyze(&preset, &state).print(&preset);
+        return;
+    }
+
     let mut window = gfx::Window::new();
     for item in preset.items.values() {
         window
@@ -102,19 +141,56 @@ pub struct NodeId(pub usize);
 // #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 // struct FluidId(pub usize);
 
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
-enum ItemOrFluidId {
+#[derive(
+    Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub enum ItemOrFluidId {
     Item(String),
     Fluid(String),
+    /// Measured in MW
+    Electricity,
+    /// Measured in MW
+    Heat,
+}
+
+impl ItemOrFluidId {
+    /// Parses the `type` and `name` fields of recipe ingredients and results
+    pub fn from_type(ty: &str, name: &str) -> Self {
+        if ty == "fluid" {
+            ItemOrFluidId::Fluid(name.to_string())
+        } else {
+            ItemOrFluidId::Item(name.to_string())
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            ItemOrFluidId::Item(name) | ItemOrFluidId::Fluid(name) => name,
+            ItemOrFluidId::Electricity => "electricity",
+            ItemOrFluidId::Heat => "heat",
+        }
+    }
 }
 
 struct Node {
     id: NodeId,
     position: Point2,
+    kind: NodeKind,
 
     inputs: Vec<InOutput>,
     outputs: Vec<InOutput>,
     direction: Cardinal,
+    /// The amount of machines needed, as calculated by the solver
+    machines: f32,
+    /// The amount of machines that have to be built, `machines` rounded up when the solver
+    /// rounds to whole machines
+    built: f32,
+    constraints: NodeConstraints,
+    /// The solver couldn't honour the constraints, because they contradict each other or the
+    /// inputs can't be supplied
+    violated: bool,
+    /// Only for sub-factories. `None` while the sub-factory is opened.
+    factory: Option<Box<Graph>>,
 }
 impl Node {
     pub fn get_socket(&self, input: bool, socket_index: usize) -> &InOutput {
@@ -124,17 +200,227 @@ impl Node {
             &self.outputs[socket_index]
         }
     }
+
+    /// The fraction of the built machines that isn't needed
+    pub fn idle(&self) -> f32 {
+        if self.built > 1e-6 {
+            (1.0 - self.machines / self.built).max(0.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// The speed bonus from modules or beacons that would let one machine fewer than `built` do
+    /// the work exactly, with that amount of machines
+    pub fn speed_bonus_for_fewer_machines(&self) -> Option<(f32, f32)> {
+        let fewer = self.built - 1.0;
+        if fewer < 1.0 || self.idle() < 1e-4 {
+            return None;
+        }
+        Some((self.machines / fewer - 1.0, fewer))
+    }
+
+    /// Whether the node stands for machines that are built, which come in whole numbers
+    pub fn is_machine(&self) -> bool {
+        matches!(
+            self.kind,
+            NodeKind::Recipe { .. }
+                | NodeKind::Boiler { .. }
+                | NodeKind::Generator { .. }
+                | NodeKind::Reactor { .. }
+                | NodeKind::Resource { .. }
+        )
+    }
+
+    /// Nodes that have a fixed amount of machines, instead of one that is calculated by the solver
+    pub fn fixed_machines(&self) -> Option<f32> {
+        match self.kind {
+            NodeKind::PowerDemand | NodeKind::Demand | NodeKind::ScienceDemand { .. } => Some(1.0),
+            // Bus lanes are solved separately, notes don't do anything
+            NodeKind::Bus | NodeKind::Note { .. } => Some(0.0),
+            _ => None,
+        }
+    }
+}
+
+/// Limits that the user pinned on a node, which the solver balances the rest of the graph around
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct NodeConstraints {
+    /// An exact amount of machines
+    pub machines: Option<f32>,
+    /// An exact rate per second for the output with this index
+    pub output_rate: Option<(usize, f32)>,
+    /// The most that the input with this index may consume per second
+    pub max_input_rates: Vec<(usize, f32)>,
+    /// Only build whole machines
+    pub integer: bool,
+}
+
+impl NodeConstraints {
+    pub fn is_empty(&self) -> bool {
+        self.machines.is_none()
+            && self.output_rate.is_none()
+            && self.max_input_rates.is_empty()
+            && !self.integer
+    }
+
+    pub fn max_input_rate(&self, socket_index: usize) -> Option<f32> {
+        self.max_input_rates
+            .iter()
+            .find(|(index, _)| *index == socket_index)
+            .map(|(_, rate)| *rate)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum NodeKind {
+    Recipe {
+        recipe: RecipeId,
+        machine: Option<String>,
+    },
+    Boiler {
+        boiler: String,
+    },
+    Generator {
+        generator: String,
+        /// Of the fluid that is fed into the generator, colder fluid makes less power
+        temperature: f32,
+    },
+    Reactor {
+        reactor: String,
+    },
+    /// Mining drills on a resource patch
+    Resource {
+        resource: String,
+        drill: Option<String>,
+    },
+    /// A sink for electricity, the demand is the `per_machine` of its only input
+    PowerDemand,
+    /// A sink for the target of a generated chain, the demand is the `per_machine` of its input
+    Demand,
+    /// A sink for the science packs of a research goal, with the labs that consume them
+    ScienceDemand {
+        lab: Option<String>,
+        labs: f32,
+    },
+    /// A graph of its own, that acts like a recipe making one copy of the graph per machine
+    Factory {
+        name: String,
+    },
+    /// Supplies an item or fluid from outside the factory, the rate is its machines
+    Source,
+    /// Takes whatever is connected to its only input out of the factory
+    Sink,
+    /// Divides its input over its outputs, by the ratios
+    Splitter {
+        ratios: Vec<f32>,
+    },
+    /// Combines its inputs into its output, by the ratios
+    Merger {
+        ratios: Vec<f32>,
+    },
+    /// Carries many items on one line. Input `i` and output `i` are a lane, lanes carry their own
+    /// rate instead of sharing the node's machines.
+    Bus,
+    /// Free text, without sockets
+    Note {
+        text: String,
+    },
+}
+
+/// The nodes and connections of the factory, or of a sub-factory
+#[derive(Default)]
+pub struct Graph {
+    nodes: FxHashMap<NodeId, Node>,
+    connections: Vec<Connection>,
+}
+
+/// The temperatures that a fluid (or heat) socket accepts. Outputs always produce a single
+/// temperature, so `min == max` for those.
+#[derive(Copy, Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TemperatureRange {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl TemperatureRange {
+    pub fn exact(temperature: f32) -> Self {
+        Self {
+            min: temperature,
+            max: temperature,
+        }
+    }
+
+    pub fn new(min: Option<f32>, max: Option<f32>) -> Self {
+        Self {
+            min: min.unwrap_or(f32::NEG_INFINITY),
+            max: max.unwrap_or(f32::INFINITY),
+        }
+    }
+
+    pub fn overlaps(&self, other: &TemperatureRange) -> bool {
+        self.min <= other.max && other.min <= self.max
+    }
+}
+
+impl std::fmt::Display for TemperatureRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.min.is_finite(), self.max.is_finite()) {
+            _ if self.min == self.max => write!(f, "{}°C", self.min),
+            (true, true) => write!(f, "{}-{}°C", self.min, self.max),
+            (true, false) => write!(f, ">= {}°C", self.min),
+            (false, true) => write!(f, "<= {}°C", self.max),
+            (false, false) => write!(f, "any °C"),
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct InOutput {
-    #[allow(dead_code)]
     item_or_fluid: ItemOrFluidId,
+    /// `None` for things that don't have a temperature, or inputs that accept any temperature
+    temperature: Option<TemperatureRange>,
+    /// The rate per second of a single machine
+    per_machine: f32,
+    /// The rate per second of all machines in the node, as calculated by the solver
     #[allow(dead_code)]
     rate: f32,
+    /// Only for outputs: the surplus may be voided, so the solver doesn't try to avoid it
+    allow_void: bool,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+impl InOutput {
+    pub fn new(item_or_fluid: ItemOrFluidId, per_machine: f32) -> Self {
+        Self {
+            item_or_fluid,
+            temperature: None,
+            per_machine,
+            rate: 0.0,
+            allow_void: false,
+        }
+    }
+
+    pub fn with_temperature(self, temperature: impl Into<Option<TemperatureRange>>) -> Self {
+        Self {
+            temperature: temperature.into(),
+            ..self
+        }
+    }
+
+    /// Whether this output can feed into the given input. The same fluid at different
+    /// temperatures is a different flow, so the temperatures have to be compatible.
+    pub fn can_connect_to(&self, input: &InOutput) -> bool {
+        self.item_or_fluid == input.item_or_fluid
+            && match (&self.temperature, &input.temperature) {
+                (Some(output), Some(input)) => output.overlaps(input),
+                _ => true,
+            }
+    }
+}
+
+#[derive(
+    Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
 enum Cardinal {
     North,
     East,
@@ -145,6 +431,15 @@ enum Cardinal {
 pub const BEZIER_CURVE_FACTOR: f32 = 0.5;
 
 impl Cardinal {
+    pub fn rotate_clockwise(&self) -> Cardinal {
+        match self {
+            Cardinal::North => Cardinal::East,
+            Cardinal::East => Cardinal::South,
+            Cardinal::South => Cardinal::West,
+            Cardinal::West => Cardinal::North,
+        }
+    }
+
     pub fn line_direction(&self, is_input: bool, len: f32) -> Vec2 {
         match (self, is_input) {
             (Cardinal::North, true) => (0., -len),
@@ -195,10 +490,38 @@ impl From<(NodeId, usize, bool)> for SocketPos {
 }
 
 pub struct Connection {
+    /// Always an output socket
     pub src: SocketPos,
+    /// Always an input socket
     pub dst: SocketPos,
+    /// The rate per second flowing through this connection, as calculated by the solver
+    pub rate: f32,
+    /// The fraction of the source output's built capacity that nothing takes
+    pub idle: f32,
+    /// Carried by trains instead of belts or pipes
+    pub train: Option<throughput::Train>,
+    /// A fixed share of the output, relative to the other connections of the same output that
+    /// have a ratio. `None` lets the solver pick by demand.
+    pub ratio: Option<f32>,
+    /// When there isn't enough to go around, connections with a higher priority are served first
+    pub priority: i32,
+    /// Points that an orthogonally routed connection goes through, in order
+    pub waypoints: Vec<Point2>,
 }
 impl Connection {
+    pub fn new(src: SocketPos, dst: SocketPos) -> Self {
+        Self {
+            src,
+            dst,
+            rate: 0.0,
+            idle: 0.0,
+            train: None,
+            ratio: None,
+            priority: 0,
+            waypoints: Vec::new(),
+        }
+    }
+
     pub fn has_socket(&self, pos: SocketPos) -> bool {
         self.src == pos || self.dst == pos
     }
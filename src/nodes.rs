@@ -0,0 +1,444 @@
+//! Constructors for the different kinds of nodes, turning preset data into per-machine rates
+
+use crate::{
+    Cardinal, Graph, InOutput, ItemOrFluidId, Node, NodeConstraints, NodeId, NodeKind,
+    TemperatureRange, factory,
+    science::SciencePlan,
+    state::{
+        Boiler, EnergySource, Generator, MINING_PRODUCTIVITY_PER_LEVEL, Preset, Reactor, RecipeId,
+        Resource,
+    },
+    utils::Point2,
+};
+
+fn new_node(
+    id: NodeId,
+    position: Point2,
+    kind: NodeKind,
+    inputs: Vec<InOutput>,
+    outputs: Vec<InOutput>,
+) -> Node {
+    Node {
+        id,
+        position,
+        kind,
+        inputs,
+        outputs,
+        direction: Cardinal::West,
+        machines: 0.0,
+        built: 0.0,
+        constraints: NodeConstraints::default(),
+        violated: false,
+        factory: None,
+    }
+}
+
+/// A sub-factory, with the unconnected sockets of `graph` as its sockets
+pub fn factory_node(id: NodeId, position: Point2, name: String, graph: Graph) -> Node {
+    let (inputs, outputs) = factory::sockets(&graph);
+    let inputs = inputs.into_iter().map(|(_, socket)| socket).collect();
+    let outputs = outputs.into_iter().map(|(_, socket)| socket).collect();
+    let mut node = new_node(id, position, NodeKind::Factory { name }, inputs, outputs);
+    node.factory = Some(Box::new(graph));
+    node
+}
+
+pub fn recipe_node(preset: &Preset, id: NodeId, position: Point2, recipe_id: RecipeId) -> Node {
+    let recipe = &preset.recipes[&recipe_id];
+    let machine = preset.default_machine_for_category(&recipe.category);
+    let crafting_speed = machine.map(|m| m.crafting_speed).unwrap_or(1.0);
+    let crafts_per_second = crafting_speed / recipe.energy_required.max(f32::EPSILON);
+
+    new_node(
+        id,
+        position,
+        NodeKind::Recipe {
+            recipe: recipe_id,
+            machine: machine.map(|m| m.name.clone()),
+        },
+        recipe
+            .ingredients
+            .iter()
+            .map(|i| {
+                InOutput::new(i.item_or_fluid.clone(), i.amount * crafts_per_second)
+                    .with_temperature(i.temperature)
+            })
+            .collect(),
+        recipe
+            .results
+            .iter()
+            .map(|r| {
+                let temperature = match &r.item_or_fluid {
+                    ItemOrFluidId::Fluid(name) => r
+                        .temperature
+                        .or_else(|| preset.fluid_by_name(name).map(|f| f.default_temperature))
+                        .map(TemperatureRange::exact),
+                    _ => None,
+                };
+                InOutput::new(r.item_or_fluid.clone(), r.amount * crafts_per_second)
+                    .with_temperature(temperature)
+            })
+            .collect(),
+    )
+}
+
+/// `mining_productivity` is the researched mining productivity level, which adds to the output
+/// but not to the fluid that is consumed
+pub fn resource_node(
+    preset: &Preset,
+    id: NodeId,
+    position: Point2,
+    resource: &Resource,
+    mining_productivity: u32,
+) -> Node {
+    let drill = preset.default_drill_for_category(&resource.category);
+    let mining_speed = drill.map(|d| d.mining_speed).unwrap_or(1.0);
+    let operations_per_second = mining_speed / resource.mining_time.max(f32::EPSILON);
+    let productivity = 1.0 + mining_productivity as f32 * MINING_PRODUCTIVITY_PER_LEVEL;
+
+    let mut inputs = Vec::new();
+    if let Some(fluid) = &resource.required_fluid {
+        inputs.push(InOutput::new(
+            ItemOrFluidId::Fluid(fluid.clone()),
+            resource.fluid_amount * operations_per_second,
+        ));
+    }
+    let mut outputs = resource
+        .results
+        .iter()
+        .map(|r| {
+            InOutput::new(
+                r.item_or_fluid.clone(),
+                r.amount * operations_per_second * productivity,
+            )
+        })
+        .collect();
+    if let Some(drill) = drill {
+        add_energy_source(
+            preset,
+            &drill.energy_source,
+            drill.energy_usage,
+            &mut inputs,
+            &mut outputs,
+        );
+    }
+
+    new_node(
+        id,
+        position,
+        NodeKind::Resource {
+            resource: resource.name.clone(),
+            drill: drill.map(|d| d.name.clone()),
+        },
+        inputs,
+        outputs,
+    )
+}
+
+pub fn power_demand_node(id: NodeId, position: Point2, megawatts: f32) -> Node {
+    new_node(
+        id,
+        position,
+        NodeKind::PowerDemand,
+        vec![InOutput::new(ItemOrFluidId::Electricity, megawatts)],
+        Vec::new(),
+    )
+}
+
+pub fn demand_node(id: NodeId, position: Point2, item_or_fluid: ItemOrFluidId, rate: f32) -> Node {
+    new_node(
+        id,
+        position,
+        NodeKind::Demand,
+        vec![InOutput::new(item_or_fluid, rate)],
+        Vec::new(),
+    )
+}
+
+/// Supplies `item_or_fluid` from outside the factory, at a rate of one per machine
+pub fn source_node(
+    id: NodeId,
+    position: Point2,
+    item_or_fluid: ItemOrFluidId,
+    temperature: Option<TemperatureRange>,
+) -> Node {
+    new_node(
+        id,
+        position,
+        NodeKind::Source,
+        Vec::new(),
+        vec![InOutput::new(item_or_fluid, 1.0).with_temperature(temperature)],
+    )
+}
+
+/// Takes `item_or_fluid` out of the factory, at a rate of one per machine
+pub fn sink_node(
+    id: NodeId,
+    position: Point2,
+    item_or_fluid: ItemOrFluidId,
+    temperature: Option<TemperatureRange>,
+) -> Node {
+    new_node(
+        id,
+        position,
+        NodeKind::Sink,
+        vec![InOutput::new(item_or_fluid, 1.0).with_temperature(temperature)],
+        Vec::new(),
+    )
+}
+
+/// One machine passes one unit per second, divided over the outputs by `ratios`
+pub fn splitter_node(
+    id: NodeId,
+    position: Point2,
+    item_or_fluid: ItemOrFluidId,
+    temperature: Option<TemperatureRange>,
+    ratios: Vec<f32>,
+) -> Node {
+    let shares = ratio_sockets(&item_or_fluid, temperature, &ratios);
+    new_node(
+        id,
+        position,
+        NodeKind::Splitter { ratios },
+        vec![InOutput::new(item_or_fluid, 1.0).with_temperature(temperature)],
+        shares,
+    )
+}
+
+/// One machine passes one unit per second, taken from the inputs by `ratios`
+pub fn merger_node(
+    id: NodeId,
+    position: Point2,
+    item_or_fluid: ItemOrFluidId,
+    temperature: Option<TemperatureRange>,
+    ratios: Vec<f32>,
+) -> Node {
+    let shares = ratio_sockets(&item_or_fluid, temperature, &ratios);
+    new_node(
+        id,
+        position,
+        NodeKind::Merger { ratios },
+        shares,
+        vec![InOutput::new(item_or_fluid, 1.0).with_temperature(temperature)],
+    )
+}
+
+/// Splitters and mergers need at least two ratios, none of them negative and not all zero
+pub fn valid_ratios(ratios: &[f32]) -> bool {
+    ratios.len() >= 2
+        && ratios.iter().all(|r| r.is_finite() && *r >= 0.0)
+        && ratios.iter().sum::<f32>() > 0.0
+}
+
+fn ratio_sockets(
+    item_or_fluid: &ItemOrFluidId,
+    temperature: Option<TemperatureRange>,
+    ratios: &[f32],
+) -> Vec<InOutput> {
+    let total: f32 = ratios.iter().sum();
+    ratios
+        .iter()
+        .map(|ratio| {
+            InOutput::new(item_or_fluid.clone(), ratio / total.max(f32::EPSILON))
+                .with_temperature(temperature)
+        })
+        .collect()
+}
+
+/// A bus with an input and an output for every lane
+pub fn bus_node(id: NodeId, position: Point2, lanes: Vec<ItemOrFluidId>) -> Node {
+    new_node(
+        id,
+        position,
+        NodeKind::Bus,
+        lanes
+            .iter()
+            .map(|lane| InOutput::new(lane.clone(), 1.0))
+            .collect(),
+        lanes
+            .into_iter()
+            .map(|lane| InOutput::new(lane, 1.0))
+            .collect(),
+    )
+}
+
+pub fn note_node(id: NodeId, position: Point2, text: String) -> Node {
+    new_node(
+        id,
+        position,
+        NodeKind::Note { text },
+        Vec::new(),
+        Vec::new(),
+    )
+}
+
+pub fn science_demand_node(id: NodeId, position: Point2, plan: &SciencePlan) -> Node {
+    new_node(
+        id,
+        position,
+        NodeKind::ScienceDemand {
+            lab: plan.lab.clone(),
+            labs: plan.labs,
+        },
+        plan.packs_per_second()
+            .map(|(pack, rate)| InOutput::new(ItemOrFluidId::Item(pack.to_string()), rate))
+            .collect(),
+        Vec::new(),
+    )
+}
+
+pub fn boiler_node(preset: &Preset, id: NodeId, position: Point2, boiler: &Boiler) -> Node {
+    let (heat_capacity, input_temperature) = preset
+        .fluid_by_name(&boiler.input_fluid)
+        .map(|f| (f.heat_capacity, f.default_temperature))
+        .unwrap_or((1000.0, 15.0));
+    let fluid_per_second = boiler.energy_consumption * 1_000_000.0
+        / ((boiler.target_temperature - input_temperature).max(1.0) * heat_capacity);
+
+    let mut inputs = vec![InOutput::new(
+        ItemOrFluidId::Fluid(boiler.input_fluid.clone()),
+        fluid_per_second,
+    )];
+    let mut outputs = vec![
+        InOutput::new(
+            ItemOrFluidId::Fluid(boiler.output_fluid.clone()),
+            fluid_per_second,
+        )
+        .with_temperature(TemperatureRange::exact(boiler.target_temperature)),
+    ];
+    add_energy_source(
+        preset,
+        &boiler.energy_source,
+        boiler.energy_consumption,
+        &mut inputs,
+        &mut outputs,
+    );
+
+    new_node(
+        id,
+        position,
+        NodeKind::Boiler {
+            boiler: boiler.name.clone(),
+        },
+        inputs,
+        outputs,
+    )
+}
+
+/// `temperature` is the temperature of the fluid that is fed into the generator
+pub fn generator_node(
+    preset: &Preset,
+    id: NodeId,
+    position: Point2,
+    generator: &Generator,
+    temperature: f32,
+) -> Node {
+    let (heat_capacity, default_temperature) = preset
+        .fluid_by_name(&generator.input_fluid)
+        .map(|f| (f.heat_capacity, f.default_temperature))
+        .unwrap_or((1000.0, 15.0));
+    let temperature = temperature.min(generator.maximum_temperature);
+    let energy_per_fluid =
+        (temperature - default_temperature).max(0.0) * heat_capacity * generator.effectivity
+            / 1_000_000.0;
+
+    let mut fluid_usage = generator.fluid_usage;
+    let mut megawatts = fluid_usage * energy_per_fluid;
+    if let Some(max) = generator.max_power_output
+        && megawatts > max
+    {
+        megawatts = max;
+        fluid_usage = max / energy_per_fluid;
+    }
+
+    new_node(
+        id,
+        position,
+        NodeKind::Generator {
+            generator: generator.name.clone(),
+            temperature,
+        },
+        vec![
+            InOutput::new(
+                ItemOrFluidId::Fluid(generator.input_fluid.clone()),
+                fluid_usage,
+            )
+            .with_temperature(TemperatureRange::new(
+                Some(generator.minimum_temperature),
+                (generator.maximum_temperature < f32::MAX).then_some(generator.maximum_temperature),
+            )),
+        ],
+        vec![InOutput::new(ItemOrFluidId::Electricity, megawatts)],
+    )
+}
+
+pub fn reactor_node(preset: &Preset, id: NodeId, position: Point2, reactor: &Reactor) -> Node {
+    let mut inputs = Vec::new();
+    let mut outputs = vec![
+        InOutput::new(ItemOrFluidId::Heat, reactor.consumption)
+            .with_temperature(TemperatureRange::exact(reactor.max_temperature)),
+    ];
+    add_energy_source(
+        preset,
+        &reactor.energy_source,
+        reactor.consumption,
+        &mut inputs,
+        &mut outputs,
+    );
+
+    new_node(
+        id,
+        position,
+        NodeKind::Reactor {
+            reactor: reactor.name.clone(),
+        },
+        inputs,
+        outputs,
+    )
+}
+
+/// Add the sockets needed to power an entity that consumes `megawatts`
+fn add_energy_source(
+    preset: &Preset,
+    source: &EnergySource,
+    megawatts: f32,
+    inputs: &mut Vec<InOutput>,
+    outputs: &mut Vec<InOutput>,
+) {
+    match source {
+        EnergySource::Burner {
+            fuel_categories,
+            effectivity,
+        } => {
+            let Some(fuel) = preset.default_fuel(fuel_categories) else {
+                println!("No fuel found for categories {fuel_categories:?}");
+                return;
+            };
+            let fuel_per_second = megawatts / effectivity / fuel.fuel_value;
+            inputs.push(InOutput::new(
+                ItemOrFluidId::Item(fuel.name.clone()),
+                fuel_per_second,
+            ));
+            if let Some(burnt_result) = &fuel.burnt_result {
+                outputs.push(InOutput::new(
+                    ItemOrFluidId::Item(burnt_result.clone()),
+                    fuel_per_second,
+                ));
+            }
+        }
+        EnergySource::Heat {
+            max_temperature,
+            min_working_temperature,
+        } => {
+            inputs.push(
+                InOutput::new(ItemOrFluidId::Heat, megawatts).with_temperature(
+                    TemperatureRange::new(Some(*min_working_temperature), Some(*max_temperature)),
+                ),
+            );
+        }
+        EnergySource::Electric => {
+            inputs.push(InOutput::new(ItemOrFluidId::Electricity, megawatts));
+        }
+        EnergySource::Other => {}
+    }
+}
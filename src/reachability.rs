@@ -0,0 +1,392 @@
+//! Finding out what can actually be made from raw resources, and what is broken
+
+use crate::{
+    ItemOrFluidId,
+    state::{Preset, RecipeId, ResearchState},
+};
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::collections::VecDeque;
+
+/// Anything that turns inputs into outputs: recipes, but also mining and boilers
+struct Process {
+    recipe: Option<RecipeId>,
+    inputs: Vec<ItemOrFluidId>,
+    outputs: Vec<ItemOrFluidId>,
+}
+
+/// A loop of recipes that can't be started from raw resources, but can run once it is seeded
+#[derive(Clone, Debug)]
+pub struct SeedCycle {
+    /// Sorted by name
+    pub recipes: Vec<RecipeId>,
+    /// The items and fluids that have to be brought in to start the loop
+    pub seeds: Vec<ItemOrFluidId>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Reachability {
+    pub items: FxHashSet<ItemOrFluidId>,
+    pub recipes: FxHashSet<RecipeId>,
+    /// Unlocked recipes that can never run, with the ingredients that can't be made
+    pub dead_recipes: FxHashMap<RecipeId, Vec<ItemOrFluidId>>,
+    /// Items and fluids that are used by a recipe, but that nothing produces
+    pub sourceless: Vec<ItemOrFluidId>,
+    pub seed_cycles: Vec<SeedCycle>,
+}
+
+/// Flood forward from what can be mined or pumped through every unlocked recipe
+pub fn analyze(preset: &Preset, research: &ResearchState) -> Reachability {
+    let processes = processes(preset, research);
+
+    let mut missing: Vec<usize> = processes.iter().map(|p| p.inputs.len()).collect();
+    let mut consumers: FxHashMap<&ItemOrFluidId, Vec<usize>> = FxHashMap::default();
+    for (index, process) in processes.iter().enumerate() {
+        for input in &process.inputs {
+            consumers.entry(input).or_default().push(index);
+        }
+    }
+
+    let mut items: FxHashSet<ItemOrFluidId> = FxHashSet::default();
+    let mut queue: VecDeque<usize> = (0..processes.len()).filter(|&i| missing[i] == 0).collect();
+    let mut reached = vec![false; processes.len()];
+    while let Some(index) = queue.pop_front() {
+        if std::mem::replace(&mut reached[index], true) {
+            continue;
+        }
+        for output in &processes[index].outputs {
+            if !items.insert(output.clone()) {
+                continue;
+            }
+            for &consumer in consumers.get(output).into_iter().flatten() {
+                missing[consumer] -= 1;
+                if missing[consumer] == 0 {
+                    queue.push_back(consumer);
+                }
+            }
+        }
+    }
+
+    let produced: FxHashSet<&ItemOrFluidId> = processes.iter().flat_map(|p| &p.outputs).collect();
+    let mut sourceless: Vec<ItemOrFluidId> = consumers
+        .keys()
+        .filter(|i| !produced.contains(*i))
+        .map(|i| (*i).clone())
+        .collect();
+    sourceless.sort();
+
+    let dead_recipes = processes
+        .iter()
+        .zip(&reached)
+        .filter(|(_, reached)| !**reached)
+        .filter_map(|(process, _)| {
+            let missing = process
+                .inputs
+                .iter()
+                .filter(|i| !items.contains(*i))
+                .cloned()
+                .collect();
+            Some((process.recipe?, missing))
+        })
+        .collect();
+
+    let seed_cycles = seed_cycles(preset, &processes, &reached, &items);
+    Reachability {
+        recipes: processes
+            .iter()
+            .zip(&reached)
+            .filter(|(_, reached)| **reached)
+            .filter_map(|(p, _)| p.recipe)
+            .collect(),
+        items,
+        dead_recipes,
+        sourceless,
+        seed_cycles,
+    }
+}
+
+fn processes(preset: &Preset, research: &ResearchState) -> Vec<Process> {
+    let mut processes: Vec<Process> = preset
+        .pumpable_fluids
+        .iter()
+        .map(|fluid| Process {
+            recipe: None,
+            inputs: Vec::new(),
+            outputs: vec![ItemOrFluidId::Fluid(fluid.clone())],
+        })
+        .collect();
+    for resource in preset.resources.values() {
+        processes.push(Process {
+            recipe: None,
+            inputs: resource
+                .required_fluid
+                .iter()
+                .map(|f| ItemOrFluidId::Fluid(f.clone()))
+                .collect(),
+            outputs: resource
+                .results
+                .iter()
+                .map(|p| p.item_or_fluid.clone())
+                .collect(),
+        });
+    }
+    for boiler in preset.boilers.values() {
+        processes.push(Process {
+            recipe: None,
+            inputs: vec![ItemOrFluidId::Fluid(boiler.input_fluid.clone())],
+            outputs: vec![ItemOrFluidId::Fluid(boiler.output_fluid.clone())],
+        });
+    }
+    for recipe in preset.recipes.values() {
+        if research.recipe_lock(preset, recipe.id).is_some() {
+            continue;
+        }
+        let mut inputs: Vec<ItemOrFluidId> = recipe
+            .ingredients
+            .iter()
+            .map(|i| i.item_or_fluid.clone())
+            .collect();
+        inputs.sort();
+        inputs.dedup();
+        processes.push(Process {
+            recipe: Some(recipe.id),
+            inputs,
+            outputs: recipe
+                .results
+                .iter()
+                .map(|p| p.item_or_fluid.clone())
+                .collect(),
+        });
+    }
+    processes
+}
+
+/// Find the loops among the unreachable recipes that only need the loop itself to run. These are
+/// the strongly connected components of the graph of unreachable items and recipes, where every
+/// recipe only misses ingredients from within the component.
+fn seed_cycles(
+    preset: &Preset,
+    processes: &[Process],
+    reached: &[bool],
+    items: &FxHashSet<ItemOrFluidId>,
+) -> Vec<SeedCycle> {
+    // Graph nodes are the unreachable processes, followed by the unreachable items
+    let process_nodes: Vec<usize> = (0..processes.len()).filter(|&i| !reached[i]).collect();
+    let mut item_nodes: FxHashMap<&ItemOrFluidId, usize> = FxHashMap::default();
+    for &index in &process_nodes {
+        let process = &processes[index];
+        for item in process.inputs.iter().chain(&process.outputs) {
+            if !items.contains(item) {
+                let next = process_nodes.len() + item_nodes.len();
+                item_nodes.entry(item).or_insert(next);
+            }
+        }
+    }
+    let node_count = process_nodes.len() + item_nodes.len();
+
+    let mut edges: Vec<Vec<usize>> = vec![Vec::new(); node_count];
+    for (node, &index) in process_nodes.iter().enumerate() {
+        let process = &processes[index];
+        for input in &process.inputs {
+            if let Some(&item) = item_nodes.get(input) {
+                edges[item].push(node);
+            }
+        }
+        for output in &process.outputs {
+            if let Some(&item) = item_nodes.get(output) {
+                edges[node].push(item);
+            }
+        }
+    }
+
+    let mut cycles = Vec::new();
+    for component in strongly_connected_components(&edges) {
+        if component.len() < 2 {
+            continue;
+        }
+        let members: FxHashSet<usize> = component.iter().copied().collect();
+        let component_items: FxHashSet<&ItemOrFluidId> = item_nodes
+            .iter()
+            .filter(|(_, node)| members.contains(node))
+            .map(|(item, _)| *item)
+            .collect();
+        let component_processes: Vec<&Process> = component
+            .iter()
+            .filter(|&&node| node < process_nodes.len())
+            .map(|&node| &processes[process_nodes[node]])
+            .collect();
+
+        let self_sufficient = component_processes.iter().all(|p| {
+            p.inputs
+                .iter()
+                .all(|i| items.contains(i) || component_items.contains(i))
+        });
+        if !self_sufficient {
+            continue;
+        }
+
+        let mut recipes: Vec<RecipeId> = component_processes
+            .iter()
+            .filter_map(|p| p.recipe)
+            .collect();
+        recipes.sort_by(|a, b| preset.recipes[a].name.cmp(&preset.recipes[b].name));
+        let mut seeds: Vec<ItemOrFluidId> = component_items
+            .into_iter()
+            .filter(|i| component_processes.iter().any(|p| p.inputs.contains(i)))
+            .cloned()
+            .collect();
+        seeds.sort();
+        cycles.push(SeedCycle { recipes, seeds });
+    }
+    cycles
+}
+
+/// Kosaraju's algorithm, with explicit stacks so large mod packs can't overflow the call stack
+fn strongly_connected_components(edges: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let mut order = Vec::with_capacity(edges.len());
+    let mut visited = vec![false; edges.len()];
+    for start in 0..edges.len() {
+        if visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        let mut stack = vec![(start, 0)];
+        while let Some((node, edge)) = stack.pop() {
+            if let Some(&next) = edges[node].get(edge) {
+                stack.push((node, edge + 1));
+                if !visited[next] {
+                    visited[next] = true;
+                    stack.push((next, 0));
+                }
+            } else {
+                order.push(node);
+            }
+        }
+    }
+
+    let mut reverse: Vec<Vec<usize>> = vec![Vec::new(); edges.len()];
+    for (node, targets) in edges.iter().enumerate() {
+        for &target in targets {
+            reverse[target].push(node);
+        }
+    }
+
+    let mut assigned = vec![false; edges.len()];
+    let mut components = Vec::new();
+    for &start in order.iter().rev() {
+        if assigned[start] {
+            continue;
+        }
+        assigned[start] = true;
+        let mut component = Vec::new();
+        let mut stack = vec![start];
+        while let Some(node) = stack.pop() {
+            component.push(node);
+            for &next in &reverse[node] {
+                if !assigned[next] {
+                    assigned[next] = true;
+                    stack.push(next);
+                }
+            }
+        }
+        components.push(component);
+    }
+    components
+}
+
+impl Reachability {
+    /// Why the item or fluid can't be made, if it can't
+    pub fn item_problem(&self, item_or_fluid: &ItemOrFluidId) -> Option<String> {
+        if self.items.contains(item_or_fluid) {
+            return None;
+        }
+        match self
+            .seed_cycles
+            .iter()
+            .find(|c| c.seeds.contains(item_or_fluid))
+        {
+            Some(_) => Some("needs a seed".to_string()),
+            None => Some("unreachable".to_string()),
+        }
+    }
+
+    /// Why the unlocked recipe can't run, if it can't
+    pub fn recipe_problem(&self, recipe: RecipeId) -> Option<String> {
+        let missing = self.dead_recipes.get(&recipe)?;
+        if let Some(cycle) = self
+            .seed_cycles
+            .iter()
+            .find(|c| c.recipes.contains(&recipe))
+        {
+            return Some(format!("needs a seed of {}", names(&cycle.seeds)));
+        }
+        Some(format!("missing {}", names(missing)))
+    }
+
+    /// Print a report of everything that can't be made
+    pub fn print(&self, preset: &Preset) {
+        let mut all: Vec<ItemOrFluidId> = preset
+            .items
+            .values()
+            .map(|i| ItemOrFluidId::Item(i.name.clone()))
+            .chain(
+                preset
+                    .fluids
+                    .values()
+                    .map(|f| ItemOrFluidId::Fluid(f.name.clone())),
+            )
+            .collect();
+        all.sort();
+        let unreachable: Vec<&ItemOrFluidId> =
+            all.iter().filter(|i| !self.items.contains(*i)).collect();
+
+        println!(
+            "{} of {} items and fluids are reachable, {} recipes can run",
+            all.len() - unreachable.len(),
+            all.len(),
+            self.recipes.len()
+        );
+
+        println!("Unreachable items and fluids ({}):", unreachable.len());
+        for item in unreachable {
+            println!("  {}", item.name());
+        }
+
+        println!("Used but never produced ({}):", self.sourceless.len());
+        for item in &self.sourceless {
+            println!("  {}", item.name());
+        }
+
+        let mut dead: Vec<(&str, &Vec<ItemOrFluidId>)> = self
+            .dead_recipes
+            .iter()
+            .map(|(id, missing)| (preset.recipes[id].name.as_str(), missing))
+            .collect();
+        dead.sort();
+        println!("Unlocked recipes that can't run ({}):", dead.len());
+        for (name, missing) in dead {
+            println!("  {name}: missing {}", names(missing));
+        }
+
+        println!("Loops that need a seed ({}):", self.seed_cycles.len());
+        for cycle in &self.seed_cycles {
+            let recipes: Vec<&str> = cycle
+                .recipes
+                .iter()
+                .map(|r| preset.recipes[r].name.as_str())
+                .collect();
+            println!(
+                "  {}: seed with {}",
+                recipes.join(", "),
+                names(&cycle.seeds)
+            );
+        }
+    }
+}
+
+fn names(items: &[ItemOrFluidId]) -> String {
+    items
+        .iter()
+        .map(|i| i.name())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
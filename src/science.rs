@@ -0,0 +1,111 @@
+//! Planning the science production that is needed to reach a research goal
+
+use crate::state::{MaxLevel, Preset, ResearchState};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+#[derive(Clone, Debug)]
+pub struct SciencePlan {
+    /// Every technology that still has to be researched, sorted by name
+    pub technologies: Vec<String>,
+    /// Technologies that are researched by a trigger instead of science packs
+    pub trigger_technologies: Vec<String>,
+    /// Total science packs needed, sorted by name
+    pub packs: Vec<(String, f32)>,
+    /// The deadline in seconds
+    pub deadline: f32,
+    pub lab: Option<String>,
+    /// The amount of labs that are needed to finish before the deadline
+    pub labs: f32,
+}
+
+impl SciencePlan {
+    /// The science packs that have to be produced per second to finish before the deadline
+    pub fn packs_per_second(&self) -> impl Iterator<Item = (&str, f32)> {
+        self.packs
+            .iter()
+            .map(|(name, amount)| (name.as_str(), amount / self.deadline))
+    }
+}
+
+/// Plan the research of `targets` within `deadline` seconds. Targets are technology names,
+/// optionally followed by `:LEVEL` to research an infinite technology up to that level.
+pub fn plan(
+    preset: &Preset,
+    research: &ResearchState,
+    targets: &[&str],
+    deadline: f32,
+) -> SciencePlan {
+    // Without a declared research state we plan from scratch
+    let is_researched =
+        |technology: &str| !research.is_everything() && research.is_researched(technology);
+
+    // The highest level to research for each infinite technology
+    let mut target_levels: FxHashMap<String, u32> = FxHashMap::default();
+    let mut needed: FxHashSet<String> = FxHashSet::default();
+    for target in targets {
+        let (name, level) = match target.split_once(':') {
+            Some((name, level)) => (name, level.parse().ok()),
+            None => (*target, None),
+        };
+        let Some(technology) = preset.technologies.get(name) else {
+            println!("Unknown technology {name:?}");
+            continue;
+        };
+        if let Some(level) = level {
+            target_levels.insert(name.to_string(), level);
+        }
+        needed.insert(technology.name.clone());
+        needed.extend(preset.prerequisite_closure(name));
+    }
+    needed.retain(|t| !is_researched(t));
+
+    let mut technologies: Vec<String> = needed.into_iter().collect();
+    technologies.sort();
+
+    let mut trigger_technologies = Vec::new();
+    let mut packs: FxHashMap<String, f32> = FxHashMap::default();
+    // Research time in seconds at researching speed 1
+    let mut research_time = 0.0;
+    for name in &technologies {
+        let technology = &preset.technologies[name];
+        let Some(cost) = &technology.cost else {
+            trigger_technologies.push(name.clone());
+            continue;
+        };
+        let first_level = technology.level();
+        let last_level = match (technology.max_level, target_levels.get(name)) {
+            (MaxLevel::Infinite, Some(level)) => (*level).max(first_level),
+            (MaxLevel::Level(max), Some(level)) => (*level).min(max).max(first_level),
+            _ => first_level,
+        };
+        for level in first_level..=last_level {
+            let Some(count) = cost.count(level) else {
+                continue;
+            };
+            research_time += count * cost.time;
+            for (pack, amount) in &cost.ingredients {
+                *packs.entry(pack.clone()).or_default() += count * amount;
+            }
+        }
+    }
+
+    let mut packs: Vec<(String, f32)> = packs.into_iter().collect();
+    packs.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let pack_names: Vec<&str> = packs.iter().map(|(name, _)| name.as_str()).collect();
+    let lab = preset.default_lab(&pack_names);
+    if lab.is_none() && !packs.is_empty() {
+        println!("No lab accepts all of {pack_names:?}");
+    }
+    let researching_speed = lab.map(|l| l.researching_speed).unwrap_or(1.0);
+    let deadline = deadline.max(1.0);
+
+    SciencePlan {
+        technologies,
+        trigger_technologies,
+        packs,
+        deadline,
+        lab: lab.map(|l| l.name.clone()),
+        labs: research_time / researching_speed / deadline,
+    }
+}
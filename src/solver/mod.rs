@@ -0,0 +1,375 @@
+pub mod simplex;
+
+use crate::{Connection, Node, NodeId, NodeKind, SocketPos};
+use rustc_hash::{FxHashMap, FxHashSet};
+use simplex::{Problem, Relation, Solution, SolveError, Var};
+
+/// Cost of a connected input that doesn't get enough supply. This should be the most expensive
+/// thing in the factory, so the solver builds more machines instead.
+const DEFICIT_COST: f64 = 1_000.0;
+/// Cost of a connected output that produces more than is taken from it, unless the output is
+/// allowed to void its surplus
+const SURPLUS_COST: f64 = 1.0;
+/// Cost of building a single machine, so the solver doesn't overbuild
+const MACHINE_COST: f64 = 0.001;
+/// Cost of every unit that flows through a connection, for every priority level it is below the
+/// highest one. Small enough to never cause a deficit or surplus, but it decides which connections
+/// go short when there isn't enough.
+const PRIORITY_COST: f64 = 0.01;
+/// Cost of breaking a constraint that the user put on a node. This only happens when the
+/// constraints contradict each other, so it's more expensive than anything else.
+const VIOLATION_COST: f64 = 100_000.0;
+
+/// How the solver deals with fractional machine counts
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum MachineRounding {
+    /// Build exactly as many machines as needed, including partial ones
+    #[default]
+    Exact,
+    /// Solve exactly, then build every node's machines rounded up
+    RoundUp,
+    /// Solve with a whole number of built machines per node, which may move work between nodes
+    Integer,
+}
+
+impl MachineRounding {
+    pub fn next(self) -> Self {
+        match self {
+            MachineRounding::Exact => MachineRounding::RoundUp,
+            MachineRounding::RoundUp => MachineRounding::Integer,
+            MachineRounding::Integer => MachineRounding::Exact,
+        }
+    }
+}
+
+/// When the last solve didn't give what was asked for
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SolveWarning {
+    /// The search for whole machine counts was cut short, there may be a way with fewer machines
+    NotFewestMachines,
+    /// No whole machine counts were found in time, so the exact counts are rounded up instead
+    RoundedUp,
+    /// The factory couldn't be solved at all, and everything is left at zero
+    Failed(SolveError),
+}
+
+impl std::fmt::Display for SolveWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SolveWarning::NotFewestMachines => {
+                write!(f, "Whole machine counts may not be the fewest possible")
+            }
+            SolveWarning::RoundedUp => {
+                write!(
+                    f,
+                    "No whole machine counts found in time, rounded up instead"
+                )
+            }
+            SolveWarning::Failed(error) => write!(f, "Failed to solve the factory: {error:?}"),
+        }
+    }
+}
+
+/// Calculate the amount of machines in every node, and the rates of every socket and connection.
+///
+/// Every connected input has to be supplied by its connections, every connected output has to be
+/// able to supply its connections. The flow of an output is split over its connections by
+/// demand, or by their ratios, and by priority when there isn't enough. Unconnected sockets are
+/// assumed to be supplied from, or drained to, outside the factory.
+///
+/// `locked` nodes use recipes that aren't researched yet, and don't get any machines. The
+/// constraints of other nodes are honoured where possible, nodes where they can't be are marked as
+/// `violated`.
+///
+/// Machines that are built but not needed, because of `rounding`, are reported as the idle
+/// fraction of every node and connection. Only nodes that stand for real machines are rounded.
+pub fn solve(
+    nodes: &mut FxHashMap<NodeId, Node>,
+    connections: &mut [Connection],
+    locked: &FxHashSet<NodeId>,
+    rounding: MachineRounding,
+) -> Option<SolveWarning> {
+    let mut problem = Problem::default();
+
+    let machine_vars: FxHashMap<NodeId, Var> = nodes
+        .keys()
+        .map(|id| (*id, problem.add_var(MACHINE_COST)))
+        .collect();
+    let max_priority = connections.iter().map(|c| c.priority).max().unwrap_or(0);
+    let flow_vars: Vec<Var> = connections
+        .iter()
+        .map(|c| problem.add_var(PRIORITY_COST * (max_priority - c.priority) as f64))
+        .collect();
+    add_ratio_constraints(&mut problem, connections, &flow_vars);
+    // Bus lanes carry their own rate, the input and output of a lane share a var
+    let lane_vars: FxHashMap<NodeId, Vec<Var>> = nodes
+        .values()
+        .filter(|node| node.kind == NodeKind::Bus)
+        .map(|node| {
+            let lanes = node.inputs.iter().map(|_| problem.add_var(MACHINE_COST));
+            (node.id, lanes.collect())
+        })
+        .collect();
+    // Slacks that mean a node's constraints aren't met when they are non-zero
+    let mut violations: FxHashMap<NodeId, Vec<Var>> = FxHashMap::default();
+    let mut integer = Vec::new();
+    // Whole machines that are built, the machines of the node only run part of the time
+    let mut built_vars: FxHashMap<NodeId, Var> = FxHashMap::default();
+
+    for node in nodes.values() {
+        let machines = machine_vars[&node.id];
+        let is_locked = locked.contains(&node.id);
+        let fixed = if is_locked {
+            Some(0.0)
+        } else {
+            node.fixed_machines()
+        };
+        if let Some(fixed) = fixed {
+            problem.add_constraint([(machines, 1.0)], Relation::Equal, fixed as f64);
+        } else if rounding == MachineRounding::Integer && node.is_machine() {
+            // machines <= built
+            let built = problem.add_var(MACHINE_COST);
+            problem.add_constraint([(machines, 1.0), (built, -1.0)], Relation::LessOrEqual, 0.0);
+            integer.push(built);
+            built_vars.insert(node.id, built);
+        }
+        let constrained = !is_locked && !node.constraints.is_empty();
+        if constrained {
+            add_node_constraints(&mut problem, node, machines, &mut violations);
+            if node.constraints.integer {
+                integer.push(machines);
+            }
+        }
+
+        for (input, sockets) in [(true, &node.inputs), (false, &node.outputs)] {
+            for (socket_index, socket) in sockets.iter().enumerate() {
+                let pos = SocketPos {
+                    node_id: node.id,
+                    socket_index,
+                    input,
+                };
+                let mut coefficients: Vec<(Var, f64)> = connections
+                    .iter()
+                    .zip(&flow_vars)
+                    .filter(|(c, _)| c.has_socket(pos))
+                    .map(|(_, var)| (*var, 1.0))
+                    .collect();
+                if coefficients.is_empty() {
+                    continue;
+                }
+
+                // flows + slack = machines * per_machine
+                let slack_cost = match (input, socket.allow_void) {
+                    (true, _) => DEFICIT_COST,
+                    (false, false) => SURPLUS_COST,
+                    (false, true) => 0.0,
+                };
+                let slack = problem.add_var(slack_cost);
+                if input && constrained {
+                    // A constrained node that isn't supplied can't run at the constrained rate
+                    violations.entry(node.id).or_default().push(slack);
+                }
+                coefficients.push((slack, 1.0));
+                let scale = lane_vars
+                    .get(&node.id)
+                    .and_then(|lanes| lanes.get(socket_index))
+                    .copied()
+                    .unwrap_or(machines);
+                coefficients.push((scale, -socket.per_machine as f64));
+                problem.add_constraint(coefficients, Relation::Equal, 0.0);
+            }
+        }
+    }
+
+    let (solution, warning) = match solve_with_fallback(&problem, &integer) {
+        Ok(solved) => solved,
+        Err(error) => {
+            clear(nodes, connections);
+            return Some(SolveWarning::Failed(error));
+        }
+    };
+    let round_up = rounding == MachineRounding::RoundUp || warning == Some(SolveWarning::RoundedUp);
+
+    for node in nodes.values_mut() {
+        node.machines = solution.value(machine_vars[&node.id]) as f32;
+        node.violated = violations
+            .get(&node.id)
+            .into_iter()
+            .flatten()
+            .any(|var| solution.value(*var) > 1e-6);
+        node.built = match built_vars.get(&node.id) {
+            Some(built) if !round_up => solution.value(*built) as f32,
+            _ if round_up && node.is_machine() && node.fixed_machines().is_none() => {
+                // Don't let float noise build a whole extra machine
+                (node.machines - 1e-4).ceil().max(0.0)
+            }
+            _ => node.machines,
+        };
+        let lanes = lane_vars.get(&node.id);
+        for sockets in [&mut node.inputs, &mut node.outputs] {
+            for (socket_index, socket) in sockets.iter_mut().enumerate() {
+                let scale = match lanes.and_then(|lanes| lanes.get(socket_index)) {
+                    Some(lane) => solution.value(*lane) as f32,
+                    None => node.machines,
+                };
+                socket.rate = socket.per_machine * scale;
+            }
+        }
+    }
+    for (connection, var) in connections.iter_mut().zip(&flow_vars) {
+        connection.rate = solution.value(*var) as f32;
+    }
+
+    // The idle fraction of a connection is the part of its output's built capacity that isn't
+    // taken by any connection
+    let mut taken: FxHashMap<SocketPos, f32> = FxHashMap::default();
+    for connection in connections.iter() {
+        *taken.entry(connection.src).or_default() += connection.rate;
+    }
+    for connection in connections.iter_mut() {
+        let node = &nodes[&connection.src.node_id];
+        let capacity = node.outputs[connection.src.socket_index].per_machine * node.built;
+        connection.idle = if capacity > 1e-6 {
+            (1.0 - taken[&connection.src] / capacity).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+    }
+    warning
+}
+
+/// Solve with whole numbers for the `integer` vars, or exactly when that takes too long
+fn solve_with_fallback(
+    problem: &Problem,
+    integer: &[Var],
+) -> Result<(Solution, Option<SolveWarning>), SolveError> {
+    match problem.solve_integer(integer) {
+        Ok(solution) if solution.is_optimal() => Ok((solution, None)),
+        Ok(solution) => Ok((solution, Some(SolveWarning::NotFewestMachines))),
+        Err(SolveError::TooManyBranches) => Ok((problem.solve()?, Some(SolveWarning::RoundedUp))),
+        Err(error) => Err(error),
+    }
+}
+
+/// Reset everything that the solver calculates, so nothing is left from an earlier solve
+fn clear(nodes: &mut FxHashMap<NodeId, Node>, connections: &mut [Connection]) {
+    for node in nodes.values_mut() {
+        node.machines = 0.0;
+        node.built = 0.0;
+        node.violated = false;
+        for socket in node.inputs.iter_mut().chain(&mut node.outputs) {
+            socket.rate = 0.0;
+        }
+    }
+    for connection in connections {
+        connection.rate = 0.0;
+        connection.idle = 0.0;
+    }
+}
+
+/// Connections of the same output that have a ratio take flows in proportion to their ratios
+fn add_ratio_constraints(problem: &mut Problem, connections: &[Connection], flow_vars: &[Var]) {
+    let mut first: FxHashMap<SocketPos, (Var, f32)> = FxHashMap::default();
+    for (connection, var) in connections.iter().zip(flow_vars) {
+        let Some(ratio) = connection.ratio else {
+            continue;
+        };
+        match first.get(&connection.src) {
+            // flow * first_ratio - first_flow * ratio = 0
+            Some(&(first_var, first_ratio)) => problem.add_constraint(
+                [(*var, first_ratio as f64), (first_var, -ratio as f64)],
+                Relation::Equal,
+                0.0,
+            ),
+            None => {
+                first.insert(connection.src, (*var, ratio));
+            }
+        }
+    }
+}
+
+/// Add the user's constraints on `node` as soft constraints, so contradicting ones still give a
+/// solution. The slacks that break them are added to `violations`.
+fn add_node_constraints(
+    problem: &mut Problem,
+    node: &Node,
+    machines: Var,
+    violations: &mut FxHashMap<NodeId, Vec<Var>>,
+) {
+    let violations = violations.entry(node.id).or_default();
+    let mut exactly = |problem: &mut Problem, per_machine: f32, value: f32| {
+        // machines * per_machine + under - over = value
+        let under = problem.add_var(VIOLATION_COST);
+        let over = problem.add_var(VIOLATION_COST);
+        problem.add_constraint(
+            [(machines, per_machine as f64), (under, 1.0), (over, -1.0)],
+            Relation::Equal,
+            value as f64,
+        );
+        violations.extend([under, over]);
+    };
+
+    let constraints = &node.constraints;
+    if let Some(fixed) = constraints.machines {
+        exactly(problem, 1.0, fixed);
+    }
+    if let Some((index, rate)) = constraints.output_rate
+        && let Some(output) = node.outputs.get(index)
+    {
+        exactly(problem, output.per_machine, rate);
+    }
+    for &(index, max) in &constraints.max_input_rates {
+        let Some(input) = node.inputs.get(index) else {
+            continue;
+        };
+        // machines * per_machine - over <= max
+        let over = problem.add_var(VIOLATION_COST);
+        problem.add_constraint(
+            [(machines, input.per_machine as f64), (over, -1.0)],
+            Relation::LessOrEqual,
+            max as f64,
+        );
+        violations.push(over);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ItemOrFluidId, nodes, utils::Point2};
+
+    #[test]
+    fn source_split_to_two_demands() {
+        let plate = ItemOrFluidId::Item("iron-plate".to_string());
+        let (source, splitter, left, right) = (NodeId(0), NodeId(1), NodeId(2), NodeId(3));
+        let mut nodes: FxHashMap<NodeId, Node> = [
+            nodes::source_node(source, Point2::ZERO, plate.clone(), None),
+            nodes::splitter_node(splitter, Point2::ZERO, plate.clone(), None, vec![1.0, 1.0]),
+            nodes::demand_node(left, Point2::ZERO, plate.clone(), 2.0),
+            nodes::demand_node(right, Point2::ZERO, plate, 3.0),
+        ]
+        .into_iter()
+        .map(|node| (node.id, node))
+        .collect();
+        let mut connections = vec![
+            Connection::new((source, 0, false).into(), (splitter, 0, true).into()),
+            Connection::new((splitter, 0, false).into(), (left, 0, true).into()),
+            Connection::new((splitter, 1, false).into(), (right, 0, true).into()),
+        ];
+
+        solve(
+            &mut nodes,
+            &mut connections,
+            &FxHashSet::default(),
+            MachineRounding::Exact,
+        );
+
+        // An even split has to send 3 both ways for the larger demand, the smaller one gets a
+        // surplus
+        assert!((nodes[&splitter].machines - 6.0).abs() < 1e-4);
+        assert!((nodes[&source].machines - 6.0).abs() < 1e-4);
+        assert!((connections[0].rate - 6.0).abs() < 1e-4);
+        assert!((connections[2].rate - 3.0).abs() < 1e-4);
+        assert!(!nodes.values().any(|node| node.violated));
+    }
+}
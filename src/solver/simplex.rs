@@ -0,0 +1,472 @@
+//! A small dense two-phase simplex solver. Factories have at most a few thousand variables, so
+//! we don't need anything fancy here.
+//!
+//! All variables are implicitly `>= 0` and the objective is always minimized.
+
+const EPSILON: f64 = 1e-9;
+/// After this many pivots we switch to Bland's rule, which is slower but can't cycle
+const BLAND_AFTER: usize = 5_000;
+const MAX_PIVOTS: usize = 100_000;
+/// How far from a whole number an integer variable may be
+const INTEGER_EPSILON: f64 = 1e-6;
+/// The most LP relaxations branch and bound solves before it gives up. This runs on every edit,
+/// so it has to stay small.
+const MAX_BRANCHES: usize = 500;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Var(usize);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Relation {
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SolveError {
+    Infeasible,
+    Unbounded,
+    /// The pivot limit was hit, this should only happen with numerically awful problems
+    TooManyPivots,
+    /// Branch and bound didn't find a whole solution before the branch limit
+    TooManyBranches,
+}
+
+#[derive(Clone)]
+struct Constraint {
+    coefficients: Vec<(Var, f64)>,
+    relation: Relation,
+    rhs: f64,
+}
+
+#[derive(Clone, Default)]
+pub struct Problem {
+    costs: Vec<f64>,
+    constraints: Vec<Constraint>,
+}
+
+pub struct Solution {
+    values: Vec<f64>,
+    /// Branch and bound can stop early with a whole solution that isn't the cheapest
+    optimal: bool,
+}
+
+impl Solution {
+    pub fn value(&self, var: Var) -> f64 {
+        self.values[var.0]
+    }
+
+    pub fn is_optimal(&self) -> bool {
+        self.optimal
+    }
+}
+
+impl Problem {
+    /// Add a new variable with the given cost in the objective
+    pub fn add_var(&mut self, cost: f64) -> Var {
+        self.costs.push(cost);
+        Var(self.costs.len() - 1)
+    }
+
+    pub fn add_constraint(
+        &mut self,
+        coefficients: impl IntoIterator<Item = (Var, f64)>,
+        relation: Relation,
+        rhs: f64,
+    ) {
+        self.constraints.push(Constraint {
+            coefficients: coefficients.into_iter().collect(),
+            relation,
+            rhs,
+        });
+    }
+
+    fn objective(&self, solution: &Solution) -> f64 {
+        self.costs
+            .iter()
+            .zip(&solution.values)
+            .map(|(c, v)| c * v)
+            .sum()
+    }
+
+    /// Solve with the `integer` variables restricted to whole numbers, by branch and bound on the
+    /// LP relaxation. Branches round up first, since more machines is nearly always feasible.
+    ///
+    /// When the branch limit is hit, the best whole solution so far is returned as not optimal,
+    /// or [`SolveError::TooManyBranches`] if there is none.
+    pub fn solve_integer(&self, integer: &[Var]) -> Result<Solution, SolveError> {
+        if integer.is_empty() {
+            return self.solve();
+        }
+
+        let mut best: Option<(f64, Solution)> = None;
+        let mut error = None;
+        let mut stack: Vec<Vec<(Var, Relation, f64)>> = vec![Vec::new()];
+        let mut branches = 0;
+        while let Some(bounds) = stack.pop() {
+            branches += 1;
+            if branches > MAX_BRANCHES {
+                error = Some(SolveError::TooManyBranches);
+                break;
+            }
+
+            let mut problem = self.clone();
+            for &(var, relation, rhs) in &bounds {
+                problem.add_constraint([(var, 1.0)], relation, rhs);
+            }
+            let solution = match problem.solve() {
+                Ok(solution) => solution,
+                Err(e) => {
+                    error.get_or_insert(e);
+                    continue;
+                }
+            };
+            let objective = self.objective(&solution);
+            if best
+                .as_ref()
+                .is_some_and(|(best, _)| objective >= best - EPSILON)
+            {
+                continue;
+            }
+
+            let fractional = integer
+                .iter()
+                .map(|&var| (var, solution.value(var)))
+                .find(|(_, value)| (value - value.round()).abs() > INTEGER_EPSILON);
+            match fractional {
+                None => best = Some((objective, solution)),
+                Some((var, value)) => {
+                    let mut down = bounds.clone();
+                    down.push((var, Relation::LessOrEqual, value.floor()));
+                    let mut up = bounds;
+                    up.push((var, Relation::GreaterOrEqual, value.ceil()));
+                    stack.push(down);
+                    stack.push(up);
+                }
+            }
+        }
+
+        let (_, mut solution) = best.ok_or(error.unwrap_or(SolveError::Infeasible))?;
+        solution.optimal = error != Some(SolveError::TooManyBranches);
+        for var in integer {
+            solution.values[var.0] = solution.values[var.0].round();
+        }
+        Ok(solution)
+    }
+
+    pub fn solve(&self) -> Result<Solution, SolveError> {
+        let var_count = self.costs.len();
+
+        // Layout of the columns: [variables] [slacks] [artificials] [rhs]
+        let slack_count = self
+            .constraints
+            .iter()
+            .filter(|c| c.relation != Relation::Equal)
+            .count();
+        let artificial_count = self
+            .constraints
+            .iter()
+            .filter(|c| {
+                let flip = c.rhs < 0.0;
+                match c.relation {
+                    Relation::LessOrEqual => flip,
+                    Relation::GreaterOrEqual => !flip,
+                    Relation::Equal => true,
+                }
+            })
+            .count();
+        let artificial_start = var_count + slack_count;
+        let columns = artificial_start + artificial_count;
+
+        let mut tableau = Tableau {
+            rows: Vec::with_capacity(self.constraints.len()),
+            basis: Vec::with_capacity(self.constraints.len()),
+            columns,
+        };
+
+        let mut next_slack = var_count;
+        let mut next_artificial = artificial_start;
+        for constraint in &self.constraints {
+            let mut row = vec![0.0; columns + 1];
+            let sign = if constraint.rhs < 0.0 { -1.0 } else { 1.0 };
+            for (var, coefficient) in &constraint.coefficients {
+                row[var.0] += coefficient * sign;
+            }
+            row[columns] = constraint.rhs * sign;
+
+            // Flipping the sign of the row also flips the relation
+            let relation = match (constraint.relation, sign < 0.0) {
+                (Relation::LessOrEqual, true) => Relation::GreaterOrEqual,
+                (Relation::GreaterOrEqual, true) => Relation::LessOrEqual,
+                (relation, _) => relation,
+            };
+            match relation {
+                Relation::LessOrEqual => {
+                    row[next_slack] = 1.0;
+                    tableau.basis.push(next_slack);
+                    next_slack += 1;
+                }
+                Relation::GreaterOrEqual => {
+                    row[next_slack] = -1.0;
+                    next_slack += 1;
+                    row[next_artificial] = 1.0;
+                    tableau.basis.push(next_artificial);
+                    next_artificial += 1;
+                }
+                Relation::Equal => {
+                    row[next_artificial] = 1.0;
+                    tableau.basis.push(next_artificial);
+                    next_artificial += 1;
+                }
+            }
+            tableau.rows.push(row);
+        }
+
+        if artificial_count > 0 {
+            let mut costs = vec![0.0; columns];
+            costs[artificial_start..].fill(1.0);
+            tableau.minimize(&costs, columns)?;
+
+            let infeasibility: f64 = (0..tableau.rows.len())
+                .filter(|&row| tableau.basis[row] >= artificial_start)
+                .map(|row| tableau.rows[row][columns])
+                .sum();
+            if infeasibility > EPSILON.sqrt() {
+                return Err(SolveError::Infeasible);
+            }
+
+            // Drive the remaining (zero valued) artificials out of the basis
+            for row in 0..tableau.rows.len() {
+                if tableau.basis[row] < artificial_start {
+                    continue;
+                }
+                if let Some(column) =
+                    (0..artificial_start).find(|&c| tableau.rows[row][c].abs() > EPSILON)
+                {
+                    tableau.pivot(row, column);
+                }
+                // Otherwise the row is redundant, and the artificial stays at 0 forever
+            }
+        }
+
+        let mut costs = self.costs.clone();
+        costs.resize(columns, 0.0);
+        tableau.minimize(&costs, artificial_start)?;
+
+        let mut values = vec![0.0; var_count];
+        for (row, &column) in tableau.basis.iter().enumerate() {
+            if column < var_count {
+                values[column] = tableau.rows[row][columns].max(0.0);
+            }
+        }
+        Ok(Solution {
+            values,
+            optimal: true,
+        })
+    }
+}
+
+struct Tableau {
+    rows: Vec<Vec<f64>>,
+    /// The column that is basic in each row
+    basis: Vec<usize>,
+    columns: usize,
+}
+
+impl Tableau {
+    /// Minimize the given costs, only letting columns before `allowed_columns` enter the basis
+    fn minimize(&mut self, costs: &[f64], allowed_columns: usize) -> Result<(), SolveError> {
+        let rhs = self.columns;
+        let mut reduced = costs.to_vec();
+        reduced.push(0.0);
+        for (row, &column) in self.basis.iter().enumerate() {
+            let cost = costs[column];
+            if cost != 0.0 {
+                for (r, value) in reduced.iter_mut().zip(&self.rows[row]) {
+                    *r -= cost * value;
+                }
+            }
+        }
+
+        for pivots in 0..MAX_PIVOTS {
+            let entering = if pivots < BLAND_AFTER {
+                (0..allowed_columns)
+                    .filter(|&c| reduced[c] < -EPSILON)
+                    .min_by(|&a, &b| reduced[a].total_cmp(&reduced[b]))
+            } else {
+                (0..allowed_columns).find(|&c| reduced[c] < -EPSILON)
+            };
+            let Some(entering) = entering else {
+                return Ok(());
+            };
+
+            let mut leaving: Option<(usize, f64)> = None;
+            for (row, values) in self.rows.iter().enumerate() {
+                if values[entering] <= EPSILON {
+                    continue;
+                }
+                let ratio = values[rhs] / values[entering];
+                let better = match leaving {
+                    None => true,
+                    Some((best_row, best_ratio)) => {
+                        ratio < best_ratio - EPSILON
+                            || (ratio < best_ratio + EPSILON
+                                && self.basis[row] < self.basis[best_row])
+                    }
+                };
+                if better {
+                    leaving = Some((row, ratio));
+                }
+            }
+            let Some((leaving, _)) = leaving else {
+                return Err(SolveError::Unbounded);
+            };
+
+            self.pivot(leaving, entering);
+            let factor = reduced[entering];
+            for (r, value) in reduced.iter_mut().zip(&self.rows[leaving]) {
+                *r -= factor * value;
+            }
+        }
+        Err(SolveError::TooManyPivots)
+    }
+
+    fn pivot(&mut self, pivot_row: usize, pivot_column: usize) {
+        let divisor = self.rows[pivot_row][pivot_column];
+        for value in &mut self.rows[pivot_row] {
+            *value /= divisor;
+        }
+
+        let pivot = std::mem::take(&mut self.rows[pivot_row]);
+        for row in &mut self.rows {
+            if row.is_empty() {
+                continue;
+            }
+            let factor = row[pivot_column];
+            if factor.abs() <= EPSILON {
+                continue;
+            }
+            for (value, p) in row.iter_mut().zip(&pivot) {
+                *value -= factor * p;
+            }
+        }
+        self.rows[pivot_row] = pivot;
+        self.basis[pivot_row] = pivot_column;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn feasible() {
+        // max x + y, as min -x - y, with x + 2y <= 4 and 3x + y <= 6
+        let mut problem = Problem::default();
+        let x = problem.add_var(-1.0);
+        let y = problem.add_var(-1.0);
+        problem.add_constraint([(x, 1.0), (y, 2.0)], Relation::LessOrEqual, 4.0);
+        problem.add_constraint([(x, 3.0), (y, 1.0)], Relation::LessOrEqual, 6.0);
+        let solution = problem.solve().unwrap();
+        assert_close(solution.value(x), 1.6);
+        assert_close(solution.value(y), 1.2);
+    }
+
+    #[test]
+    fn greater_or_equal() {
+        let mut problem = Problem::default();
+        let x = problem.add_var(2.0);
+        let y = problem.add_var(3.0);
+        problem.add_constraint([(x, 1.0), (y, 1.0)], Relation::GreaterOrEqual, 10.0);
+        problem.add_constraint([(y, 1.0)], Relation::GreaterOrEqual, 4.0);
+        let solution = problem.solve().unwrap();
+        assert_close(solution.value(x), 6.0);
+        assert_close(solution.value(y), 4.0);
+    }
+
+    #[test]
+    fn infeasible() {
+        let mut problem = Problem::default();
+        let x = problem.add_var(1.0);
+        problem.add_constraint([(x, 1.0)], Relation::GreaterOrEqual, 5.0);
+        problem.add_constraint([(x, 1.0)], Relation::LessOrEqual, 3.0);
+        assert_eq!(problem.solve().err(), Some(SolveError::Infeasible));
+    }
+
+    #[test]
+    fn infeasible_negative_rhs() {
+        // x >= 0 can't be at most -1
+        let mut problem = Problem::default();
+        let x = problem.add_var(1.0);
+        problem.add_constraint([(x, 1.0)], Relation::LessOrEqual, -1.0);
+        assert_eq!(problem.solve().err(), Some(SolveError::Infeasible));
+    }
+
+    #[test]
+    fn unbounded() {
+        let mut problem = Problem::default();
+        let x = problem.add_var(-1.0);
+        let y = problem.add_var(0.0);
+        problem.add_constraint([(x, 1.0), (y, -1.0)], Relation::LessOrEqual, 1.0);
+        assert_eq!(problem.solve().err(), Some(SolveError::Unbounded));
+    }
+
+    #[test]
+    fn degenerate_does_not_cycle() {
+        // Beale's example, which cycles forever with the textbook largest coefficient rule
+        let mut problem = Problem::default();
+        let x4 = problem.add_var(-0.75);
+        let x5 = problem.add_var(150.0);
+        let x6 = problem.add_var(-0.02);
+        let x7 = problem.add_var(6.0);
+        problem.add_constraint(
+            [(x4, 0.25), (x5, -60.0), (x6, -0.04), (x7, 9.0)],
+            Relation::LessOrEqual,
+            0.0,
+        );
+        problem.add_constraint(
+            [(x4, 0.5), (x5, -90.0), (x6, -0.02), (x7, 3.0)],
+            Relation::LessOrEqual,
+            0.0,
+        );
+        problem.add_constraint([(x6, 1.0)], Relation::LessOrEqual, 1.0);
+        let solution = problem.solve().unwrap();
+        assert_close(problem.objective(&solution), -0.05);
+        assert_close(solution.value(x4), 0.04);
+        assert_close(solution.value(x6), 1.0);
+    }
+
+    #[test]
+    fn equality() {
+        let mut problem = Problem::default();
+        let x = problem.add_var(1.0);
+        let y = problem.add_var(2.0);
+        let z = problem.add_var(0.0);
+        problem.add_constraint([(x, 1.0), (y, 1.0)], Relation::Equal, 5.0);
+        problem.add_constraint([(x, 1.0), (z, -1.0)], Relation::Equal, 2.0);
+        // The same constraint again, which leaves an artificial in the basis
+        problem.add_constraint([(x, 2.0), (y, 2.0)], Relation::Equal, 10.0);
+        let solution = problem.solve().unwrap();
+        assert_close(solution.value(x), 5.0);
+        assert_close(solution.value(y), 0.0);
+        assert_close(solution.value(z), 3.0);
+    }
+
+    #[test]
+    fn integer() {
+        // The relaxation builds 2.5, whole numbers need 3
+        let mut problem = Problem::default();
+        let x = problem.add_var(1.0);
+        problem.add_constraint([(x, 2.0)], Relation::GreaterOrEqual, 5.0);
+        let solution = problem.solve_integer(&[x]).unwrap();
+        assert_close(solution.value(x), 3.0);
+    }
+}
@@ -1,7 +1,9 @@
 mod preset;
+mod research;
 mod theme;
 
 pub use preset::*;
+pub use research::*;
 pub use theme::*;
 
 #[derive(serde::Serialize, serde::Deserialize)]
@@ -1,11 +1,23 @@
 mod id_generator;
+mod index;
 mod json;
+mod logistics;
+mod mining;
+mod power;
+mod technology;
 
+pub use index::PresetIndex;
+pub use logistics::*;
+pub use mining::*;
+pub use power::*;
+pub use technology::*;
+
+use crate::{ItemOrFluidId, TemperatureRange, gfx::Color};
 use id_generator::IdGenerator;
 use itertools::Itertools;
 use std::time::Instant;
 
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct Preset {
@@ -14,6 +26,28 @@ pub struct Preset {
     pub items: FxHashMap<ItemId, Item>,
     pub fluids: FxHashMap<FluidId, Fluid>,
     pub recipes: FxHashMap<RecipeId, Recipe>,
+    pub crafting_machines: FxHashMap<String, CraftingMachine>,
+    pub boilers: FxHashMap<String, Boiler>,
+    pub generators: FxHashMap<String, Generator>,
+    pub reactors: FxHashMap<String, Reactor>,
+    pub resources: FxHashMap<String, Resource>,
+    pub mining_drills: FxHashMap<String, MiningDrill>,
+    /// Fluids that offshore pumps can pump from a tile, sorted by name
+    pub pumpable_fluids: Vec<String>,
+    pub technologies: FxHashMap<String, Technology>,
+    pub labs: FxHashMap<String, Lab>,
+    /// Sorted from slowest to fastest
+    pub belts: Vec<Belt>,
+    /// Sorted from slowest to fastest
+    pub inserters: Vec<Inserter>,
+    /// Fluid per second that a single pipe run can carry, `None` if there are no pipes
+    pub pipe_throughput: Option<f32>,
+    /// Slots of the biggest cargo wagon, `None` if there are no cargo wagons
+    pub cargo_wagon_slots: Option<u32>,
+    /// Fluid that the biggest fluid wagon holds, `None` if there are no fluid wagons
+    pub fluid_wagon_capacity: Option<f32>,
+    #[serde(skip)]
+    pub index: PresetIndex,
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
@@ -40,8 +74,9 @@ impl Preset {
             let data =
                 std::fs::read_to_string(&json_file_name).expect("Failed to read preset.json");
             let data = data.leak::<'static>();
-            match serde_json::from_str(data) {
-                Ok(preset) => {
+            match serde_json::from_str::<Preset>(data) {
+                Ok(mut preset) => {
+                    preset.index = index::build(&preset);
                     println!("Loaded preset.json in {:?}", start.elapsed());
                     return preset;
                 }
@@ -66,6 +101,12 @@ impl Preset {
         println!("  Found {} recipes", deserialized.recipe.len());
         println!("  Found {} items", deserialized.item.len());
         println!("  Found {} fluids", deserialized.fluid.len());
+        println!("  Found {} boilers", deserialized.boiler.len());
+        println!("  Found {} generators", deserialized.generator.len());
+        println!("  Found {} reactors", deserialized.reactor.len());
+        println!("  Found {} resources", deserialized.resource.len());
+        println!("  Found {} mining drills", deserialized.mining_drill.len());
+        println!("  Found {} technologies", deserialized.technology.len());
 
         let mut preset = Preset {
             name: name.to_string(),
@@ -73,45 +114,158 @@ impl Preset {
             items: FxHashMap::default(),
             fluids: FxHashMap::default(),
             recipes: FxHashMap::default(),
+            crafting_machines: FxHashMap::default(),
+            boilers: FxHashMap::default(),
+            generators: FxHashMap::default(),
+            reactors: FxHashMap::default(),
+            resources: FxHashMap::default(),
+            mining_drills: FxHashMap::default(),
+            pumpable_fluids: Vec::new(),
+            technologies: FxHashMap::default(),
+            labs: FxHashMap::default(),
+            belts: Vec::new(),
+            inserters: Vec::new(),
+            pipe_throughput: None,
+            cargo_wagon_slots: None,
+            fluid_wagon_capacity: None,
+            index: PresetIndex::default(),
         };
 
         let item_ids = IdGenerator::<ItemId>::from_iter(deserialized.item.keys().cloned());
         let fluid_ids = IdGenerator::<FluidId>::from_iter(deserialized.fluid.keys().cloned());
         let recipe_ids = IdGenerator::<RecipeId>::from_iter(deserialized.recipe.keys().cloned());
 
-        for (name, _item) in &deserialized.item {
+        for (name, item) in &deserialized.item {
             let id = item_ids.get(name);
             preset.items.insert(
                 id,
                 Item {
                     id,
                     name: name.clone(),
+                    fuel_value: item.fuel_value.map(|v| v.mega()).unwrap_or(0.0),
+                    fuel_category: item.fuel_category.clone(),
+                    burnt_result: item.burnt_result.clone(),
+                    stack_size: item.stack_size.unwrap_or(1),
                 },
             );
         }
 
-        for (name, _fluid) in &deserialized.fluid {
+        for (name, fluid) in &deserialized.fluid {
             let id = fluid_ids.get(name);
             preset.fluids.insert(
                 id,
                 Fluid {
                     id,
                     name: name.clone(),
+                    default_temperature: fluid.default_temperature.unwrap_or(15.0),
+                    max_temperature: fluid
+                        .max_temperature
+                        .or(fluid.default_temperature)
+                        .unwrap_or(15.0),
+                    heat_capacity: fluid
+                        .heat_capacity
+                        .map(|v| v.base_value() as f32)
+                        .unwrap_or(1000.0),
+                    base_color: fluid.base_color.map(|c| c.0),
                 },
             );
         }
 
-        for (name, _recipe) in &deserialized.recipe {
+        let hidden: FxHashSet<ItemOrFluidId> = deserialized
+            .item
+            .values()
+            .filter(|i| {
+                i.hidden == Some(true)
+                    || i.flags
+                        .as_ref()
+                        .is_some_and(|f| f.contains(&json::Flags::Hidden))
+            })
+            .map(|i| ItemOrFluidId::Item(i.name.clone()))
+            .chain(
+                deserialized
+                    .fluid
+                    .values()
+                    .filter(|f| f.hidden == Some(true))
+                    .map(|f| ItemOrFluidId::Fluid(f.name.clone())),
+            )
+            .collect();
+
+        for (name, recipe) in &deserialized.recipe {
             let id = recipe_ids.get(name);
+            let mut results: Vec<RecipeProduct> = recipe
+                .results
+                .iter()
+                .flat_map(|r| r.iter())
+                .map(|r| RecipeProduct {
+                    item_or_fluid: ItemOrFluidId::from_type(&r.ty, &r.name),
+                    amount: r.expected_amount(),
+                    temperature: r.temperature,
+                })
+                .collect();
+            if let Some(result) = &recipe.result {
+                results.push(RecipeProduct {
+                    item_or_fluid: ItemOrFluidId::Item(result.clone()),
+                    amount: recipe.result_count.unwrap_or(1.0),
+                    temperature: None,
+                });
+            }
+            let category = recipe
+                .category
+                .clone()
+                .unwrap_or_else(|| "crafting".to_string());
             preset.recipes.insert(
                 id,
                 Recipe {
                     id,
                     name: name.clone(),
+                    hidden: recipe.hidden == Some(true)
+                        || (!results.is_empty()
+                            && results.iter().all(|r| hidden.contains(&r.item_or_fluid))),
+                    class: RecipeClass::classify(&category, recipe.subgroup.as_deref(), &results),
+                    category,
+                    energy_required: recipe.energy_required.unwrap_or(0.5),
+                    enabled: recipe.enabled.unwrap_or(true),
+                    ingredients: recipe
+                        .ingredients
+                        .iter()
+                        .flat_map(|i| i.iter())
+                        .map(|i| RecipeIngredient {
+                            item_or_fluid: ItemOrFluidId::from_type(&i.ty, &i.name),
+                            amount: i.amount,
+                            temperature: (i.minimum_temperature.is_some()
+                                || i.maximum_temperature.is_some())
+                            .then(|| {
+                                TemperatureRange::new(i.minimum_temperature, i.maximum_temperature)
+                            }),
+                        })
+                        .collect(),
+                    results,
                 },
             );
         }
 
+        for machine in deserialized
+            .assembling_machine
+            .values()
+            .chain(deserialized.furnace.values())
+            .chain(deserialized.rocket_silo.values())
+        {
+            preset.crafting_machines.insert(
+                machine.name.clone(),
+                CraftingMachine {
+                    name: machine.name.clone(),
+                    crafting_speed: machine.crafting_speed,
+                    crafting_categories: machine.crafting_categories.to_vec(),
+                    energy_usage: machine.energy_usage.map(|u| u.mega()).unwrap_or(0.0),
+                },
+            );
+        }
+
+        power::load(&mut preset, &deserialized);
+        mining::load(&mut preset, &deserialized);
+        technology::load(&mut preset, &deserialized);
+        logistics::load(&mut preset, &deserialized);
+
         // TODO: Optimize this
         // I'm pretty sure we can do this in 1 loop for each entry in `json` and using an intermediate format
         for (group_name, group) in &deserialized.item_group {
@@ -165,6 +319,7 @@ impl Preset {
             preset.groups.push(group);
         }
         preset.groups.sort_by_key(|g| g.order.clone());
+        preset.index = index::build(&preset);
 
         std::fs::write(
             json_file_name,
@@ -175,6 +330,24 @@ impl Preset {
         preset
     }
 
+    /// The crafting machine that is used by default for the given recipe category. This is the
+    /// slowest machine that can craft it, which is usually the first one that gets unlocked.
+    pub fn default_machine_for_category(&self, category: &str) -> Option<&CraftingMachine> {
+        self.machines_for_category(category).min_by(|a, b| {
+            a.crafting_speed
+                .total_cmp(&b.crafting_speed)
+                .then_with(|| a.name.cmp(&b.name))
+        })
+    }
+
+    /// The recipe that destroys the given item or fluid, like Py's venting, sinkhole and
+    /// incineration recipes. Recipes that void only this are preferred.
+    pub fn void_recipe_for(&self, item_or_fluid: &ItemOrFluidId) -> Option<&Recipe> {
+        self.recipes_consuming(item_or_fluid)
+            .filter(|r| r.class == RecipeClass::Void)
+            .min_by_key(|r| (r.ingredients.len(), &r.name))
+    }
+
     pub(crate) fn icon_for_fluid(&self, fluid: &Fluid) -> String {
         format!(
             "preset/{}/script-output/fluid/{}.png",
@@ -186,6 +359,35 @@ impl Preset {
         format!("preset/{}/script-output/item/{}.png", self.name, item.name)
     }
 
+    /// `None` for electricity and heat, which don't have icons
+    pub(crate) fn icon_for_item_or_fluid(&self, item_or_fluid: &ItemOrFluidId) -> Option<String> {
+        match item_or_fluid {
+            ItemOrFluidId::Item(name) => Some(format!(
+                "preset/{}/script-output/item/{}.png",
+                self.name, name
+            )),
+            ItemOrFluidId::Fluid(name) => Some(format!(
+                "preset/{}/script-output/fluid/{}.png",
+                self.name, name
+            )),
+            ItemOrFluidId::Electricity | ItemOrFluidId::Heat => None,
+        }
+    }
+
+    /// The colour of flows of the item or fluid. Only fluids have a colour in the data, items get
+    /// a hue from their name, so an item has the same colour everywhere.
+    pub(crate) fn color_for_item_or_fluid(&self, item_or_fluid: &ItemOrFluidId) -> Color {
+        match item_or_fluid {
+            ItemOrFluidId::Item(name) => color_from_name(name),
+            ItemOrFluidId::Fluid(name) => self
+                .fluid_by_name(name)
+                .and_then(|fluid| fluid.base_color)
+                .unwrap_or_else(|| color_from_name(name)),
+            ItemOrFluidId::Electricity => Color::rgb(0xF5, 0xD0, 0x42),
+            ItemOrFluidId::Heat => Color::rgb(0xE8, 0x6A, 0x2C),
+        }
+    }
+
     pub(crate) fn icon_for_recipe(&self, recipe: &Recipe) -> String {
         format!(
             "preset/{}/script-output/recipe/{}.png",
@@ -201,22 +403,136 @@ impl Preset {
     }
 }
 
+/// A bright colour with a hue that depends on the name
+fn color_from_name(name: &str) -> Color {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = rustc_hash::FxHasher::default();
+    name.hash(&mut hasher);
+    let hue = (hasher.finish() % 360) as f32 / 60.0;
+    let (saturation, value) = (0.55, 0.9);
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+    Color::rgbaf(r + m, g + m, b + m, 1.0)
+}
+
 #[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
 pub struct Recipe {
     pub id: RecipeId,
     pub name: String,
+    pub category: String,
+    /// Hidden recipes, or recipes that only make hidden things
+    pub hidden: bool,
+    pub class: RecipeClass,
+    /// Time in seconds that a single craft takes at crafting speed 1
+    pub energy_required: f32,
+    /// Whether the recipe is available from the start, without researching anything
+    pub enabled: bool,
+    pub ingredients: Vec<RecipeIngredient>,
+    pub results: Vec<RecipeProduct>,
+}
+
+/// What a recipe is for, so the helper recipes that mods generate can be told apart
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum RecipeClass {
+    #[default]
+    Normal,
+    /// Filling or emptying barrels
+    Barreling,
+    /// Gets rid of its ingredients without making anything, like venting or incineration
+    Void,
+}
+
+impl RecipeClass {
+    fn classify(category: &str, subgroup: Option<&str>, results: &[RecipeProduct]) -> Self {
+        const VOID_CATEGORIES: [&str; 5] =
+            ["venting", "sinkhole", "incineration", "void", "runoff"];
+        if category.contains("barreling")
+            || matches!(subgroup, Some("fill-barrel" | "empty-barrel"))
+        {
+            RecipeClass::Barreling
+        } else if results.is_empty() || VOID_CATEGORIES.iter().any(|c| category.contains(c)) {
+            RecipeClass::Void
+        } else {
+            RecipeClass::Normal
+        }
+    }
+}
+
+/// Which helper recipes are shown in selectors and used for generated chains. Everything is
+/// hidden by default.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RecipeFilter {
+    pub hidden: bool,
+    pub barreling: bool,
+    pub void: bool,
+}
+
+impl RecipeFilter {
+    pub fn allows(&self, hidden: bool, class: RecipeClass) -> bool {
+        let class_allowed = match class {
+            RecipeClass::Normal => true,
+            RecipeClass::Barreling => self.barreling,
+            RecipeClass::Void => self.void,
+        };
+        (self.hidden || !hidden) && class_allowed
+    }
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct RecipeIngredient {
+    pub item_or_fluid: ItemOrFluidId,
+    pub amount: f32,
+    /// Only set for fluids that have a temperature requirement
+    pub temperature: Option<TemperatureRange>,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct RecipeProduct {
+    pub item_or_fluid: ItemOrFluidId,
+    /// The average amount per craft, with the probability already applied
+    pub amount: f32,
+    /// Only set for fluids that are produced at a specific temperature
+    pub temperature: Option<f32>,
 }
 
 #[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
 pub struct Item {
     pub id: ItemId,
     pub name: String,
+    /// In MJ
+    pub fuel_value: f32,
+    pub fuel_category: Option<String>,
+    pub burnt_result: Option<String>,
+    pub stack_size: u32,
 }
 
 #[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
 pub struct Fluid {
     pub id: FluidId,
     pub name: String,
+    pub default_temperature: f32,
+    pub max_temperature: f32,
+    /// In J per unit per degree
+    pub heat_capacity: f32,
+    pub base_color: Option<Color>,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct CraftingMachine {
+    pub name: String,
+    pub crafting_speed: f32,
+    pub crafting_categories: Vec<String>,
+    /// In MW
+    pub energy_usage: f32,
 }
 
 #[derive(
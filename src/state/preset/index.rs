@@ -0,0 +1,154 @@
+use super::{CraftingMachine, Fluid, FluidId, Item, ItemId, Preset, Recipe, RecipeId, Technology};
+use crate::ItemOrFluidId;
+use rustc_hash::FxHashMap;
+
+/// Reverse lookups into the preset. These aren't stored in preset.json, as JSON keys have to be
+/// strings, but rebuilt every time a preset is loaded.
+#[derive(Debug, Default)]
+pub struct PresetIndex {
+    /// Recipes that have the item or fluid as a result, sorted by name
+    producers: FxHashMap<ItemOrFluidId, Vec<RecipeId>>,
+    /// Recipes that have the item or fluid as an ingredient, sorted by name
+    consumers: FxHashMap<ItemOrFluidId, Vec<RecipeId>>,
+    /// Names of the crafting machines for every recipe category, sorted by name
+    machines: FxHashMap<String, Vec<String>>,
+    /// Recipes and ingredients refer to items by name
+    items: FxHashMap<String, ItemId>,
+    /// Sockets and connections refer to fluids by name, and look up their colour every frame
+    fluids: FxHashMap<String, FluidId>,
+    /// Templates refer to recipes by name, as ids change between presets
+    recipes: FxHashMap<String, RecipeId>,
+    /// Names of the technologies that unlock every recipe, sorted
+    unlocked_by: FxHashMap<RecipeId, Vec<String>>,
+}
+
+pub(super) fn build(preset: &Preset) -> PresetIndex {
+    let mut index = PresetIndex::default();
+
+    let mut recipes: Vec<&Recipe> = preset.recipes.values().collect();
+    recipes.sort_by(|a, b| a.name.cmp(&b.name));
+    for recipe in recipes {
+        for product in &recipe.results {
+            let producers = index
+                .producers
+                .entry(product.item_or_fluid.clone())
+                .or_default();
+            if !producers.contains(&recipe.id) {
+                producers.push(recipe.id);
+            }
+        }
+        for ingredient in &recipe.ingredients {
+            let consumers = index
+                .consumers
+                .entry(ingredient.item_or_fluid.clone())
+                .or_default();
+            if !consumers.contains(&recipe.id) {
+                consumers.push(recipe.id);
+            }
+        }
+    }
+
+    let mut machines: Vec<&CraftingMachine> = preset.crafting_machines.values().collect();
+    machines.sort_by(|a, b| a.name.cmp(&b.name));
+    for machine in machines {
+        for category in &machine.crafting_categories {
+            index
+                .machines
+                .entry(category.clone())
+                .or_default()
+                .push(machine.name.clone());
+        }
+    }
+
+    index.items = preset
+        .items
+        .values()
+        .map(|item| (item.name.clone(), item.id))
+        .collect();
+    index.fluids = preset
+        .fluids
+        .values()
+        .map(|fluid| (fluid.name.clone(), fluid.id))
+        .collect();
+    index.recipes = preset
+        .recipes
+        .values()
+        .map(|recipe| (recipe.name.clone(), recipe.id))
+        .collect();
+
+    for technology in preset.technologies.values() {
+        for recipe in &technology.unlocks {
+            index
+                .unlocked_by
+                .entry(*recipe)
+                .or_default()
+                .push(technology.name.clone());
+        }
+    }
+    for technologies in index.unlocked_by.values_mut() {
+        technologies.sort();
+        technologies.dedup();
+    }
+
+    index
+}
+
+impl Preset {
+    /// All recipes that have the given item or fluid as one of their results
+    pub fn recipes_producing(
+        &self,
+        item_or_fluid: &ItemOrFluidId,
+    ) -> impl Iterator<Item = &Recipe> {
+        self.index
+            .producers
+            .get(item_or_fluid)
+            .into_iter()
+            .flatten()
+            .map(|id| &self.recipes[id])
+    }
+
+    /// All recipes that have the given item or fluid as one of their ingredients
+    pub fn recipes_consuming(
+        &self,
+        item_or_fluid: &ItemOrFluidId,
+    ) -> impl Iterator<Item = &Recipe> {
+        self.index
+            .consumers
+            .get(item_or_fluid)
+            .into_iter()
+            .flatten()
+            .map(|id| &self.recipes[id])
+    }
+
+    pub fn item_by_name(&self, name: &str) -> Option<&Item> {
+        self.index.items.get(name).map(|id| &self.items[id])
+    }
+
+    pub fn fluid_by_name(&self, name: &str) -> Option<&Fluid> {
+        self.index.fluids.get(name).map(|id| &self.fluids[id])
+    }
+
+    pub fn recipe_by_name(&self, name: &str) -> Option<&Recipe> {
+        self.index.recipes.get(name).map(|id| &self.recipes[id])
+    }
+
+    /// All technologies that unlock the given recipe, sorted by name
+    pub fn technologies_unlocking(&self, recipe: RecipeId) -> impl Iterator<Item = &Technology> {
+        self.index
+            .unlocked_by
+            .get(&recipe)
+            .into_iter()
+            .flatten()
+            .map(|name| &self.technologies[name])
+    }
+
+    /// All crafting machines that can craft recipes of the given category
+    pub fn machines_for_category(&self, category: &str) -> impl Iterator<Item = &CraftingMachine> {
+        self.index
+            .machines
+            .get(category)
+            .into_iter()
+            .flatten()
+            .map(|name| &self.crafting_machines[name])
+    }
+}
@@ -1,24 +1,29 @@
 #![allow(dead_code)]
 
-// mod flags;
+mod color;
+mod flags;
 // mod fluid_boxes;
-// mod int_or_infinite;
-// mod prerequisites;
-// mod recipe_ingredient;
-// mod recipe_result;
-// mod unit;
-// mod vec_or_map;
-
-// pub use flags::Flags;
-// pub use recipe_ingredient::RecipeIngredient;
-// pub use recipe_result::RecipeResult;
-// pub use unit::Unit;
+mod int_or_infinite;
+mod prerequisites;
+mod recipe_ingredient;
+mod recipe_result;
+mod unit;
+mod vec_or_map;
+
+pub use color::JsonColor;
+pub use flags::Flags;
+pub use int_or_infinite::IntOrInfinite;
+pub use prerequisites::Prerequisites;
+pub use recipe_ingredient::RecipeIngredient;
+pub use recipe_result::RecipeResult;
+pub use unit::Unit;
+pub use vec_or_map::VecOrMap;
 
 mod fxhashmap_values;
 
 use rustc_hash::FxHashMap;
 
-#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, serde::Deserialize)]
 pub struct Root {
     pub item: FxHashMap<String, Item>,
     pub fluid: FxHashMap<String, Fluid>,
@@ -28,15 +33,55 @@ pub struct Root {
     pub item_group: FxHashMap<String, ItemGroup>,
     #[serde(rename = "item-subgroup")]
     pub item_subgroup: FxHashMap<String, ItemSubgroup>,
+
+    #[serde(rename = "assembling-machine", default)]
+    pub assembling_machine: FxHashMap<String, CraftingMachine>,
+    #[serde(default)]
+    pub furnace: FxHashMap<String, CraftingMachine>,
+    #[serde(rename = "rocket-silo", default)]
+    pub rocket_silo: FxHashMap<String, CraftingMachine>,
+
+    /// Also contains heat exchangers, which are boilers with a `heat` energy source
+    #[serde(default)]
+    pub boiler: FxHashMap<String, Boiler>,
+    #[serde(default)]
+    pub generator: FxHashMap<String, Generator>,
+    #[serde(default)]
+    pub reactor: FxHashMap<String, Reactor>,
+
+    #[serde(default)]
+    pub resource: FxHashMap<String, Resource>,
+    #[serde(rename = "mining-drill", default)]
+    pub mining_drill: FxHashMap<String, MiningDrill>,
+    #[serde(default)]
+    pub tile: FxHashMap<String, Tile>,
+
+    #[serde(default)]
+    pub technology: FxHashMap<String, Technology>,
+    #[serde(default)]
+    pub lab: FxHashMap<String, Lab>,
+
+    #[serde(rename = "transport-belt", default)]
+    pub transport_belt: FxHashMap<String, TransportBelt>,
+    #[serde(default)]
+    pub inserter: FxHashMap<String, Inserter>,
+    #[serde(default)]
+    pub pipe: FxHashMap<String, Pipe>,
+    #[serde(default)]
+    pub pump: FxHashMap<String, Pump>,
+    #[serde(rename = "cargo-wagon", default)]
+    pub cargo_wagon: FxHashMap<String, CargoWagon>,
+    #[serde(rename = "fluid-wagon", default)]
+    pub fluid_wagon: FxHashMap<String, FluidWagon>,
 }
 
-#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, serde::Deserialize)]
 pub struct ItemGroup {
     pub name: String,
     pub order: String,
 }
 
-#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, serde::Deserialize)]
 pub struct ItemSubgroup {
     pub name: String,
     pub order: String,
@@ -49,21 +94,21 @@ pub trait Groupable {
     fn order(&self) -> Option<&String>;
 }
 
-#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, serde::Deserialize)]
 pub struct Item {
     pub name: String,
     pub group: Option<String>,
     pub subgroup: Option<String>,
     pub order: Option<String>,
     pub category: Option<String>,
-    // pub hidden: Option<bool>,
+    pub hidden: Option<bool>,
     // pub rocket_launch_product: Option<(String, usize)>,
     // pub burnt_fuel_result: Option<String>,
-    // pub burnt_result: Option<String>,
-    // pub fuel_category: Option<String>,
-    // pub fuel_value: Option<Unit>,
-    // pub flags: Option<VecOrMap<Flags>>,
-
+    pub burnt_result: Option<String>,
+    pub fuel_category: Option<String>,
+    pub fuel_value: Option<Unit>,
+    pub flags: Option<VecOrMap<Flags>>,
+    pub stack_size: Option<u32>,
     // #[remaining]
     // pub remaining: FxHashMap<String, serde_json::Value>,
 }
@@ -80,19 +125,20 @@ impl Groupable for Item {
     }
 }
 
-#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, serde::Deserialize)]
 pub struct Fluid {
     pub name: String,
     pub group: Option<String>,
     pub subgroup: Option<String>,
     pub order: Option<String>,
-    // pub default_temperature: Option<f32>,
+    pub default_temperature: Option<f32>,
     // pub min_temperature: Option<f32>,
-    // pub max_temperature: Option<f32>,
+    pub max_temperature: Option<f32>,
     // pub gas_temperature: Option<f32>,
     // pub auto_barrel: Option<bool>,
-    // pub hidden: Option<bool>,
-    // pub heat_capacity: Option<Unit>,
+    pub hidden: Option<bool>,
+    pub heat_capacity: Option<Unit>,
+    pub base_color: Option<JsonColor>,
     // pub fuel_value: Option<Unit>,
     // pub fuel_category: Option<String>,
     // pub flags: Option<VecOrMap<Flags>>,
@@ -112,7 +158,7 @@ impl Groupable for Fluid {
     }
 }
 
-#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, serde::Deserialize)]
 pub struct Recipe {
     pub name: String,
     pub category: Option<String>,
@@ -120,22 +166,22 @@ pub struct Recipe {
     pub subgroup: Option<String>,
     pub order: Option<String>,
     // pub main_product: Option<String>,
-    // pub ingredients: Option<VecOrMap<RecipeIngredient>>,
+    pub ingredients: Option<VecOrMap<RecipeIngredient>>,
 
-    // pub result: Option<String>,
-    // pub result_count: Option<f32>,
-    // pub results: Option<VecOrMap<RecipeResult>>,
+    pub result: Option<String>,
+    pub result_count: Option<f32>,
+    pub results: Option<VecOrMap<RecipeResult>>,
 
     // pub mod_: Option<String>,
     // pub hide_from_player_crafting: Option<bool>,
-    // pub enabled: Option<bool>,
+    pub enabled: Option<bool>,
     // pub allow_productivity: Option<bool>,
-    // pub hidden: Option<bool>,
+    pub hidden: Option<bool>,
     // pub always_show_made_in: Option<bool>,
     // pub allow_decomposition: Option<bool>,
     // pub allow_as_intermediate: Option<bool>,
     // pub allow_intermediates: Option<bool>,
-    // pub energy_required: Option<f32>,
+    pub energy_required: Option<f32>,
     // pub show_amount_in_title: Option<bool>,
     // pub flags: Option<VecOrMap<Flags>>,
 
@@ -155,6 +201,208 @@ impl Groupable for Recipe {
     }
 }
 
+/// Shared by `assembling-machine`, `furnace` and `rocket-silo`
+#[derive(Debug, serde::Deserialize)]
+pub struct CraftingMachine {
+    pub name: String,
+    pub crafting_speed: f32,
+    pub crafting_categories: VecOrMap<String>,
+    pub energy_usage: Option<Unit>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct EnergySource {
+    #[serde(rename = "type")]
+    pub ty: String,
+    /// Factorio 2.0 style
+    pub fuel_categories: Option<VecOrMap<String>>,
+    /// Factorio 1.1 style
+    pub fuel_category: Option<String>,
+    pub effectivity: Option<f32>,
+    pub max_temperature: Option<f32>,
+    pub min_working_temperature: Option<f32>,
+}
+
+impl EnergySource {
+    pub fn fuel_categories(&self) -> Vec<String> {
+        match (&self.fuel_categories, &self.fuel_category) {
+            (Some(categories), _) => categories.to_vec(),
+            (None, Some(category)) => vec![category.clone()],
+            (None, None) => vec!["chemical".to_string()],
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct FluidBox {
+    pub filter: Option<String>,
+    pub minimum_temperature: Option<f32>,
+    pub maximum_temperature: Option<f32>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct Boiler {
+    pub name: String,
+    pub energy_consumption: Unit,
+    pub energy_source: EnergySource,
+    pub fluid_box: FluidBox,
+    pub output_fluid_box: FluidBox,
+    pub target_temperature: Option<f32>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct Generator {
+    pub name: String,
+    pub effectivity: Option<f32>,
+    pub fluid_usage_per_tick: f32,
+    pub maximum_temperature: Option<f32>,
+    pub fluid_box: FluidBox,
+    pub max_power_output: Option<Unit>,
+    pub burns_fluid: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct Reactor {
+    pub name: String,
+    pub consumption: Unit,
+    pub energy_source: EnergySource,
+    pub heat_buffer: HeatBuffer,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct HeatBuffer {
+    pub max_temperature: f32,
+    pub min_working_temperature: Option<f32>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct Resource {
+    pub name: String,
+    pub category: Option<String>,
+    pub minable: Option<Minable>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct Minable {
+    pub mining_time: f32,
+    pub result: Option<String>,
+    pub count: Option<f32>,
+    pub results: Option<VecOrMap<RecipeResult>>,
+    /// Amount of `required_fluid` per 10 mining operations
+    pub fluid_amount: Option<f32>,
+    pub required_fluid: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct MiningDrill {
+    pub name: String,
+    pub mining_speed: f32,
+    pub resource_categories: VecOrMap<String>,
+    pub energy_usage: Option<Unit>,
+    pub energy_source: EnergySource,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct Tile {
+    /// The fluid that offshore pumps can pump from this tile
+    pub fluid: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct Technology {
+    pub name: String,
+    pub prerequisites: Option<Prerequisites>,
+    pub effects: Option<VecOrMap<TechnologyEffect>>,
+    /// Not set for technologies that are researched by a trigger instead of science packs
+    pub unit: Option<TechnologyUnit>,
+    pub max_level: Option<IntOrInfinite>,
+    pub enabled: Option<bool>,
+    pub hidden: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct TechnologyEffect {
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub recipe: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct TechnologyUnit {
+    pub count: Option<f32>,
+    pub count_formula: Option<String>,
+    pub time: f32,
+    pub ingredients: VecOrMap<TechnologyIngredient>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct Lab {
+    pub name: String,
+    pub researching_speed: Option<f32>,
+    pub inputs: VecOrMap<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct TransportBelt {
+    pub name: String,
+    /// Tiles per tick
+    pub speed: f32,
+    pub hidden: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct Inserter {
+    pub name: String,
+    /// Revolutions per tick
+    pub rotation_speed: f32,
+    pub hidden: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct Pipe {
+    pub name: String,
+    pub hidden: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct Pump {
+    pub name: String,
+    /// Fluid units per tick
+    pub pumping_speed: f32,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct CargoWagon {
+    pub name: String,
+    /// Slots, each holds a stack of items
+    pub inventory_size: u32,
+    pub hidden: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct FluidWagon {
+    pub name: String,
+    pub capacity: f32,
+    pub hidden: Option<bool>,
+}
+
+/// Either `["automation-science-pack", 1]` or `{ "name": "automation-science-pack", "amount": 1 }`
+#[derive(Debug, serde::Deserialize)]
+#[serde(untagged)]
+pub enum TechnologyIngredient {
+    Tuple(String, f32),
+    Struct { name: String, amount: f32 },
+}
+
+impl TechnologyIngredient {
+    pub fn name_and_amount(&self) -> (&str, f32) {
+        match self {
+            TechnologyIngredient::Tuple(name, amount)
+            | TechnologyIngredient::Struct { name, amount } => (name, *amount),
+        }
+    }
+}
+
 // macro_rules! id {
 //     ($name:ident) => {
 //         #[derive(
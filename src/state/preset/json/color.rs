@@ -0,0 +1,73 @@
+use crate::gfx::Color;
+
+/// Colours are `{r, g, b, a}` maps or `[r, g, b, a]` lists. The components go from 0 to 1, or
+/// from 0 to 255 when any of them is above 1. Missing components are 0, and alpha is 1.
+#[derive(Debug, Clone, Copy)]
+pub struct JsonColor(pub Color);
+
+impl JsonColor {
+    fn from_components(mut rgba: [f64; 4]) -> Self {
+        if rgba.iter().any(|c| *c > 1.0) {
+            for c in &mut rgba {
+                *c /= 255.0;
+            }
+        }
+        let [r, g, b, a] = rgba.map(|c| c.clamp(0.0, 1.0) as f32);
+        JsonColor(Color::rgbaf(r, g, b, a))
+    }
+}
+
+impl<'de> serde::de::Deserialize<'de> for JsonColor {
+    fn deserialize<D>(deserializer: D) -> Result<JsonColor, D::Error>
+    where
+        D: serde::de::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = JsonColor;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a colour as a map of r, g, b and a, or a list of numbers")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::MapAccess<'de>,
+            {
+                let mut rgba = [0.0, 0.0, 0.0, 1.0];
+                while let Some(key) = map.next_key::<String>()? {
+                    let index = match key.as_str() {
+                        "r" => 0,
+                        "g" => 1,
+                        "b" => 2,
+                        "a" => 3,
+                        _ => {
+                            map.next_value::<serde::de::IgnoredAny>()?;
+                            continue;
+                        }
+                    };
+                    rgba[index] = map.next_value()?;
+                }
+                Ok(JsonColor::from_components(rgba))
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::SeqAccess<'de>,
+            {
+                let mut rgba = [0.0, 0.0, 0.0, 1.0];
+                let mut index = 0;
+                while let Some(component) = seq.next_element::<f64>()? {
+                    if index < rgba.len() {
+                        rgba[index] = component;
+                    }
+                    index += 1;
+                }
+                Ok(JsonColor::from_components(rgba))
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
@@ -16,6 +16,8 @@ pub enum Flags {
     Spawnable,
     NotRepairable,
     NotDeconstructable,
+    /// Any flag that isn't listed above, newer versions and mods add more
+    Other,
 }
 
 impl std::str::FromStr for Flags {
@@ -101,12 +103,11 @@ impl<'de> serde::de::Deserialize<'de> for Flags {
                 formatter.write_str("a string flag")
             }
 
-            fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E>
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
             where
                 E: serde::de::Error,
             {
-                v.parse()
-                    .map_err(|_| serde::de::Error::unknown_variant(v, Flags::all_names()))
+                Ok(v.parse().unwrap_or(Flags::Other))
             }
         }
 
@@ -1,21 +1,19 @@
 use rustc_hash::FxHashSet;
 
 #[derive(Debug)]
-pub struct Prerequisites<'a> {
+pub struct Prerequisites {
     pub prerequisites: FxHashSet<String>,
 }
 
-impl<'de: 'a, 'a> serde::de::Deserialize<'de> for Prerequisites<'a> {
-    fn deserialize<D>(deserializer: D) -> Result<Prerequisites<'a>, D::Error>
+impl<'de> serde::de::Deserialize<'de> for Prerequisites {
+    fn deserialize<D>(deserializer: D) -> Result<Prerequisites, D::Error>
     where
         D: serde::de::Deserializer<'de>,
     {
-        struct Visitor<'a> {
-            _marker: std::marker::PhantomData<&'a ()>,
-        }
+        struct Visitor;
 
-        impl<'de: 'a, 'a> serde::de::Visitor<'de> for Visitor<'a> {
-            type Value = Prerequisites<'a>;
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = Prerequisites;
 
             fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
                 formatter.write_str(
@@ -47,8 +45,6 @@ impl<'de: 'a, 'a> serde::de::Deserialize<'de> for Prerequisites<'a> {
             }
         }
 
-        deserializer.deserialize_any(Visitor {
-            _marker: std::marker::PhantomData,
-        })
+        deserializer.deserialize_any(Visitor)
     }
 }
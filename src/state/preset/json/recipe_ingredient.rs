@@ -1,8 +1,8 @@
-use rustc_hash::{FxHashMap, FxHashSet};
-use supervisors_derive::CustomDeserialize;
+use rustc_hash::FxHashMap;
 
-#[derive(Debug, CustomDeserialize)]
-pub struct RecipeIngredient<'a> {
+#[derive(Debug, serde::Deserialize)]
+pub struct RecipeIngredient {
+    #[serde(rename = "type")]
     pub ty: String,
     pub name: String,
     pub amount: f32,
@@ -12,6 +12,6 @@ pub struct RecipeIngredient<'a> {
     pub fluidbox_index: Option<f32>,
     pub ignored_by_stats: Option<usize>,
 
-    #[remaining]
+    #[serde(flatten)]
     pub remaining: FxHashMap<String, serde_json::Value>,
 }
@@ -1,8 +1,8 @@
-use rustc_hash::{FxHashMap, FxHashSet};
-use supervisors_derive::CustomDeserialize;
+use rustc_hash::FxHashMap;
 
-#[derive(Debug, CustomDeserialize)]
-pub struct RecipeResult<'a> {
+#[derive(Debug, serde::Deserialize)]
+pub struct RecipeResult {
+    #[serde(rename = "type")]
     pub ty: String,
     pub name: String,
     pub amount: Option<f32>,
@@ -16,6 +16,18 @@ pub struct RecipeResult<'a> {
     pub ignored_by_productivity: Option<usize>,
     pub percent_spoiled: Option<f32>,
 
-    #[remaining]
+    #[serde(flatten)]
     pub remaining: FxHashMap<String, serde_json::Value>,
 }
+
+impl RecipeResult {
+    /// The average amount produced per craft, taking `probability` and `amount_min`/`amount_max` into account
+    pub fn expected_amount(&self) -> f32 {
+        let amount = match (self.amount, self.amount_min, self.amount_max) {
+            (Some(amount), _, _) => amount,
+            (None, Some(min), Some(max)) => (min + max) / 2.0,
+            _ => 1.0,
+        };
+        amount * self.probability.unwrap_or(1.0)
+    }
+}
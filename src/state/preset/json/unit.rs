@@ -4,6 +4,18 @@ pub struct Unit {
     unit_type: UnitType,
 }
 
+impl Unit {
+    /// The value in joules or watts, depending on the unit type
+    pub fn base_value(&self) -> f64 {
+        self.amount as f64 * self.unit_type.multiplier()
+    }
+
+    /// The value in megajoules or megawatts, depending on the unit type
+    pub fn mega(&self) -> f32 {
+        (self.base_value() / 1_000_000.0) as f32
+    }
+}
+
 impl<'de> serde::de::Deserialize<'de> for Unit {
     fn deserialize<D>(deserializer: D) -> Result<Unit, D::Error>
     where
@@ -82,6 +94,16 @@ impl UnitType {
     pub fn all() -> &'static [&'static str] {
         &["J", "kJ", "MJ", "GJ", "YJ", "W", "kW", "MW", "GW"]
     }
+
+    pub fn multiplier(&self) -> f64 {
+        match self {
+            UnitType::J | UnitType::W => 1.0,
+            UnitType::KJ | UnitType::KW => 1e3,
+            UnitType::MJ | UnitType::MW => 1e6,
+            UnitType::GJ | UnitType::GW => 1e9,
+            UnitType::YJ => 1e24,
+        }
+    }
 }
 
 impl std::str::FromStr for UnitType {
@@ -0,0 +1,88 @@
+use super::{Preset, json};
+use crate::ItemOrFluidId;
+
+const TICKS_PER_SECOND: f32 = 60.0;
+/// Belts hold 8 items per tile, 4 on each of their two lanes
+const ITEMS_PER_TILE: f32 = 8.0;
+/// What a pipe run carries when there are no pumps to push it, from the vanilla fluid system
+const DEFAULT_PIPE_THROUGHPUT: f32 = 1200.0;
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Belt {
+    pub name: String,
+    /// Items per second on both lanes together
+    pub throughput: f32,
+}
+
+impl Belt {
+    pub fn lane_throughput(&self) -> f32 {
+        self.throughput / 2.0
+    }
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Inserter {
+    pub name: String,
+    /// Items per second between two chests, one item per swing and without stack bonuses
+    pub throughput: f32,
+}
+
+pub(super) fn load(preset: &mut Preset, root: &json::Root) {
+    preset.belts = root
+        .transport_belt
+        .values()
+        .filter(|b| b.hidden != Some(true))
+        .map(|b| Belt {
+            name: b.name.clone(),
+            throughput: b.speed * TICKS_PER_SECOND * ITEMS_PER_TILE,
+        })
+        .collect();
+    preset
+        .belts
+        .sort_by(|a, b| a.throughput.total_cmp(&b.throughput));
+
+    // A swing there and back is a full revolution
+    preset.inserters = root
+        .inserter
+        .values()
+        .filter(|i| i.hidden != Some(true))
+        .map(|i| Inserter {
+            name: i.name.clone(),
+            throughput: i.rotation_speed * TICKS_PER_SECOND,
+        })
+        .collect();
+    preset
+        .inserters
+        .sort_by(|a, b| a.throughput.total_cmp(&b.throughput));
+
+    // A pipe run can carry what the fastest pump pushes into it
+    preset.pipe_throughput = root.pipe.values().any(|p| p.hidden != Some(true)).then(|| {
+        root.pump
+            .values()
+            .map(|p| p.pumping_speed * TICKS_PER_SECOND)
+            .max_by(f32::total_cmp)
+            .unwrap_or(DEFAULT_PIPE_THROUGHPUT)
+    });
+}
+
+impl Preset {
+    pub fn belt(&self, name: &str) -> Option<&Belt> {
+        self.belts.iter().find(|b| b.name == name)
+    }
+
+    pub fn inserter(&self, name: &str) -> Option<&Inserter> {
+        self.inserters.iter().find(|i| i.name == name)
+    }
+
+    /// How much of the item or fluid fits in a single wagon
+    pub fn wagon_capacity(&self, item_or_fluid: &ItemOrFluidId) -> Option<f32> {
+        match item_or_fluid {
+            ItemOrFluidId::Item(name) => {
+                let stack_size = self.item_by_name(name)?.stack_size;
+                Some((self.cargo_wagon_slots? * stack_size) as f32)
+            }
+            ItemOrFluidId::Fluid(_) => self.fluid_wagon_capacity,
+            ItemOrFluidId::Electricity | ItemOrFluidId::Heat => None,
+        }
+    }
+}
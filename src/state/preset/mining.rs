@@ -0,0 +1,120 @@
+use super::{EnergySource, Preset, RecipeProduct, json};
+use crate::ItemOrFluidId;
+
+/// Ore patches, oil fields and other things that can be mined
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Resource {
+    pub name: String,
+    pub category: String,
+    /// Time in seconds that a single mining operation takes at mining speed 1
+    pub mining_time: f32,
+    pub results: Vec<RecipeProduct>,
+    pub required_fluid: Option<String>,
+    /// Amount of `required_fluid` consumed per mining operation
+    pub fluid_amount: f32,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct MiningDrill {
+    pub name: String,
+    pub mining_speed: f32,
+    pub resource_categories: Vec<String>,
+    /// In MW
+    pub energy_usage: f32,
+    pub energy_source: EnergySource,
+}
+
+/// Every level of mining productivity research adds this much productivity
+pub const MINING_PRODUCTIVITY_PER_LEVEL: f32 = 0.1;
+
+pub(super) fn load(preset: &mut Preset, root: &json::Root) {
+    for resource in root.resource.values() {
+        let Some(minable) = &resource.minable else {
+            continue;
+        };
+        let mut results: Vec<RecipeProduct> = minable
+            .results
+            .iter()
+            .flat_map(|r| r.iter())
+            .map(|r| RecipeProduct {
+                item_or_fluid: ItemOrFluidId::from_type(&r.ty, &r.name),
+                amount: r.expected_amount(),
+                temperature: r.temperature,
+            })
+            .collect();
+        if let Some(result) = &minable.result {
+            results.push(RecipeProduct {
+                item_or_fluid: ItemOrFluidId::Item(result.clone()),
+                amount: minable.count.unwrap_or(1.0),
+                temperature: None,
+            });
+        }
+        if results.is_empty() {
+            println!("Resource {:?} has no results, skipping", resource.name);
+            continue;
+        }
+
+        preset.resources.insert(
+            resource.name.clone(),
+            Resource {
+                name: resource.name.clone(),
+                category: resource
+                    .category
+                    .clone()
+                    .unwrap_or_else(|| "basic-solid".to_string()),
+                mining_time: minable.mining_time,
+                results,
+                required_fluid: minable.required_fluid.clone(),
+                fluid_amount: minable.fluid_amount.unwrap_or(0.0) / 10.0,
+            },
+        );
+    }
+
+    for drill in root.mining_drill.values() {
+        preset.mining_drills.insert(
+            drill.name.clone(),
+            MiningDrill {
+                name: drill.name.clone(),
+                mining_speed: drill.mining_speed,
+                resource_categories: drill.resource_categories.to_vec(),
+                energy_usage: drill.energy_usage.map(|u| u.mega()).unwrap_or(0.0),
+                energy_source: EnergySource::from_json(&drill.energy_source),
+            },
+        );
+    }
+
+    let mut pumpable_fluids: Vec<String> =
+        root.tile.values().filter_map(|t| t.fluid.clone()).collect();
+    pumpable_fluids.sort();
+    pumpable_fluids.dedup();
+    preset.pumpable_fluids = pumpable_fluids;
+}
+
+impl Preset {
+    /// The drill that is used by default for the given resource category. Like crafting machines
+    /// this is the slowest drill, but drills that need to be fuelled are skipped if there is an
+    /// alternative.
+    pub fn default_drill_for_category(&self, category: &str) -> Option<&MiningDrill> {
+        self.mining_drills
+            .values()
+            .filter(|d| d.resource_categories.iter().any(|c| c == category))
+            .min_by(|a, b| {
+                let a_burner = matches!(a.energy_source, EnergySource::Burner { .. });
+                let b_burner = matches!(b.energy_source, EnergySource::Burner { .. });
+                a_burner
+                    .cmp(&b_burner)
+                    .then_with(|| a.mining_speed.total_cmp(&b.mining_speed))
+                    .then_with(|| a.name.cmp(&b.name))
+            })
+    }
+
+    /// All resources that produce the given item or fluid
+    pub fn resources_producing(
+        &self,
+        item_or_fluid: &ItemOrFluidId,
+    ) -> impl Iterator<Item = &Resource> {
+        self.resources
+            .values()
+            .filter(move |r| r.results.iter().any(|p| &p.item_or_fluid == item_or_fluid))
+    }
+}
@@ -0,0 +1,218 @@
+use super::{Item, Preset, json};
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub enum EnergySource {
+    Burner {
+        fuel_categories: Vec<String>,
+        effectivity: f32,
+    },
+    Heat {
+        max_temperature: f32,
+        min_working_temperature: f32,
+    },
+    Electric,
+    /// Fluid and void energy sources, which we don't plan for
+    Other,
+}
+
+impl EnergySource {
+    pub(super) fn from_json(source: &json::EnergySource) -> Self {
+        match source.ty.as_str() {
+            "burner" => EnergySource::Burner {
+                fuel_categories: source.fuel_categories(),
+                effectivity: source.effectivity.unwrap_or(1.0),
+            },
+            "heat" => EnergySource::Heat {
+                max_temperature: source.max_temperature.unwrap_or(15.0),
+                min_working_temperature: source.min_working_temperature.unwrap_or(15.0),
+            },
+            "electric" => EnergySource::Electric,
+            _ => EnergySource::Other,
+        }
+    }
+}
+
+/// Boilers and heat exchangers
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Boiler {
+    pub name: String,
+    /// In MW
+    pub energy_consumption: f32,
+    pub energy_source: EnergySource,
+    pub input_fluid: String,
+    pub output_fluid: String,
+    pub target_temperature: f32,
+}
+
+/// Steam engines and turbines
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Generator {
+    pub name: String,
+    pub input_fluid: String,
+    /// Fluid consumed per second at full load
+    pub fluid_usage: f32,
+    pub minimum_temperature: f32,
+    pub maximum_temperature: f32,
+    pub effectivity: f32,
+    /// In MW, if the generator is capped
+    pub max_power_output: Option<f32>,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Reactor {
+    pub name: String,
+    /// In MW
+    pub consumption: f32,
+    pub energy_source: EnergySource,
+    pub max_temperature: f32,
+}
+
+/// A chain of entities that turns fuel (and water) into electricity
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PowerChain {
+    pub reactor: Option<String>,
+    pub boiler: String,
+    pub generator: String,
+}
+
+impl PowerChain {
+    pub fn label(&self) -> String {
+        match &self.reactor {
+            Some(reactor) => format!("{} > {} > {}", reactor, self.boiler, self.generator),
+            None => format!("{} > {}", self.boiler, self.generator),
+        }
+    }
+}
+
+/// Fuels that are picked over others when they are available
+const PREFERRED_FUELS: &[&str] = &["coal", "uranium-fuel-cell"];
+
+pub(super) fn load(preset: &mut Preset, root: &json::Root) {
+    for boiler in root.boiler.values() {
+        let (Some(input_fluid), Some(output_fluid)) = (
+            boiler.fluid_box.filter.clone(),
+            boiler.output_fluid_box.filter.clone(),
+        ) else {
+            println!("Boiler {:?} has no fluid filters, skipping", boiler.name);
+            continue;
+        };
+        preset.boilers.insert(
+            boiler.name.clone(),
+            Boiler {
+                name: boiler.name.clone(),
+                energy_consumption: boiler.energy_consumption.mega(),
+                energy_source: EnergySource::from_json(&boiler.energy_source),
+                input_fluid,
+                output_fluid,
+                target_temperature: boiler.target_temperature.unwrap_or(165.0),
+            },
+        );
+    }
+
+    for generator in root.generator.values() {
+        if generator.burns_fluid == Some(true) {
+            continue;
+        }
+        let Some(input_fluid) = generator.fluid_box.filter.clone() else {
+            println!(
+                "Generator {:?} has no fluid filter, skipping",
+                generator.name
+            );
+            continue;
+        };
+        preset.generators.insert(
+            generator.name.clone(),
+            Generator {
+                name: generator.name.clone(),
+                input_fluid,
+                fluid_usage: generator.fluid_usage_per_tick * 60.0,
+                minimum_temperature: generator.fluid_box.minimum_temperature.unwrap_or(15.0),
+                maximum_temperature: generator.maximum_temperature.unwrap_or(f32::MAX),
+                effectivity: generator.effectivity.unwrap_or(1.0),
+                max_power_output: generator.max_power_output.map(|p| p.mega()),
+            },
+        );
+    }
+
+    for reactor in root.reactor.values() {
+        preset.reactors.insert(
+            reactor.name.clone(),
+            Reactor {
+                name: reactor.name.clone(),
+                consumption: reactor.consumption.mega(),
+                energy_source: EnergySource::from_json(&reactor.energy_source),
+                max_temperature: reactor.heat_buffer.max_temperature,
+            },
+        );
+    }
+}
+
+impl Preset {
+    /// All combinations of reactor, boiler and generator that can produce electricity, sorted so
+    /// that the simplest chains come first
+    pub fn power_chains(&self) -> Vec<PowerChain> {
+        let mut chains = Vec::new();
+        for generator in self.generators.values() {
+            for boiler in self.boilers.values() {
+                if boiler.output_fluid != generator.input_fluid
+                    || boiler.target_temperature < generator.minimum_temperature
+                {
+                    continue;
+                }
+                match &boiler.energy_source {
+                    EnergySource::Burner { .. } => chains.push(PowerChain {
+                        reactor: None,
+                        boiler: boiler.name.clone(),
+                        generator: generator.name.clone(),
+                    }),
+                    EnergySource::Heat {
+                        min_working_temperature,
+                        ..
+                    } => {
+                        for reactor in self.reactors.values() {
+                            if reactor.max_temperature < *min_working_temperature
+                                || !matches!(reactor.energy_source, EnergySource::Burner { .. })
+                            {
+                                continue;
+                            }
+                            chains.push(PowerChain {
+                                reactor: Some(reactor.name.clone()),
+                                boiler: boiler.name.clone(),
+                                generator: generator.name.clone(),
+                            });
+                        }
+                    }
+                    // Electric boilers would feed on their own output
+                    EnergySource::Electric | EnergySource::Other => {}
+                }
+            }
+        }
+        chains.sort_by(|a, b| {
+            a.reactor
+                .is_some()
+                .cmp(&b.reactor.is_some())
+                .then_with(|| a.label().cmp(&b.label()))
+        });
+        chains
+    }
+
+    /// The fuel that is used by default for a burner with the given fuel categories
+    pub fn default_fuel(&self, fuel_categories: &[String]) -> Option<&Item> {
+        let mut fuels = self
+            .items
+            .values()
+            .filter(|i| i.fuel_value > 0.0)
+            .filter(|i| {
+                i.fuel_category
+                    .as_ref()
+                    .is_some_and(|c| fuel_categories.contains(c))
+            })
+            .collect::<Vec<_>>();
+        fuels.sort_by(|a, b| a.name.cmp(&b.name));
+        fuels
+            .iter()
+            .find(|i| PREFERRED_FUELS.contains(&i.name.as_str()))
+            .or(fuels.first())
+            .copied()
+    }
+}
@@ -0,0 +1,309 @@
+use super::{Preset, RecipeId, json};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Technology {
+    pub name: String,
+    pub prerequisites: Vec<String>,
+    /// Recipes that are unlocked by researching this technology
+    pub unlocks: Vec<RecipeId>,
+    /// `None` for technologies that are researched by a trigger, e.g. crafting an item
+    pub cost: Option<TechnologyCost>,
+    pub max_level: MaxLevel,
+    pub hidden: bool,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct TechnologyCost {
+    pub count: TechnologyCount,
+    /// Time in seconds of a single research unit
+    pub time: f32,
+    /// Science packs consumed per research unit
+    pub ingredients: Vec<(String, f32)>,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub enum TechnologyCount {
+    Fixed(f32),
+    /// A formula in terms of the level `L`, e.g. `2^(L-6)*1000`
+    Formula(String),
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Lab {
+    pub name: String,
+    pub researching_speed: f32,
+    /// The science packs that this lab accepts
+    pub inputs: Vec<String>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum MaxLevel {
+    Level(u32),
+    Infinite,
+}
+
+impl TechnologyCost {
+    /// The amount of research units needed for the given level of the technology
+    pub fn count(&self, level: u32) -> Option<f32> {
+        match &self.count {
+            TechnologyCount::Fixed(count) => Some(*count),
+            TechnologyCount::Formula(formula) => evaluate_count_formula(formula, level as f64)
+                .map(|c| c as f32)
+                .or_else(|| {
+                    println!("Failed to evaluate count formula {formula:?}");
+                    None
+                }),
+        }
+    }
+}
+
+pub(super) fn load(preset: &mut Preset, root: &json::Root) {
+    let recipe_ids: FxHashMap<&str, RecipeId> = preset
+        .recipes
+        .values()
+        .map(|r| (r.name.as_str(), r.id))
+        .collect();
+
+    let mut technologies = FxHashMap::default();
+    for technology in root.technology.values() {
+        if technology.enabled == Some(false) {
+            continue;
+        }
+        let mut prerequisites: Vec<String> = technology
+            .prerequisites
+            .iter()
+            .flat_map(|p| p.prerequisites.iter().cloned())
+            .collect();
+        prerequisites.sort();
+
+        let unlocks = technology
+            .effects
+            .iter()
+            .flat_map(|e| e.iter())
+            .filter(|e| e.ty == "unlock-recipe")
+            .filter_map(|e| e.recipe.as_deref())
+            .filter_map(|recipe| recipe_ids.get(recipe).copied())
+            .collect();
+
+        let cost = technology.unit.as_ref().map(|unit| TechnologyCost {
+            count: match (&unit.count_formula, unit.count) {
+                (Some(formula), _) => TechnologyCount::Formula(formula.clone()),
+                (None, count) => TechnologyCount::Fixed(count.unwrap_or(1.0)),
+            },
+            time: unit.time,
+            ingredients: unit
+                .ingredients
+                .iter()
+                .map(|i| {
+                    let (name, amount) = i.name_and_amount();
+                    (name.to_string(), amount)
+                })
+                .collect(),
+        });
+
+        technologies.insert(
+            technology.name.clone(),
+            Technology {
+                name: technology.name.clone(),
+                prerequisites,
+                unlocks,
+                cost,
+                max_level: match technology.max_level {
+                    Some(json::IntOrInfinite::Infinite) => MaxLevel::Infinite,
+                    Some(json::IntOrInfinite::Int(level)) => MaxLevel::Level(level as u32),
+                    None => MaxLevel::Level(1),
+                },
+                hidden: technology.hidden.unwrap_or(false),
+            },
+        );
+    }
+    preset.technologies = technologies;
+
+    for lab in root.lab.values() {
+        preset.labs.insert(
+            lab.name.clone(),
+            Lab {
+                name: lab.name.clone(),
+                researching_speed: lab.researching_speed.unwrap_or(1.0),
+                inputs: lab.inputs.to_vec(),
+            },
+        );
+    }
+}
+
+impl Technology {
+    /// The level of this technology, taken from the number at the end of its name. Upgrades like
+    /// `mining-productivity-3` are separate technologies, but share their count formula.
+    pub fn level(&self) -> u32 {
+        self.name
+            .rsplit_once('-')
+            .and_then(|(_, level)| level.parse().ok())
+            .unwrap_or(1)
+    }
+}
+
+impl Preset {
+    /// The lab that is used by default to research with the given science packs. This is the
+    /// slowest lab that accepts all of them.
+    pub fn default_lab(&self, packs: &[&str]) -> Option<&Lab> {
+        self.labs
+            .values()
+            .filter(|l| packs.iter().all(|p| l.inputs.iter().any(|i| i == p)))
+            .min_by(|a, b| {
+                a.researching_speed
+                    .total_cmp(&b.researching_speed)
+                    .then_with(|| a.name.cmp(&b.name))
+            })
+    }
+
+    /// The names of every technology that has to be researched before the given technology,
+    /// directly or indirectly. The technology itself is not included.
+    pub fn prerequisite_closure(&self, technology: &str) -> FxHashSet<String> {
+        let mut closure = FxHashSet::default();
+        let mut queue = vec![technology];
+        while let Some(name) = queue.pop() {
+            let Some(technology) = self.technologies.get(name) else {
+                println!("Unknown technology {name:?}");
+                continue;
+            };
+            for prerequisite in &technology.prerequisites {
+                if closure.insert(prerequisite.clone()) {
+                    queue.push(prerequisite);
+                }
+            }
+        }
+        closure
+    }
+}
+
+/// Evaluate a Factorio technology count formula. These support numbers, the level as `L` or `l`,
+/// `+ - * / ^` and parentheses.
+fn evaluate_count_formula(formula: &str, level: f64) -> Option<f64> {
+    let tokens: Vec<char> = formula.chars().filter(|c| !c.is_whitespace()).collect();
+    let mut parser = FormulaParser {
+        tokens: &tokens,
+        position: 0,
+        level,
+    };
+    let value = parser.expression()?;
+    (parser.position == tokens.len()).then_some(value)
+}
+
+struct FormulaParser<'a> {
+    tokens: &'a [char],
+    position: usize,
+    level: f64,
+}
+
+impl FormulaParser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.tokens.get(self.position).copied()
+    }
+
+    fn expression(&mut self) -> Option<f64> {
+        let mut value = self.term()?;
+        while let Some(op @ ('+' | '-')) = self.peek() {
+            self.position += 1;
+            let rhs = self.term()?;
+            value = if op == '+' { value + rhs } else { value - rhs };
+        }
+        Some(value)
+    }
+
+    fn term(&mut self) -> Option<f64> {
+        let mut value = self.unary()?;
+        while let Some(op @ ('*' | '/')) = self.peek() {
+            self.position += 1;
+            let rhs = self.unary()?;
+            value = if op == '*' { value * rhs } else { value / rhs };
+        }
+        Some(value)
+    }
+
+    /// Minus binds looser than `^`, like in Lua, so `-2^2` is -4
+    fn unary(&mut self) -> Option<f64> {
+        if self.peek() == Some('-') {
+            self.position += 1;
+            return Some(-self.unary()?);
+        }
+        self.power()
+    }
+
+    /// `^` is right associative, and its exponent can be negative, as in `2^-1`
+    fn power(&mut self) -> Option<f64> {
+        let base = self.atom()?;
+        if self.peek() == Some('^') {
+            self.position += 1;
+            let exponent = self.unary()?;
+            return Some(base.powf(exponent));
+        }
+        Some(base)
+    }
+
+    fn atom(&mut self) -> Option<f64> {
+        match self.peek()? {
+            '(' => {
+                self.position += 1;
+                let value = self.expression()?;
+                if self.peek() != Some(')') {
+                    return None;
+                }
+                self.position += 1;
+                Some(value)
+            }
+            'L' | 'l' => {
+                self.position += 1;
+                Some(self.level)
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                let start = self.position;
+                while self.peek().is_some_and(|c| c.is_ascii_digit() || c == '.') {
+                    self.position += 1;
+                }
+                self.tokens[start..self.position]
+                    .iter()
+                    .collect::<String>()
+                    .parse()
+                    .ok()
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::evaluate_count_formula;
+
+    fn evaluate(formula: &str, level: f64) -> f64 {
+        evaluate_count_formula(formula, level).unwrap()
+    }
+
+    #[test]
+    fn vanilla_formulas() {
+        assert_eq!(evaluate("2^(L-6)*1000", 7.0), 2000.0);
+        assert_eq!(evaluate("2^(L-6)*1000", 10.0), 16000.0);
+        assert_eq!(evaluate("L^2*1000", 3.0), 9000.0);
+        assert_eq!(evaluate("1000*(L - 6)", 8.0), 2000.0);
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(evaluate("-2^2", 0.0), -4.0);
+        assert_eq!(evaluate("2^-1", 0.0), 0.5);
+        assert_eq!(evaluate("2^3^2", 0.0), 512.0);
+        assert_eq!(evaluate("-L*3", 2.0), -6.0);
+        assert_eq!(evaluate("--2", 0.0), 2.0);
+        assert_eq!(evaluate("1+2*3", 0.0), 7.0);
+        assert_eq!(evaluate("(1+2)*3", 0.0), 9.0);
+        assert_eq!(evaluate("10-4-3", 0.0), 3.0);
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(evaluate_count_formula("2^", 0.0), None);
+        assert_eq!(evaluate_count_formula("(1+2", 0.0), None);
+        assert_eq!(evaluate_count_formula("L L", 1.0), None);
+    }
+}
@@ -0,0 +1,109 @@
+use super::{Preset, RecipeId};
+use rustc_hash::FxHashSet;
+
+/// The technologies that are researched in a factory, which decides which recipes can be used
+#[derive(Clone, Debug, Default)]
+pub struct ResearchState {
+    /// `None` if nothing has been declared, in which case everything counts as researched
+    researched: Option<FxHashSet<String>>,
+    /// The researched mining productivity level, applied to every resource node
+    pub mining_productivity: u32,
+}
+
+/// Why a recipe can't be used yet
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RecipeLock {
+    /// The recipe needs this technology, which isn't researched
+    Technology(String),
+    /// The recipe isn't enabled, and no technology unlocks it
+    Unobtainable,
+}
+
+impl std::fmt::Display for RecipeLock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecipeLock::Technology(technology) => write!(f, "requires {technology}"),
+            RecipeLock::Unobtainable => write!(f, "not unlockable"),
+        }
+    }
+}
+
+impl ResearchState {
+    pub fn is_everything(&self) -> bool {
+        self.researched.is_none()
+    }
+
+    pub fn researched_count(&self) -> usize {
+        self.researched.as_ref().map_or(0, |r| r.len())
+    }
+
+    pub fn is_researched(&self, technology: &str) -> bool {
+        self.researched
+            .as_ref()
+            .is_none_or(|r| r.contains(technology))
+    }
+
+    /// Go back to treating everything as researched
+    pub fn research_everything(&mut self) {
+        self.researched = None;
+    }
+
+    /// Mark the technology as researched, together with all of its prerequisites. Returns
+    /// `false` if the technology doesn't exist.
+    pub fn research(&mut self, preset: &Preset, technology: &str) -> bool {
+        if !preset.technologies.contains_key(technology) {
+            println!("Unknown technology {technology:?}");
+            return false;
+        }
+        let researched = self.researched.get_or_insert_with(FxHashSet::default);
+        researched.insert(technology.to_string());
+        researched.extend(preset.prerequisite_closure(technology));
+        true
+    }
+
+    /// Mark the technology as not researched, together with everything that depends on it
+    pub fn forget(&mut self, preset: &Preset, technology: &str) {
+        let Some(researched) = &mut self.researched else {
+            // Everything is researched, so start from the full tree instead
+            self.researched = Some(preset.technologies.keys().cloned().collect());
+            return self.forget(preset, technology);
+        };
+        researched.remove(technology);
+        researched.retain(|t| !preset.prerequisite_closure(t).contains(technology));
+    }
+
+    /// Replace the researched technologies with a list of names, separated by commas or
+    /// whitespace. Unknown names are skipped.
+    pub fn import(&mut self, preset: &Preset, list: &str) {
+        self.researched = Some(FxHashSet::default());
+        self.research_list(preset, list);
+    }
+
+    /// Research every technology in a list like the one `import` takes, on top of what is
+    /// already researched
+    pub fn research_list(&mut self, preset: &Preset, list: &str) {
+        for technology in list
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|t| !t.is_empty())
+        {
+            self.research(preset, technology);
+        }
+    }
+
+    /// `None` if the recipe can be used, otherwise the reason why it can't
+    pub fn recipe_lock(&self, preset: &Preset, recipe: RecipeId) -> Option<RecipeLock> {
+        if self.is_everything() || preset.recipes[&recipe].enabled {
+            return None;
+        }
+        if preset
+            .technologies_unlocking(recipe)
+            .any(|t| self.is_researched(&t.name))
+        {
+            return None;
+        }
+        match preset.technologies_unlocking(recipe).next() {
+            Some(technology) => Some(RecipeLock::Technology(technology.name.clone())),
+            None => Some(RecipeLock::Unobtainable),
+        }
+    }
+}
@@ -0,0 +1,536 @@
+//! Sub-factories saved to disk, so they can be reused in other factories or shared with others.
+//!
+//! Templates refer to recipes and machines by name instead of by id, and are rebuilt from the
+//! current preset when they are inserted.
+
+use crate::{
+    Cardinal, Connection, Graph, ItemOrFluidId, Node, NodeConstraints, NodeId, NodeKind, SocketPos,
+    TemperatureRange, nodes, state::Preset, throughput::Train, utils::Point2,
+};
+use std::path::PathBuf;
+
+/// Templates are stored as JSON files in this directory, syncing it shares the templates
+pub const TEMPLATE_DIR: &str = "templates";
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct Template {
+    pub name: String,
+    pub tags: Vec<String>,
+    pub graph: TemplateGraph,
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct TemplateGraph {
+    pub nodes: Vec<TemplateNode>,
+    pub connections: Vec<TemplateConnection>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct TemplateNode {
+    pub kind: TemplateKind,
+    pub position: (f32, f32),
+    pub direction: Cardinal,
+    #[serde(default)]
+    pub constraints: NodeConstraints,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub enum TemplateKind {
+    Recipe {
+        recipe: String,
+    },
+    Resource {
+        resource: String,
+    },
+    Boiler {
+        boiler: String,
+    },
+    Generator {
+        generator: String,
+        /// Of the fluid that is fed into the generator
+        temperature: f32,
+    },
+    Reactor {
+        reactor: String,
+    },
+    PowerDemand {
+        megawatts: f32,
+    },
+    Demand {
+        item_or_fluid: ItemOrFluidId,
+        rate: f32,
+    },
+    Factory {
+        name: String,
+        graph: TemplateGraph,
+    },
+    Source {
+        item_or_fluid: ItemOrFluidId,
+        temperature: Option<TemperatureRange>,
+    },
+    Sink {
+        item_or_fluid: ItemOrFluidId,
+        temperature: Option<TemperatureRange>,
+    },
+    Splitter {
+        item_or_fluid: ItemOrFluidId,
+        temperature: Option<TemperatureRange>,
+        ratios: Vec<f32>,
+    },
+    Merger {
+        item_or_fluid: ItemOrFluidId,
+        temperature: Option<TemperatureRange>,
+        ratios: Vec<f32>,
+    },
+    Bus {
+        lanes: Vec<ItemOrFluidId>,
+    },
+    Note {
+        text: String,
+    },
+}
+
+/// Nodes are referred to by their index in `TemplateGraph::nodes`
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct TemplateConnection {
+    pub src: usize,
+    pub src_socket: usize,
+    pub dst: usize,
+    pub dst_socket: usize,
+    #[serde(default)]
+    pub train: Option<Train>,
+    #[serde(default)]
+    pub ratio: Option<f32>,
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default)]
+    pub waypoints: Vec<(f32, f32)>,
+}
+
+impl TemplateGraph {
+    /// Nodes that can't be saved, like research demands, are left out and reported in `skipped`
+    pub fn from_graph(preset: &Preset, graph: &Graph, skipped: &mut Vec<String>) -> Self {
+        let mut ids: Vec<&NodeId> = graph.nodes.keys().collect();
+        ids.sort();
+
+        let mut template = TemplateGraph::default();
+        let mut indices = rustc_hash::FxHashMap::default();
+        for id in ids {
+            let node = &graph.nodes[id];
+            let Some(kind) = TemplateKind::from_node(preset, node, skipped) else {
+                continue;
+            };
+            indices.insert(*id, template.nodes.len());
+            template.nodes.push(TemplateNode {
+                kind,
+                position: (node.position.x, node.position.y),
+                direction: node.direction,
+                constraints: node.constraints.clone(),
+            });
+        }
+        for connection in &graph.connections {
+            let (Some(&src), Some(&dst)) = (
+                indices.get(&connection.src.node_id),
+                indices.get(&connection.dst.node_id),
+            ) else {
+                continue;
+            };
+            template.connections.push(TemplateConnection {
+                src,
+                src_socket: connection.src.socket_index,
+                dst,
+                dst_socket: connection.dst.socket_index,
+                train: connection.train,
+                ratio: connection.ratio,
+                priority: connection.priority,
+                waypoints: connection.waypoints.iter().map(|p| (p.x, p.y)).collect(),
+            });
+        }
+        template
+    }
+
+    /// Rebuild the graph from the current preset. Nodes that don't exist in the preset anymore,
+    /// and connections that don't fit, are left out and reported in `missing`. `solve` is called
+    /// on every sub-factory graph, so the sub-factory sockets get their rates.
+    pub fn instantiate(
+        &self,
+        preset: &Preset,
+        mining_productivity: u32,
+        solve: &mut dyn FnMut(&mut Graph),
+        missing: &mut Vec<String>,
+    ) -> Graph {
+        let mut graph = Graph::default();
+        let mut ids: Vec<Option<NodeId>> = Vec::with_capacity(self.nodes.len());
+        for (index, template) in self.nodes.iter().enumerate() {
+            let id = NodeId(index);
+            let position = Point2::new(template.position.0, template.position.1);
+            match template
+                .kind
+                .build(preset, id, position, mining_productivity, solve, missing)
+            {
+                Ok(mut node) => {
+                    node.direction = template.direction;
+                    node.constraints = template.constraints.clone();
+                    graph.nodes.insert(id, node);
+                    ids.push(Some(id));
+                }
+                Err(problem) => {
+                    missing.push(problem);
+                    ids.push(None);
+                }
+            }
+        }
+
+        for connection in &self.connections {
+            let (Some(Some(src)), Some(Some(dst))) =
+                (ids.get(connection.src), ids.get(connection.dst))
+            else {
+                continue;
+            };
+            let src_socket = graph.nodes[src].outputs.get(connection.src_socket);
+            let dst_socket = graph.nodes[dst].inputs.get(connection.dst_socket);
+            match (src_socket, dst_socket) {
+                (Some(output), Some(input)) if output.can_connect_to(input) => {
+                    let mut new = Connection::new(
+                        SocketPos {
+                            node_id: *src,
+                            socket_index: connection.src_socket,
+                            input: false,
+                        },
+                        SocketPos {
+                            node_id: *dst,
+                            socket_index: connection.dst_socket,
+                            input: true,
+                        },
+                    );
+                    new.train = connection.train;
+                    new.ratio = connection.ratio;
+                    new.priority = connection.priority;
+                    new.waypoints = connection
+                        .waypoints
+                        .iter()
+                        .map(|&(x, y)| Point2::new(x, y))
+                        .collect();
+                    graph.connections.push(new);
+                }
+                _ => missing.push(format!(
+                    "connection from node {} to node {} doesn't fit anymore",
+                    connection.src, connection.dst
+                )),
+            }
+        }
+        solve(&mut graph);
+        graph
+    }
+
+    /// What is missing from the preset, without building anything
+    pub fn problems(&self, preset: &Preset) -> Vec<String> {
+        let mut problems = Vec::new();
+        for node in &self.nodes {
+            problems.extend(node.kind.missing_from(preset));
+            if let TemplateKind::Factory { graph, .. } = &node.kind {
+                problems.extend(graph.problems(preset));
+            }
+        }
+        problems
+    }
+}
+
+impl TemplateKind {
+    fn from_node(preset: &Preset, node: &Node, skipped: &mut Vec<String>) -> Option<Self> {
+        Some(match &node.kind {
+            NodeKind::Recipe { recipe, .. } => TemplateKind::Recipe {
+                recipe: preset.recipes[recipe].name.clone(),
+            },
+            NodeKind::Resource { resource, .. } => TemplateKind::Resource {
+                resource: resource.clone(),
+            },
+            NodeKind::Boiler { boiler } => TemplateKind::Boiler {
+                boiler: boiler.clone(),
+            },
+            NodeKind::Generator {
+                generator,
+                temperature,
+            } => TemplateKind::Generator {
+                generator: generator.clone(),
+                temperature: *temperature,
+            },
+            NodeKind::Reactor { reactor } => TemplateKind::Reactor {
+                reactor: reactor.clone(),
+            },
+            NodeKind::PowerDemand => TemplateKind::PowerDemand {
+                megawatts: node.inputs[0].per_machine,
+            },
+            NodeKind::Demand => TemplateKind::Demand {
+                item_or_fluid: node.inputs[0].item_or_fluid.clone(),
+                rate: node.inputs[0].per_machine,
+            },
+            NodeKind::Factory { name } => TemplateKind::Factory {
+                name: name.clone(),
+                graph: node
+                    .factory
+                    .as_deref()
+                    .map(|graph| TemplateGraph::from_graph(preset, graph, skipped))
+                    .unwrap_or_default(),
+            },
+            NodeKind::Source => TemplateKind::Source {
+                item_or_fluid: node.outputs[0].item_or_fluid.clone(),
+                temperature: node.outputs[0].temperature,
+            },
+            NodeKind::Sink => TemplateKind::Sink {
+                item_or_fluid: node.inputs[0].item_or_fluid.clone(),
+                temperature: node.inputs[0].temperature,
+            },
+            NodeKind::Splitter { ratios } => TemplateKind::Splitter {
+                item_or_fluid: node.inputs[0].item_or_fluid.clone(),
+                temperature: node.inputs[0].temperature,
+                ratios: ratios.clone(),
+            },
+            NodeKind::Merger { ratios } => TemplateKind::Merger {
+                item_or_fluid: node.outputs[0].item_or_fluid.clone(),
+                temperature: node.outputs[0].temperature,
+                ratios: ratios.clone(),
+            },
+            NodeKind::Bus => TemplateKind::Bus {
+                lanes: node
+                    .inputs
+                    .iter()
+                    .map(|lane| lane.item_or_fluid.clone())
+                    .collect(),
+            },
+            NodeKind::Note { text } => TemplateKind::Note { text: text.clone() },
+            NodeKind::ScienceDemand { .. } => {
+                skipped.push("research demands aren't saved in templates".to_string());
+                return None;
+            }
+        })
+    }
+
+    /// Everything the node needs that isn't in the preset, and ratios that can't be used
+    fn missing_from(&self, preset: &Preset) -> Vec<String> {
+        let (kind, name, exists) = match self {
+            TemplateKind::Recipe { recipe } => {
+                ("recipe", recipe, preset.recipe_by_name(recipe).is_some())
+            }
+            TemplateKind::Resource { resource } => (
+                "resource",
+                resource,
+                preset.resources.contains_key(resource),
+            ),
+            TemplateKind::Boiler { boiler } => {
+                ("boiler", boiler, preset.boilers.contains_key(boiler))
+            }
+            TemplateKind::Generator { generator, .. } => (
+                "generator",
+                generator,
+                preset.generators.contains_key(generator),
+            ),
+            TemplateKind::Reactor { reactor } => {
+                ("reactor", reactor, preset.reactors.contains_key(reactor))
+            }
+            TemplateKind::Demand { item_or_fluid, .. }
+            | TemplateKind::Source { item_or_fluid, .. }
+            | TemplateKind::Sink { item_or_fluid, .. } => {
+                return missing_item_or_fluid(preset, item_or_fluid)
+                    .into_iter()
+                    .collect();
+            }
+            TemplateKind::Splitter {
+                item_or_fluid,
+                ratios,
+                ..
+            }
+            | TemplateKind::Merger {
+                item_or_fluid,
+                ratios,
+                ..
+            } => {
+                let mut missing: Vec<String> = missing_item_or_fluid(preset, item_or_fluid)
+                    .into_iter()
+                    .collect();
+                if !nodes::valid_ratios(ratios) {
+                    missing.push(format!("invalid ratios {ratios:?}"));
+                }
+                return missing;
+            }
+            TemplateKind::Bus { lanes } => {
+                return lanes
+                    .iter()
+                    .filter_map(|lane| missing_item_or_fluid(preset, lane))
+                    .collect();
+            }
+            TemplateKind::PowerDemand { .. }
+            | TemplateKind::Factory { .. }
+            | TemplateKind::Note { .. } => return Vec::new(),
+        };
+        (!exists)
+            .then(|| format!("missing {kind} {name}"))
+            .into_iter()
+            .collect()
+    }
+
+    fn build(
+        &self,
+        preset: &Preset,
+        id: NodeId,
+        position: Point2,
+        mining_productivity: u32,
+        solve: &mut dyn FnMut(&mut Graph),
+        missing: &mut Vec<String>,
+    ) -> Result<Node, String> {
+        let problems = self.missing_from(preset);
+        if !problems.is_empty() {
+            return Err(problems.join(", "));
+        }
+        Ok(match self {
+            TemplateKind::Recipe { recipe } => {
+                let recipe = preset.recipe_by_name(recipe).unwrap();
+                nodes::recipe_node(preset, id, position, recipe.id)
+            }
+            TemplateKind::Resource { resource } => nodes::resource_node(
+                preset,
+                id,
+                position,
+                &preset.resources[resource],
+                mining_productivity,
+            ),
+            TemplateKind::Boiler { boiler } => {
+                nodes::boiler_node(preset, id, position, &preset.boilers[boiler])
+            }
+            TemplateKind::Generator {
+                generator,
+                temperature,
+            } => nodes::generator_node(
+                preset,
+                id,
+                position,
+                &preset.generators[generator],
+                *temperature,
+            ),
+            TemplateKind::Reactor { reactor } => {
+                nodes::reactor_node(preset, id, position, &preset.reactors[reactor])
+            }
+            TemplateKind::PowerDemand { megawatts } => {
+                nodes::power_demand_node(id, position, *megawatts)
+            }
+            TemplateKind::Demand {
+                item_or_fluid,
+                rate,
+            } => nodes::demand_node(id, position, item_or_fluid.clone(), *rate),
+            TemplateKind::Factory { name, graph } => {
+                let graph = graph.instantiate(preset, mining_productivity, solve, missing);
+                nodes::factory_node(id, position, name.clone(), graph)
+            }
+            TemplateKind::Source {
+                item_or_fluid,
+                temperature,
+            } => nodes::source_node(id, position, item_or_fluid.clone(), *temperature),
+            TemplateKind::Sink {
+                item_or_fluid,
+                temperature,
+            } => nodes::sink_node(id, position, item_or_fluid.clone(), *temperature),
+            TemplateKind::Splitter {
+                item_or_fluid,
+                temperature,
+                ratios,
+            } => nodes::splitter_node(
+                id,
+                position,
+                item_or_fluid.clone(),
+                *temperature,
+                ratios.clone(),
+            ),
+            TemplateKind::Merger {
+                item_or_fluid,
+                temperature,
+                ratios,
+            } => nodes::merger_node(
+                id,
+                position,
+                item_or_fluid.clone(),
+                *temperature,
+                ratios.clone(),
+            ),
+            TemplateKind::Bus { lanes } => nodes::bus_node(id, position, lanes.clone()),
+            TemplateKind::Note { text } => nodes::note_node(id, position, text.clone()),
+        })
+    }
+}
+
+fn missing_item_or_fluid(preset: &Preset, item_or_fluid: &ItemOrFluidId) -> Option<String> {
+    match item_or_fluid {
+        ItemOrFluidId::Item(name) if preset.item_by_name(name).is_none() => {
+            Some(format!("missing item {name}"))
+        }
+        ItemOrFluidId::Fluid(name) if preset.fluid_by_name(name).is_none() => {
+            Some(format!("missing fluid {name}"))
+        }
+        _ => None,
+    }
+}
+
+impl Template {
+    fn path(&self) -> PathBuf {
+        let file_name: String = self
+            .name
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        PathBuf::from(TEMPLATE_DIR).join(format!("{file_name}.json"))
+    }
+
+    /// Fails instead of overwriting the file of another template whose name maps to the same file
+    /// name, like "a b" and "a_b"
+    pub fn save(&self) -> std::io::Result<()> {
+        std::fs::create_dir_all(TEMPLATE_DIR)?;
+        let path = self.path();
+        if let Ok(data) = std::fs::read_to_string(&path) {
+            let existing = serde_json::from_str::<Template>(&data).ok().map(|t| t.name);
+            if existing.as_ref() != Some(&self.name) {
+                let owner = match existing {
+                    Some(name) => format!("template {name:?}"),
+                    None => "a file that isn't a template".to_string(),
+                };
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    format!("{} is already used by {owner}", path.display()),
+                ));
+            }
+        }
+        let json = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, json)
+    }
+
+    /// All templates in the template directory, sorted by name. Files that can't be read are
+    /// reported and skipped.
+    pub fn load_all() -> Vec<Template> {
+        let Ok(entries) = std::fs::read_dir(TEMPLATE_DIR) else {
+            return Vec::new();
+        };
+        let mut templates: Vec<Template> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|e| e == "json"))
+            .filter_map(|path| {
+                let template = std::fs::read_to_string(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|data| serde_json::from_str(&data).map_err(|e| e.to_string()));
+                match template {
+                    Ok(template) => Some(template),
+                    Err(e) => {
+                        println!("Failed to load template {}: {e}", path.display());
+                        None
+                    }
+                }
+            })
+            .collect();
+        templates.sort_by(|a, b| a.name.cmp(&b.name));
+        templates
+    }
+}
@@ -0,0 +1,169 @@
+//! How many belt lanes, pipes, inserters and trains a connection needs
+
+use crate::{ItemOrFluidId, state::Preset};
+
+/// The belt and inserter tiers that connections are measured against
+#[derive(Clone, Debug, Default)]
+pub struct LogisticsSettings {
+    pub belt: Option<String>,
+    pub inserter: Option<String>,
+}
+
+impl LogisticsSettings {
+    /// Start with the slowest belt and inserter
+    pub fn new(preset: &Preset) -> Self {
+        Self {
+            belt: preset.belts.first().map(|b| b.name.clone()),
+            inserter: preset.inserters.first().map(|i| i.name.clone()),
+        }
+    }
+
+    pub fn next_belt(&mut self, preset: &Preset) {
+        let names: Vec<&String> = preset.belts.iter().map(|b| &b.name).collect();
+        self.belt = next(&names, self.belt.as_ref());
+    }
+
+    pub fn next_inserter(&mut self, preset: &Preset) {
+        let names: Vec<&String> = preset.inserters.iter().map(|i| &i.name).collect();
+        self.inserter = next(&names, self.inserter.as_ref());
+    }
+}
+
+fn next(names: &[&String], current: Option<&String>) -> Option<String> {
+    let index = names
+        .iter()
+        .position(|n| Some(*n) == current)
+        .map_or(0, |i| (i + 1) % names.len());
+    names.get(index).map(|n| (*n).clone())
+}
+
+/// What it takes to move the rate of a connection
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Transport {
+    Belt {
+        lanes: u32,
+        /// Inserters that are needed to take the items off the belt
+        inserters: Option<u32>,
+    },
+    Pipe {
+        /// The fraction of a single pipe run that is used
+        utilization: f32,
+    },
+    /// Electricity and heat, or there is nothing in the preset to carry it
+    None,
+}
+
+impl Transport {
+    pub fn new(
+        preset: &Preset,
+        settings: &LogisticsSettings,
+        item_or_fluid: &ItemOrFluidId,
+        rate: f32,
+    ) -> Self {
+        match item_or_fluid {
+            ItemOrFluidId::Item(_) => {
+                let Some(belt) = settings.belt.as_deref().and_then(|b| preset.belt(b)) else {
+                    return Transport::None;
+                };
+                let inserters = settings
+                    .inserter
+                    .as_deref()
+                    .and_then(|i| preset.inserter(i))
+                    .map(|i| whole(rate / i.throughput));
+                Transport::Belt {
+                    lanes: whole(rate / belt.lane_throughput()),
+                    inserters,
+                }
+            }
+            ItemOrFluidId::Fluid(_) => match preset.pipe_throughput {
+                Some(throughput) => Transport::Pipe {
+                    utilization: rate / throughput,
+                },
+                None => Transport::None,
+            },
+            ItemOrFluidId::Electricity | ItemOrFluidId::Heat => Transport::None,
+        }
+    }
+
+    /// More than a single belt or pipe run can carry
+    pub fn overloaded(&self) -> bool {
+        match *self {
+            Transport::Belt { lanes, .. } => lanes > 2,
+            Transport::Pipe { utilization } => utilization > 1.0,
+            Transport::None => false,
+        }
+    }
+}
+
+/// Rounds up, ignoring float noise just above a whole number
+fn whole(amount: f32) -> u32 {
+    (amount - 1e-4).ceil().max(0.0) as u32
+}
+
+impl std::fmt::Display for Transport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Transport::Belt { lanes, inserters } => {
+                write!(f, "{lanes} lanes")?;
+                if self.overloaded() {
+                    write!(f, " ({} belts)", lanes.div_ceil(2))?;
+                }
+                if let Some(inserters) = inserters {
+                    write!(f, ", {inserters} inserters")?;
+                }
+                Ok(())
+            }
+            Transport::Pipe { utilization } => write!(f, "pipe {:.0}%", utilization * 100.0),
+            Transport::None => Ok(()),
+        }
+    }
+}
+
+/// A connection that is carried by trains instead of belts or pipes, like from an outpost to the
+/// main base
+#[derive(Copy, Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Train {
+    /// Seconds for a train to load, travel, unload and come back
+    pub round_trip: f32,
+    /// The most wagons that a single train pulls
+    pub max_wagons: u32,
+}
+
+impl Default for Train {
+    fn default() -> Self {
+        Self {
+            round_trip: 120.0,
+            max_wagons: 4,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TrainPlan {
+    pub trains: u32,
+    pub wagons_per_train: u32,
+}
+
+impl Train {
+    /// The trains needed to carry `rate` per second, `None` if there is no wagon for it
+    pub fn plan(
+        &self,
+        preset: &Preset,
+        item_or_fluid: &ItemOrFluidId,
+        rate: f32,
+    ) -> Option<TrainPlan> {
+        let capacity = preset.wagon_capacity(item_or_fluid)?;
+        let wagons = whole(rate * self.round_trip / capacity).max(1);
+        let trains = wagons.div_ceil(self.max_wagons.max(1));
+        Some(TrainPlan {
+            trains,
+            wagons_per_train: wagons.div_ceil(trains),
+        })
+    }
+}
+
+impl std::fmt::Display for TrainPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} x {} wagons", self.trains, self.wagons_per_train)
+    }
+}
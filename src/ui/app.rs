@@ -1,46 +1,1072 @@
 use super::{
     PopupClickResult,
     context_menu::{ContextMenu, ContextMenuItem},
-    drag::{Drag, DragState},
+    cost_table::CostTable,
+    drag::{Drag, DragState, Moved},
+    explorer::{Explorer, ExplorerClick},
     hover::Hover,
+    minimap::Minimap,
+    prompt::Prompt,
+    rate_unit::RateUnit,
+    routing::{self, EdgeRouting, RouteRequest},
     selector::Selector,
-    utils::get_node_socket_position,
+    spatial::SpatialIndex,
+    templates::{BrowserClick, TemplateBrowser},
+    utils::{
+        bezier_midpoint, bezier_point, bezier_tangent, get_node_position, get_node_socket_position,
+    },
 };
 use crate::{
-    BEZIER_CURVE_FACTOR, Connection, Node, NodeId, SocketPos,
+    BEZIER_CURVE_FACTOR, Connection, Graph, InOutput, ItemOrFluidId, Node, NodeConstraints, NodeId,
+    NodeKind, SocketPos, TemperatureRange,
+    chain::{self, ChainSettings},
+    cost::{self, RawBreakdown},
+    factory,
     gfx::{DrawWorldCtx, EventCtx, KeyCode, MouseButton, Paint},
-    state::{Preset, Theme},
-    utils::{self, Point2, Rectangle, Vec2},
+    nodes,
+    reachability::{self, Reachability},
+    science,
+    solver::{self, MachineRounding, SolveWarning},
+    state::{PowerChain, Preset, RecipeId, RecipeLock, ResearchState, Theme},
+    template::{Template, TemplateGraph},
+    throughput::{LogisticsSettings, Train, Transport},
+    utils::{Point2, Rectangle, Vec2},
 };
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::time::Instant;
+
+/// Below this zoom text is too small to read, and only icons are drawn
+const LABEL_MIN_ZOOM: f32 = 0.5;
+/// Below this zoom the icons on sockets are too small to recognise
+const SOCKET_ICON_MIN_ZOOM: f32 = 0.3;
 
 pub struct App {
-    #[allow(dead_code)]
     preset: Preset,
     nodes: FxHashMap<NodeId, Node>,
     connections: Vec<Connection>,
     theme: Theme,
     hover: Hover,
     dragging: Drag,
+    research: ResearchState,
+    chain_settings: ChainSettings,
+    /// Whether selectors mark the things that can't be made from raw resources
+    show_reachability: bool,
+    research_cache: ResearchCache,
+    /// Shown until the next solve
+    solve_warning: Option<SolveWarning>,
+    rounding: MachineRounding,
+    rate_unit: RateUnit,
+    routing: EdgeRouting,
+    /// Dots travel along connections, faster for higher rates
+    animate_flows: bool,
+    /// For animations
+    started: Instant,
+    show_minimap: bool,
+    minimap: Minimap,
+    /// Where nodes and connections are and how connections are routed. `None` after the graph
+    /// changed, until it is needed again.
+    graph_cache: Option<GraphCache>,
+    /// What flows through the sockets, from the last solve
+    totals: SocketTotals,
+    logistics: LogisticsSettings,
 
     context_menu: Option<ContextMenu>,
     selector: Option<Selector>,
+    prompt: Option<Prompt>,
+    cost_table: Option<CostTable>,
+    explorer: Option<Explorer>,
+    templates: Option<TemplateBrowser>,
+
+    /// The factories around the sub-factory that is open, outermost first
+    breadcrumbs: Vec<Breadcrumb>,
+}
+
+/// Analyses of the recipes that only change with the research. They are made when they are first
+/// needed, and thrown away when something is researched or forgotten.
+#[derive(Default)]
+struct ResearchCache {
+    raw_costs: Option<FxHashMap<ItemOrFluidId, RawBreakdown>>,
+    reachability: Option<Reachability>,
+}
+
+impl ResearchCache {
+    fn raw_costs(
+        &mut self,
+        preset: &Preset,
+        research: &ResearchState,
+    ) -> &FxHashMap<ItemOrFluidId, RawBreakdown> {
+        self.raw_costs
+            .get_or_insert_with(|| cost::raw_costs(preset, research))
+    }
+
+    /// What selectors show: the raw costs, and the reachability of everything if `reachability`
+    fn for_selector(
+        &mut self,
+        preset: &Preset,
+        research: &ResearchState,
+        reachability: bool,
+    ) -> (
+        &FxHashMap<ItemOrFluidId, RawBreakdown>,
+        Option<&Reachability>,
+    ) {
+        let costs = self
+            .raw_costs
+            .get_or_insert_with(|| cost::raw_costs(preset, research));
+        let reachability = reachability.then(|| {
+            &*self
+                .reachability
+                .get_or_insert_with(|| reachability::analyze(preset, research))
+        });
+        (costs, reachability)
+    }
+}
+
+/// What is slow to work out about the open graph, made once for every change to the graph
+struct GraphCache {
+    /// Where every node and connection is, for hover tests and to skip drawing what is off
+    /// screen
+    spatial: SpatialIndex<Indexed>,
+    /// The route of every connection, by its index. `None` when connections are drawn as curves.
+    routes: Option<Vec<Vec<Point2>>>,
+    /// Connections of what is being dragged were routed on their own, and get their lanes among
+    /// the other routes back when the drag ends
+    dragged: bool,
+}
+
+/// The rates through every socket that has connections
+#[derive(Default)]
+struct SocketTotals {
+    /// Connections and total rate of every output, for the share of each connection
+    outputs: FxHashMap<SocketPos, (usize, f32)>,
+    /// What every connected input gets, to find the ones that aren't supplied
+    supplied: FxHashMap<SocketPos, f32>,
+}
+
+impl SocketTotals {
+    fn new(connections: &[Connection]) -> Self {
+        let mut totals = Self::default();
+        for connection in connections {
+            let (count, total) = totals.outputs.entry(connection.src).or_default();
+            *count += 1;
+            *total += connection.rate;
+            *totals.supplied.entry(connection.dst).or_default() += connection.rate;
+        }
+        totals
+    }
+}
+
+/// A factory that was left to open one of its sub-factories
+struct Breadcrumb {
+    /// The sub-factory that was opened
+    node: NodeId,
+    name: String,
+    nodes: FxHashMap<NodeId, Node>,
+    connections: Vec<Connection>,
+    /// The sockets inside that the inputs and outputs of the sub-factory stood for when it was
+    /// opened
+    inputs: Vec<SocketPos>,
+    outputs: Vec<SocketPos>,
 }
 
 impl App {
     pub fn new(preset: Preset) -> Self {
         Self {
+            logistics: LogisticsSettings::new(&preset),
             preset,
-            nodes: utils::demo_nodes(),
+            nodes: FxHashMap::default(),
             connections: Vec::new(),
             theme: Theme::default(),
             hover: Hover::None,
             dragging: Drag::default(),
+            research: ResearchState::default(),
+            chain_settings: ChainSettings::default(),
+            show_reachability: false,
+            research_cache: ResearchCache::default(),
+            solve_warning: None,
+            rounding: MachineRounding::default(),
+            rate_unit: RateUnit::default(),
+            routing: EdgeRouting::default(),
+            animate_flows: false,
+            started: Instant::now(),
+            show_minimap: true,
+            minimap: Minimap::default(),
+            graph_cache: None,
+            totals: SocketTotals::default(),
             context_menu: None,
             selector: None,
+            prompt: None,
+            cost_table: None,
+            explorer: None,
+            templates: None,
+            breadcrumbs: Vec::new(),
+        }
+    }
+
+    fn next_node_id(&self) -> NodeId {
+        NodeId(self.nodes.keys().map(|id| id.0 + 1).max().unwrap_or(0))
+    }
+
+    fn add_node(&mut self, node: Node) -> NodeId {
+        let id = node.id;
+        self.nodes.insert(id, node);
+        self.graph_changed();
+        id
+    }
+
+    /// Forget where things are and how connections are routed, after nodes, connections or
+    /// waypoints changed
+    fn graph_changed(&mut self) {
+        self.graph_cache = None;
+    }
+
+    fn remove_node(&mut self, node_id: NodeId) {
+        self.nodes.remove(&node_id);
+        self.connections
+            .retain(|c| c.src.node_id != node_id && c.dst.node_id != node_id);
+        self.solve();
+    }
+
+    /// Nodes with recipes that aren't researched yet
+    fn locked_nodes(&self, nodes: &FxHashMap<NodeId, Node>) -> FxHashSet<NodeId> {
+        nodes
+            .values()
+            .filter(|n| self.node_lock(n).is_some())
+            .map(|n| n.id)
+            .collect()
+    }
+
+    fn solve(&mut self) {
+        let locked = self.locked_nodes(&self.nodes);
+        self.solve_warning = solver::solve(
+            &mut self.nodes,
+            &mut self.connections,
+            &locked,
+            self.rounding,
+        );
+        self.totals = SocketTotals::new(&self.connections);
+        self.graph_changed();
+    }
+
+    /// Solve a graph that isn't the open one, like the graph of a new sub-factory
+    fn solve_graph(&self, graph: &mut Graph) {
+        let locked = self.locked_nodes(&graph.nodes);
+        if let Some(warning) = solver::solve(
+            &mut graph.nodes,
+            &mut graph.connections,
+            &locked,
+            self.rounding,
+        ) {
+            println!("Sub-factory: {warning}");
+        }
+    }
+
+    fn open_template_browser(&mut self, pos: Point2) {
+        self.templates = Some(TemplateBrowser::new(
+            &self.preset,
+            Template::load_all(),
+            pos,
+        ));
+    }
+
+    /// Insert the template as a sub-factory. Whatever is missing from the preset is left out and
+    /// reported.
+    fn insert_template(&mut self, pos: Point2, template: &Template) {
+        let mut missing = Vec::new();
+        let graph = template.graph.instantiate(
+            &self.preset,
+            self.research.mining_productivity,
+            &mut |graph| self.solve_graph(graph),
+            &mut missing,
+        );
+        for problem in &missing {
+            println!("Template {:?}: {problem}", template.name);
+        }
+        let node = nodes::factory_node(self.next_node_id(), pos, template.name.clone(), graph);
+        self.add_node(node);
+        self.solve();
+    }
+
+    fn save_template(&mut self, node_id: NodeId, tags: &str) {
+        let node = &self.nodes[&node_id];
+        let (NodeKind::Factory { name }, Some(graph)) = (&node.kind, node.factory.as_deref())
+        else {
+            return;
+        };
+        let mut skipped = Vec::new();
+        let template = Template {
+            name: name.clone(),
+            tags: tags
+                .split(',')
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty())
+                .collect(),
+            graph: TemplateGraph::from_graph(&self.preset, graph, &mut skipped),
+        };
+        for problem in &skipped {
+            println!("Template {:?}: {problem}", template.name);
+        }
+        match template.save() {
+            Ok(()) => println!("Saved template {:?}", template.name),
+            Err(e) => println!("Failed to save template {:?}: {e}", template.name),
+        }
+    }
+
+    fn add_factory(&mut self, pos: Point2, name: String) {
+        let node = nodes::factory_node(self.next_node_id(), pos, name, Graph::default());
+        self.add_node(node);
+        self.solve();
+    }
+
+    /// Show the graph of a sub-factory instead of the current one
+    fn enter_factory(&mut self, node_id: NodeId) {
+        let Some(node) = self.nodes.get_mut(&node_id) else {
+            return;
+        };
+        let NodeKind::Factory { name } = &node.kind else {
+            return;
+        };
+        let name = name.clone();
+        let graph = node.factory.take().unwrap_or_default();
+        let (inputs, outputs) = factory::sockets(&graph);
+        self.breadcrumbs.push(Breadcrumb {
+            node: node_id,
+            name,
+            inputs: inputs.into_iter().map(|(pos, _)| pos).collect(),
+            outputs: outputs.into_iter().map(|(pos, _)| pos).collect(),
+            nodes: std::mem::replace(&mut self.nodes, graph.nodes),
+            connections: std::mem::replace(&mut self.connections, graph.connections),
+        });
+        self.hover = Hover::None;
+        self.dragging.clear();
+        self.totals = SocketTotals::new(&self.connections);
+        self.graph_changed();
+    }
+
+    /// Go back to the factory around the open sub-factory, and update the sockets of the
+    /// sub-factory to the rates of its graph
+    fn leave_factory(&mut self) {
+        let Some(breadcrumb) = self.breadcrumbs.pop() else {
+            return;
+        };
+        let graph = Graph {
+            nodes: std::mem::replace(&mut self.nodes, breadcrumb.nodes),
+            connections: std::mem::replace(&mut self.connections, breadcrumb.connections),
+        };
+        let (inputs, outputs) = factory::sockets(&graph);
+        let input_indices = factory::remap(&breadcrumb.inputs, &inputs);
+        let output_indices = factory::remap(&breadcrumb.outputs, &outputs);
+        let node = self.nodes.get_mut(&breadcrumb.node).unwrap();
+        node.inputs = inputs.into_iter().map(|(_, socket)| socket).collect();
+        node.outputs = outputs.into_iter().map(|(_, socket)| socket).collect();
+        node.factory = Some(Box::new(graph));
+
+        // Connections follow the socket inside that they were connected to. Those to sockets
+        // that are gone, or that now carry something else, are dropped.
+        let nodes = &self.nodes;
+        self.connections.retain_mut(|c| {
+            for (pos, indices) in [(&mut c.src, &output_indices), (&mut c.dst, &input_indices)] {
+                if pos.node_id != breadcrumb.node {
+                    continue;
+                }
+                match indices.get(pos.socket_index).copied().flatten() {
+                    Some(index) => pos.socket_index = index,
+                    None => return false,
+                }
+            }
+            let src = nodes[&c.src.node_id].outputs.get(c.src.socket_index);
+            let dst = nodes[&c.dst.node_id].inputs.get(c.dst.socket_index);
+            matches!((src, dst), (Some(src), Some(dst)) if src.can_connect_to(dst))
+        });
+        self.hover = Hover::None;
+        self.dragging.clear();
+        self.solve();
+    }
+
+    /// The clickable names of the open factories, the outermost factory is at depth 0
+    fn breadcrumb_layout(&self) -> Vec<(Rectangle, usize, &str)> {
+        if self.breadcrumbs.is_empty() {
+            return Vec::new();
+        }
+        let names =
+            std::iter::once("Factory").chain(self.breadcrumbs.iter().map(|b| b.name.as_str()));
+        let mut x = 10.;
+        names
+            .enumerate()
+            .map(|(depth, name)| {
+                let rect = Rectangle::new(x, 10., name.len() as f32 * 8. + 20., 24.);
+                x += rect.width + 10.;
+                (rect, depth, name)
+            })
+            .collect()
+    }
+
+    fn breadcrumb_at(&self, mouse: Point2) -> Option<usize> {
+        self.breadcrumb_layout()
+            .into_iter()
+            .find(|(rect, _, _)| rect.contains(mouse))
+            .map(|(_, depth, _)| depth)
+    }
+
+    /// Change the constraints of a node, and rebalance the factory around them
+    fn constrain(&mut self, node: NodeId, change: impl FnOnce(&mut NodeConstraints)) {
+        change(&mut self.nodes.get_mut(&node).unwrap().constraints);
+        self.solve();
+    }
+
+    /// Context menu items that pin or clear the constraints of a node
+    fn constraint_menu_items(&self, node_id: NodeId) -> Vec<ContextMenuItem> {
+        let node = &self.nodes[&node_id];
+        let constraints = &node.constraints;
+        let mut items = Vec::new();
+
+        items.push(match constraints.machines {
+            Some(machines) => {
+                ContextMenuItem::new(format!("Unfix machines ({machines})"), move |app| {
+                    app.constrain(node_id, |c| c.machines = None)
+                })
+            }
+            None => {
+                let current = node.machines.ceil().max(1.0);
+                ContextMenuItem::new("Fix machines", move |app| {
+                    app.prompt = Some(Prompt::new_number("Machines", current, move |n, app| {
+                        app.constrain(node_id, |c| c.machines = Some(n.max(0.0)))
+                    }));
+                })
+            }
+        });
+
+        for (index, output) in node.outputs.iter().enumerate() {
+            let name = output.item_or_fluid.name().to_string();
+            items.push(match constraints.output_rate {
+                Some((i, rate)) if i == index => {
+                    ContextMenuItem::new(format!("Unfix {name} rate ({rate}/s)"), move |app| {
+                        app.constrain(node_id, |c| c.output_rate = None)
+                    })
+                }
+                _ => {
+                    let current = output.rate;
+                    ContextMenuItem::new(format!("Fix {name} rate"), move |app| {
+                        app.prompt = Some(Prompt::new_number(
+                            format!("{name} per second"),
+                            current,
+                            move |rate, app| {
+                                app.constrain(node_id, |c| {
+                                    c.output_rate = Some((index, rate.max(0.0)))
+                                })
+                            },
+                        ));
+                    })
+                }
+            });
+        }
+
+        for (index, input) in node.inputs.iter().enumerate() {
+            let name = input.item_or_fluid.name().to_string();
+            items.push(match constraints.max_input_rate(index) {
+                Some(max) => {
+                    ContextMenuItem::new(format!("Unlimit {name} input ({max}/s)"), move |app| {
+                        app.constrain(node_id, |c| c.max_input_rates.retain(|(i, _)| *i != index))
+                    })
+                }
+                None => {
+                    let current = input.rate;
+                    ContextMenuItem::new(format!("Limit {name} input"), move |app| {
+                        app.prompt = Some(Prompt::new_number(
+                            format!("Max {name} per second"),
+                            current,
+                            move |max, app| {
+                                app.constrain(node_id, |c| {
+                                    c.max_input_rates.push((index, max.max(0.0)))
+                                })
+                            },
+                        ));
+                    })
+                }
+            });
+        }
+
+        items.push(ContextMenuItem::new(
+            if constraints.integer {
+                "Allow partial machines"
+            } else {
+                "Whole machines only"
+            },
+            move |app| app.constrain(node_id, |c| c.integer = !c.integer),
+        ));
+        if !constraints.is_empty() {
+            items.push(ContextMenuItem::new("Clear constraints", move |app| {
+                app.constrain(node_id, |c| *c = NodeConstraints::default())
+            }));
+        }
+        items
+    }
+
+    /// Change the train of every connection at the socket
+    fn set_train(&mut self, pos: SocketPos, change: impl Fn(&mut Option<Train>)) {
+        for connection in self.connections.iter_mut().filter(|c| c.has_socket(pos)) {
+            change(&mut connection.train);
+        }
+    }
+
+    /// Context menu items that move the connections at the socket by train
+    fn train_menu_items(&self, pos: SocketPos) -> Vec<ContextMenuItem> {
+        let mut connections = self.connections.iter().filter(|c| c.has_socket(pos));
+        let Some(first) = connections.next() else {
+            return Vec::new();
+        };
+        let Some(train) = first.train.or_else(|| connections.find_map(|c| c.train)) else {
+            return vec![ContextMenuItem::new("Send by train", move |app| {
+                app.set_train(pos, |t| *t = Some(Train::default()))
+            })];
+        };
+
+        vec![
+            ContextMenuItem::new("Send by belt", move |app| app.set_train(pos, |t| *t = None)),
+            ContextMenuItem::new(format!("Round trip: {}s", train.round_trip), move |app| {
+                app.prompt = Some(Prompt::new_number(
+                    "Round trip (seconds)",
+                    train.round_trip,
+                    move |seconds, app| {
+                        app.set_train(pos, |t| {
+                            *t = Some(Train {
+                                round_trip: seconds.max(1.0),
+                                ..t.unwrap_or(train)
+                            })
+                        })
+                    },
+                ));
+            }),
+            ContextMenuItem::new(
+                format!("Wagons per train: {}", train.max_wagons),
+                move |app| {
+                    app.prompt = Some(Prompt::new_number(
+                        "Most wagons per train",
+                        train.max_wagons as f32,
+                        move |wagons, app| {
+                            app.set_train(pos, |t| {
+                                *t = Some(Train {
+                                    max_wagons: wagons.round().max(1.0) as u32,
+                                    ..t.unwrap_or(train)
+                                })
+                            })
+                        },
+                    ));
+                },
+            ),
+        ]
+    }
+
+    /// Why the recipe of this node can't be used with the current research, if it can't
+    fn node_lock(&self, node: &Node) -> Option<RecipeLock> {
+        match &node.kind {
+            NodeKind::Recipe { recipe, .. } => self.research.recipe_lock(&self.preset, *recipe),
+            _ => None,
+        }
+    }
+
+    /// Generate a chain of nodes that produces `rate` of `target` per second
+    fn add_chain(&mut self, pos: Point2, target: ItemOrFluidId, rate: f32) {
+        let chain = chain::build(
+            &self.preset,
+            &self.research,
+            &self.chain_settings,
+            self.next_node_id(),
+            pos,
+            target,
+            rate,
+        );
+        for node in chain.nodes {
+            self.add_node(node);
+        }
+        self.connections.extend(chain.connections);
+        self.solve();
+    }
+
+    fn add_recipe(&mut self, pos: Point2, recipe: RecipeId) {
+        let node = nodes::recipe_node(&self.preset, self.next_node_id(), pos, recipe);
+        self.add_node(node);
+        self.solve();
+    }
+
+    fn open_chain_rate_prompt(&mut self, pos: Point2, target: ItemOrFluidId) {
+        self.prompt = Some(Prompt::new_number(
+            format!("{} per second", target.name()),
+            1.0,
+            move |rate, app| app.add_chain(pos, target, rate),
+        ));
+    }
+
+    /// Add a node that consumes the science packs for `targets`, and print the plan
+    fn add_science_demand(&mut self, pos: Point2, targets: &str, deadline_minutes: f32) {
+        let targets: Vec<&str> = targets
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|t| !t.is_empty())
+            .collect();
+        let plan = science::plan(
+            &self.preset,
+            &self.research,
+            &targets,
+            deadline_minutes * 60.0,
+        );
+
+        println!("Research plan for {targets:?}:");
+        println!("  {} technologies to research", plan.technologies.len());
+        for technology in &plan.trigger_technologies {
+            println!("  {technology} is researched by a trigger");
+        }
+        for ((pack, total), (_, per_second)) in plan.packs.iter().zip(plan.packs_per_second()) {
+            println!("  {total} {pack} ({per_second:.3}/s)");
+        }
+        if let Some(lab) = &plan.lab {
+            println!("  {:.2} {lab}", plan.labs);
+        }
+
+        let node = nodes::science_demand_node(self.next_node_id(), pos, &plan);
+        self.add_node(node);
+        self.solve();
+    }
+
+    fn research_technologies(&mut self, list: &str) {
+        self.research.research_list(&self.preset, list);
+        self.research_changed();
+    }
+
+    /// Rebalance the factory for the new research, and forget what was worked out for the old one
+    fn research_changed(&mut self) {
+        self.research_cache = ResearchCache::default();
+        self.solve();
+    }
+
+    fn add_power_demand(&mut self, pos: Point2, megawatts: f32) {
+        let id = self.add_node(nodes::power_demand_node(
+            self.next_node_id(),
+            pos,
+            megawatts,
+        ));
+        match self.preset.power_chains().into_iter().next() {
+            Some(chain) => self.supply_power_demand(id, &chain),
+            None => println!("No power chains found in this preset"),
         }
     }
 
+    fn add_resource(&mut self, pos: Point2, resource: &str) {
+        let resource = self.preset.resources[resource].clone();
+        let node = nodes::resource_node(
+            &self.preset,
+            self.next_node_id(),
+            pos,
+            &resource,
+            self.research.mining_productivity,
+        );
+        self.add_node(node);
+        self.solve();
+    }
+
+    /// Rebuild every resource node for the new productivity level. The sockets stay the same, so
+    /// connections don't have to be touched.
+    fn set_mining_productivity(&mut self, level: u32) {
+        self.research.mining_productivity = level;
+        for node in self.nodes.values_mut() {
+            let NodeKind::Resource { resource, .. } = &node.kind else {
+                continue;
+            };
+            let mut new_node = nodes::resource_node(
+                &self.preset,
+                node.id,
+                node.position,
+                &self.preset.resources[resource],
+                level,
+            );
+            new_node.direction = node.direction;
+            new_node.constraints = node.constraints.clone();
+            for (new_output, output) in new_node.outputs.iter_mut().zip(&node.outputs) {
+                new_output.allow_void = output.allow_void;
+            }
+            *node = new_node;
+        }
+        self.solve();
+    }
+
+    /// Generate the nodes of `chain` to the left of `demand`, and connect them
+    fn supply_power_demand(&mut self, demand: NodeId, chain: &PowerChain) {
+        let demand_position = self.nodes[&demand].position;
+        let mut position = demand_position - Vec2::new(200., 0.);
+
+        let generator = self.preset.generators[&chain.generator].clone();
+        let boiler = self.preset.boilers[&chain.boiler].clone();
+        let generator_node = nodes::generator_node(
+            &self.preset,
+            self.next_node_id(),
+            position,
+            &generator,
+            boiler.target_temperature,
+        );
+        let generator_id = self.add_node(generator_node);
+        self.connect_matching(generator_id, demand);
+
+        position -= Vec2::new(200., 0.);
+        let boiler_node = nodes::boiler_node(&self.preset, self.next_node_id(), position, &boiler);
+        let boiler_id = self.add_node(boiler_node);
+        self.connect_matching(boiler_id, generator_id);
+
+        if let Some(reactor) = &chain.reactor {
+            position -= Vec2::new(200., 0.);
+            let reactor = self.preset.reactors[reactor].clone();
+            let reactor_node =
+                nodes::reactor_node(&self.preset, self.next_node_id(), position, &reactor);
+            let reactor_id = self.add_node(reactor_node);
+            self.connect_matching(reactor_id, boiler_id);
+        }
+
+        self.solve();
+    }
+
+    /// Connect every output of `src` to the first input of `dst` that it can feed into
+    fn connect_matching(&mut self, src: NodeId, dst: NodeId) {
+        let src_node = &self.nodes[&src];
+        let dst_node = &self.nodes[&dst];
+        for (output_index, output) in src_node.outputs.iter().enumerate() {
+            if let Some(input_index) = dst_node
+                .inputs
+                .iter()
+                .position(|i| output.can_connect_to(i))
+            {
+                self.connections.push(Connection::new(
+                    (src, output_index, false).into(),
+                    (dst, input_index, true).into(),
+                ));
+            }
+        }
+    }
+
+    /// How much more an output produces than its connections take
+    fn surplus(&self, pos: SocketPos) -> f32 {
+        let taken: f32 = self
+            .connections
+            .iter()
+            .filter(|c| c.src == pos)
+            .map(|c| c.rate)
+            .sum();
+        let output = self.nodes[&pos.node_id].get_socket(false, pos.socket_index);
+        (output.rate - taken).max(0.0)
+    }
+
+    /// Add a node that voids the surplus of an output, and connect it. The solver picks the
+    /// amount of machines that takes exactly the surplus.
+    fn void_surplus(&mut self, pos: SocketPos) {
+        let node = &self.nodes[&pos.node_id];
+        let item_or_fluid = &node.outputs[pos.socket_index].item_or_fluid;
+        let Some(recipe) = self.preset.void_recipe_for(item_or_fluid) else {
+            println!("Nothing can void {}", item_or_fluid.name());
+            return;
+        };
+        let input_index = recipe
+            .ingredients
+            .iter()
+            .position(|i| &i.item_or_fluid == item_or_fluid)
+            .unwrap();
+        let position = node.position + Vec2::new(200.0, pos.socket_index as f32 * 100.0);
+        let id = self.next_node_id();
+        let void_node = nodes::recipe_node(&self.preset, id, position, recipe.id);
+        self.add_node(void_node);
+        self.connections
+            .push(Connection::new(pos, (id, input_index, true).into()));
+        self.solve();
+    }
+
+    /// Add a node next to the socket at `pos`, built for the socket's item or fluid, and connect
+    /// its first socket on the other side to it
+    fn attach_node(
+        &mut self,
+        pos: SocketPos,
+        build: impl FnOnce(NodeId, Point2, ItemOrFluidId, Option<TemperatureRange>) -> Node,
+    ) {
+        let node = &self.nodes[&pos.node_id];
+        let socket = node.get_socket(pos.input, pos.socket_index);
+        let side = if pos.input { -200.0 } else { 200.0 };
+        let position = node.position + Vec2::new(side, pos.socket_index as f32 * 100.0);
+        let id = self.next_node_id();
+        let item_or_fluid = socket.item_or_fluid.clone();
+        let temperature = socket.temperature;
+        self.add_node(build(id, position, item_or_fluid, temperature));
+        let other = (id, 0, !pos.input).into();
+        self.connections.push(if pos.input {
+            Connection::new(other, pos)
+        } else {
+            Connection::new(pos, other)
+        });
+        self.solve();
+    }
+
+    /// Rebuild a splitter or merger with new ratios. Connections to sockets that are gone are
+    /// removed.
+    fn set_ratios(&mut self, node_id: NodeId, ratios: Vec<f32>) {
+        let node = &self.nodes[&node_id];
+        let mut rebuilt = match &node.kind {
+            NodeKind::Splitter { .. } => {
+                let input = &node.inputs[0];
+                let item_or_fluid = input.item_or_fluid.clone();
+                nodes::splitter_node(
+                    node_id,
+                    node.position,
+                    item_or_fluid,
+                    input.temperature,
+                    ratios,
+                )
+            }
+            NodeKind::Merger { .. } => {
+                let output = &node.outputs[0];
+                let item_or_fluid = output.item_or_fluid.clone();
+                nodes::merger_node(
+                    node_id,
+                    node.position,
+                    item_or_fluid,
+                    output.temperature,
+                    ratios,
+                )
+            }
+            _ => return,
+        };
+        rebuilt.direction = node.direction;
+        rebuilt.constraints = node.constraints.clone();
+        let (inputs, outputs) = (rebuilt.inputs.len(), rebuilt.outputs.len());
+        self.connections.retain(|c| {
+            !(c.src.node_id == node_id && c.src.socket_index >= outputs
+                || c.dst.node_id == node_id && c.dst.socket_index >= inputs)
+        });
+        self.nodes.insert(node_id, rebuilt);
+        self.solve();
+    }
+
+    fn add_bus_lane(&mut self, node_id: NodeId, lane: ItemOrFluidId) {
+        let node = self.nodes.get_mut(&node_id).unwrap();
+        node.inputs.push(InOutput::new(lane.clone(), 1.0));
+        node.outputs.push(InOutput::new(lane, 1.0));
+        self.solve();
+    }
+
+    /// The routes of all connections around all nodes, by connection index. Routed together, so
+    /// routes that share a line get their lanes once for the whole graph.
+    fn route_all(&self) -> Vec<Vec<Point2>> {
+        let requests: Vec<RouteRequest> = self
+            .connections
+            .iter()
+            .map(|connection| self.route_request(connection))
+            .collect();
+        // Notes are below everything, routes can go over them
+        let obstacles: Vec<Rectangle> = self
+            .nodes
+            .values()
+            .filter(|node| !matches!(node.kind, NodeKind::Note { .. }))
+            .map(get_node_position)
+            .collect();
+        routing::route_all(&requests, &obstacles)
+    }
+
+    fn route_request<'a>(&self, connection: &'a Connection) -> RouteRequest<'a> {
+        let src_node = &self.nodes[&connection.src.node_id];
+        let dst_node = &self.nodes[&connection.dst.node_id];
+        RouteRequest {
+            src: connection.src,
+            from: get_node_socket_position(src_node, connection.src.socket_index, false).center(),
+            from_direction: src_node.direction.line_direction(false, 1.),
+            to: get_node_socket_position(dst_node, connection.dst.socket_index, true).center(),
+            to_direction: dst_node.direction.line_direction(true, 1.),
+            waypoints: &connection.waypoints,
+        }
+    }
+
+    /// The start and end of a connection, with the directions its curve leaves and enters them
+    fn connection_curve(&self, connection: &Connection) -> (Point2, Vec2, Point2, Vec2) {
+        let src_node = &self.nodes[&connection.src.node_id];
+        let dst_node = &self.nodes[&connection.dst.node_id];
+        let from =
+            get_node_socket_position(src_node, connection.src.socket_index, connection.src.input)
+                .center();
+        let to =
+            get_node_socket_position(dst_node, connection.dst.socket_index, connection.dst.input)
+                .center();
+        let from_direction = src_node.direction.line_direction(
+            connection.src.input,
+            from.distance(to) * BEZIER_CURVE_FACTOR,
+        );
+        let to_direction = dst_node.direction.line_direction(
+            connection.dst.input,
+            from.distance(to) * BEZIER_CURVE_FACTOR,
+        );
+        (from, from_direction, to, to_direction)
+    }
+
+    /// An area that a connection is drawn in. Curves stay inside their control points, routes
+    /// go around nodes and can leave them by up to the padding of the route search.
+    fn connection_bounds(&self, connection: &Connection) -> Rectangle {
+        let (from, from_direction, to, to_direction) = self.connection_curve(connection);
+        let mut points = vec![from, from + from_direction, to + to_direction, to];
+        let padding = match self.routing {
+            EdgeRouting::Bezier => 0.,
+            EdgeRouting::Orthogonal => {
+                points.extend(&connection.waypoints);
+                routing::SEARCH_PADDING
+            }
+        };
+        bounding_box(points.into_iter(), padding)
+    }
+
+    /// Index where every node and connection is and route the connections, unless that was done
+    /// since the graph last changed
+    fn update_graph_cache(&mut self) {
+        if self.graph_cache.is_some() {
+            return;
+        }
+        let mut spatial = SpatialIndex::default();
+        for node in self.nodes.values() {
+            spatial.insert(node_bounds(node), Indexed::Node(node.id));
+        }
+        for (index, connection) in self.connections.iter().enumerate() {
+            spatial.insert(
+                self.connection_bounds(connection),
+                Indexed::Connection(index),
+            );
+        }
+        let routes = (self.routing == EdgeRouting::Orthogonal).then(|| self.route_all());
+        self.graph_cache = Some(GraphCache {
+            spatial,
+            routes,
+            dragged: false,
+        });
+    }
+
+    /// Keep the graph cache up to date with a node or waypoint that is being dragged, without
+    /// going over the whole graph again. Only it and its connections move in the index, and only
+    /// its connections are routed again.
+    fn dragged(&mut self, moved: Moved) {
+        let Some(mut cache) = self.graph_cache.take() else {
+            return;
+        };
+        let connections: Vec<usize> = match moved {
+            Moved::Node(node_id) => {
+                let node = &self.nodes[&node_id];
+                cache
+                    .spatial
+                    .insert(node_bounds(node), Indexed::Node(node_id));
+                (0..self.connections.len())
+                    .filter(|&i| {
+                        let c = &self.connections[i];
+                        c.src.node_id == node_id || c.dst.node_id == node_id
+                    })
+                    .collect()
+            }
+            Moved::Waypoint { connection } => vec![connection],
+        };
+        for index in connections {
+            let bounds = self.connection_bounds(&self.connections[index]);
+            cache.spatial.insert(bounds, Indexed::Connection(index));
+            if let Some(routes) = &mut cache.routes {
+                // Notes are below everything, routes can go over them
+                let obstacles: Vec<Rectangle> = cache
+                    .spatial
+                    .overlapping(bounds)
+                    .into_iter()
+                    .filter_map(|indexed| match indexed {
+                        Indexed::Node(id) => Some(&self.nodes[&id]),
+                        Indexed::Connection(_) => None,
+                    })
+                    .filter(|node| !matches!(node.kind, NodeKind::Note { .. }))
+                    .map(get_node_position)
+                    .collect();
+                let request = self.route_request(&self.connections[index]);
+                routes[index] = routing::route_all(&[request], &obstacles).remove(0);
+            }
+        }
+        cache.dragged = true;
+        self.graph_cache = Some(cache);
+    }
+
+    /// Add a waypoint halfway between the last waypoint, or the start, and the end of the
+    /// connection. Dragging it moves the route.
+    fn add_waypoint(&mut self, index: usize) {
+        let connection = &self.connections[index];
+        let socket_center = |pos: SocketPos| {
+            get_node_socket_position(&self.nodes[&pos.node_id], pos.socket_index, pos.input)
+                .center()
+        };
+        let after = match connection.waypoints.last() {
+            Some(waypoint) => *waypoint,
+            None => socket_center(connection.src),
+        };
+        let to = socket_center(connection.dst);
+        let middle = Point2::new((after.x + to.x) / 2., (after.y + to.y) / 2.);
+        self.connections[index].waypoints.push(middle);
+        self.graph_changed();
+    }
+
+    fn waypoint_menu_items(&self, pos: SocketPos) -> Vec<ContextMenuItem> {
+        if self.routing != EdgeRouting::Orthogonal {
+            return Vec::new();
+        }
+        let mut items = Vec::new();
+        for (index, connection) in self.connections.iter().enumerate() {
+            if !connection.has_socket(pos) {
+                continue;
+            }
+            let other = if pos.input {
+                connection.src
+            } else {
+                connection.dst
+            };
+            let title = node_title(&self.preset, &self.nodes[&other.node_id]);
+            items.push(ContextMenuItem::new(
+                format!("Add waypoint ({title})"),
+                move |app| app.add_waypoint(index),
+            ));
+        }
+        items
+    }
+
+    fn node_kind_menu_items(&self, node_id: NodeId) -> Vec<ContextMenuItem> {
+        let mut items = Vec::new();
+        match &self.nodes[&node_id].kind {
+            NodeKind::Splitter { ratios } | NodeKind::Merger { ratios } => {
+                let current = format_ratios(ratios);
+                items.push(ContextMenuItem::new(
+                    format!("Ratios: {current}"),
+                    move |app| {
+                        app.prompt = Some(Prompt::new(
+                            "Ratios (like 1:1:2)",
+                            &current,
+                            move |text, app| match parse_ratios(text) {
+                                Some(ratios) => app.set_ratios(node_id, ratios),
+                                None => println!("Invalid ratios {text:?}"),
+                            },
+                        ));
+                    },
+                ));
+            }
+            NodeKind::Bus => {
+                items.push(ContextMenuItem::new("Add item lane", move |app| {
+                    app.open_item_selector(move |item, app| app.add_bus_lane(node_id, item))
+                }));
+                items.push(ContextMenuItem::new("Add fluid lane", move |app| {
+                    app.open_fluid_selector(move |fluid, app| app.add_bus_lane(node_id, fluid))
+                }));
+            }
+            NodeKind::Note { text } => {
+                let text = text.clone();
+                items.push(ContextMenuItem::new("Edit note", move |app| {
+                    app.prompt = Some(Prompt::new("Note", &text, move |text, app| {
+                        if let Some(node) = app.nodes.get_mut(&node_id) {
+                            node.kind = NodeKind::Note {
+                                text: text.to_string(),
+                            };
+                        }
+                    }));
+                }));
+            }
+            _ => {}
+        }
+        items
+    }
+
     fn click_background(&self, ctx: &EventCtx) {
         println!("TODO click background at {:?}", ctx.world_mouse);
     }
@@ -49,17 +1075,108 @@ impl App {
         println!("TODO click node {:?} at {:?}", node_id, ctx.world_mouse);
     }
 
-    fn click_node_socket(&mut self, ctx: &EventCtx, pos: SocketPos) {
-        if let Some(idx) = self.connections.iter().position(|c| c.has_socket(pos)) {
-            self.connections.remove(idx);
-        } else {
-            println!(
+    /// Disconnect the socket, or pick the connection to remove if it has several
+    fn click_node_socket(&mut self, ctx: &mut EventCtx, pos: SocketPos) {
+        let connected: Vec<&Connection> = self
+            .connections
+            .iter()
+            .filter(|c| c.has_socket(pos))
+            .collect();
+        match connected.as_slice() {
+            [] => println!(
                 "TODO click node {:?} socket {:?} at {:?}",
                 pos.node_id,
                 self.nodes[&pos.node_id].get_socket(pos.input, pos.socket_index),
                 ctx.world_mouse
-            );
-        };
+            ),
+            [connection] => {
+                let (src, dst) = (connection.src, connection.dst);
+                self.disconnect(src, dst);
+            }
+            connected => {
+                let items: Vec<ContextMenuItem> = connected
+                    .iter()
+                    .map(|c| {
+                        let (src, dst) = (c.src, c.dst);
+                        let other = if pos.input { src } else { dst };
+                        let title = node_title(&self.preset, &self.nodes[&other.node_id]);
+                        ContextMenuItem::new(format!("Disconnect {title}"), move |app| {
+                            app.disconnect(src, dst)
+                        })
+                    })
+                    .collect();
+                self.set_right_click_menu(ctx, items);
+            }
+        }
+    }
+
+    fn disconnect(&mut self, src: SocketPos, dst: SocketPos) {
+        self.connections.retain(|c| c.src != src || c.dst != dst);
+        self.solve();
+    }
+
+    fn change_connection(
+        &mut self,
+        src: SocketPos,
+        dst: SocketPos,
+        change: impl FnOnce(&mut Connection),
+    ) {
+        if let Some(connection) = self
+            .connections
+            .iter_mut()
+            .find(|c| c.src == src && c.dst == dst)
+        {
+            change(connection);
+            self.solve();
+        }
+    }
+
+    /// Context menu items that set how an output is split over its connections
+    fn distribution_menu_items(&self, pos: SocketPos) -> Vec<ContextMenuItem> {
+        let connected: Vec<&Connection> =
+            self.connections.iter().filter(|c| c.src == pos).collect();
+        if connected.len() < 2 {
+            return Vec::new();
+        }
+        let mut items = Vec::new();
+        for connection in connected {
+            let (src, dst) = (connection.src, connection.dst);
+            let title = node_title(&self.preset, &self.nodes[&dst.node_id]);
+            let ratio = connection.ratio;
+            items.push(ContextMenuItem::new(
+                match ratio {
+                    Some(ratio) => format!("To {title}: ratio {ratio}"),
+                    None => format!("To {title}: by demand"),
+                },
+                move |app| {
+                    app.prompt = Some(Prompt::new_number(
+                        "Ratio (0 to split by demand)",
+                        ratio.unwrap_or(1.0),
+                        move |ratio, app| {
+                            app.change_connection(src, dst, |c| {
+                                c.ratio = (ratio > 0.0).then_some(ratio)
+                            })
+                        },
+                    ));
+                },
+            ));
+            let priority = connection.priority;
+            items.push(ContextMenuItem::new(
+                format!("To {title}: priority {priority}"),
+                move |app| {
+                    app.prompt = Some(Prompt::new_number(
+                        "Priority (higher is served first)",
+                        priority as f32,
+                        move |priority, app| {
+                            app.change_connection(src, dst, |c| {
+                                c.priority = priority.round() as i32
+                            })
+                        },
+                    ));
+                },
+            ));
+        }
+        items
     }
 
     fn drop_node_socket(&mut self, ctx: &EventCtx, pos: SocketPos) {
@@ -74,19 +1191,36 @@ impl App {
             input: hover_is_input,
         } = self.hover
         {
-            if node != pos.node_id {
-                let src = pos;
-                let dst = (node, socket, hover_is_input).into();
+            if node != pos.node_id && hover_is_input != pos.input {
+                let other = (node, socket, hover_is_input).into();
+                let (src, dst) = if pos.input {
+                    (other, pos)
+                } else {
+                    (pos, other)
+                };
+                let src_socket = self.nodes[&src.node_id].get_socket(false, src.socket_index);
+                let dst_socket = self.nodes[&dst.node_id].get_socket(true, dst.socket_index);
+                if !src_socket.can_connect_to(dst_socket) {
+                    println!(
+                        "Can't connect {:?} ({:?}) to {:?} ({:?})",
+                        src_socket.item_or_fluid,
+                        src_socket.temperature,
+                        dst_socket.item_or_fluid,
+                        dst_socket.temperature
+                    );
+                    return;
+                }
 
                 if let Some(idx) = self
                     .connections
                     .iter()
-                    .position(|c| c.has_socket(pos) && c.has_socket(src))
+                    .position(|c| c.has_socket(src) && c.has_socket(dst))
                 {
                     self.connections.remove(idx);
                 } else {
-                    self.connections.push(Connection { src, dst })
+                    self.connections.push(Connection::new(src, dst))
                 }
+                self.solve();
             }
         }
     }
@@ -109,27 +1243,149 @@ impl App {
     }
 
     fn open_recipe_selector(&mut self, pos: Point2) {
-        self.selector = Some(Selector::new_recipe(&self.preset, move |recipe_id, app| {
-            println!(
-                "TODO add recipe {:?} at {pos:?}",
-                app.preset.recipes[&recipe_id]
-            );
-        }));
+        let (costs, reachability) =
+            self.research_cache
+                .for_selector(&self.preset, &self.research, self.show_reachability);
+        self.selector = Some(Selector::new_recipe(
+            &self.preset,
+            &self.research,
+            costs,
+            reachability,
+            self.chain_settings.filter,
+            move |recipe_id, app| app.add_recipe(pos, recipe_id),
+        ));
     }
 
-    fn open_item_selector(&mut self, pos: Point2) {
-        self.selector = Some(Selector::new_item(&self.preset, move |item_id, app| {
-            println!("TODO add item {:?} at {pos:?}", app.preset.items[&item_id]);
-        }));
+    fn open_research_goal_prompt(&mut self, pos: Point2) {
+        self.prompt = Some(Prompt::new(
+            "Research goal (comma separated, NAME:LEVEL for infinite)",
+            "",
+            move |targets, app| {
+                let targets = targets.to_string();
+                app.prompt = Some(Prompt::new_number(
+                    "Deadline (minutes)",
+                    60.0,
+                    move |minutes, app| app.add_science_demand(pos, &targets, minutes),
+                ));
+            },
+        ));
     }
-    fn open_fluid_selector(&mut self, pos: Point2) {
-        self.selector = Some(Selector::new_fluid(&self.preset, move |fluid_id, app| {
-            println!(
-                "TODO add fluid {:?} at {pos:?}",
-                app.preset.fluids[&fluid_id]
-            );
+
+    fn open_research_prompt(&mut self) {
+        self.prompt = Some(Prompt::new(
+            "Research technologies (comma separated)",
+            "",
+            |list, app| app.research_technologies(list),
+        ));
+    }
+
+    fn open_forget_research_prompt(&mut self) {
+        self.prompt = Some(Prompt::new("Forget technology", "", |technology, app| {
+            app.research.forget(&app.preset, technology.trim());
+            app.research_changed();
         }));
     }
+
+    fn open_import_research_prompt(&mut self) {
+        self.prompt = Some(Prompt::new(
+            "Import researched technologies from file",
+            "",
+            |path, app| match std::fs::read_to_string(path.trim()) {
+                Ok(list) => {
+                    app.research.import(&app.preset, &list);
+                    app.research_changed();
+                }
+                Err(e) => println!("Failed to read {path:?}: {e:?}"),
+            },
+        ));
+    }
+
+    fn open_power_demand_prompt(&mut self, pos: Point2) {
+        self.prompt = Some(Prompt::new_number(
+            "Power demand (MW)",
+            10.0,
+            move |megawatts, app| {
+                if megawatts <= 0.0 {
+                    println!("Invalid number {megawatts:?}, the demand has to be positive");
+                    return;
+                }
+                app.add_power_demand(pos, megawatts)
+            },
+        ));
+    }
+
+    fn open_resource_selector(&mut self, pos: Point2) {
+        self.selector = Some(Selector::new_resource(
+            &self.preset,
+            move |resource, app| app.add_resource(pos, &resource),
+        ));
+    }
+
+    fn open_mining_productivity_prompt(&mut self) {
+        self.prompt = Some(Prompt::new_number(
+            "Mining productivity level",
+            self.research.mining_productivity as f32,
+            |level, app| app.set_mining_productivity(level.max(0.0) as u32),
+        ));
+    }
+
+    fn open_item_selector(&mut self, then: impl Fn(ItemOrFluidId, &mut App) + Clone + 'static) {
+        let (costs, reachability) =
+            self.research_cache
+                .for_selector(&self.preset, &self.research, self.show_reachability);
+        self.selector = Some(Selector::new_item(
+            &self.preset,
+            costs,
+            reachability,
+            move |item_id, app| {
+                let name = app.preset.items[&item_id].name.clone();
+                then(ItemOrFluidId::Item(name), app);
+            },
+        ));
+    }
+    fn open_fluid_selector(&mut self, then: impl Fn(ItemOrFluidId, &mut App) + Clone + 'static) {
+        let (costs, reachability) =
+            self.research_cache
+                .for_selector(&self.preset, &self.research, self.show_reachability);
+        self.selector = Some(Selector::new_fluid(
+            &self.preset,
+            costs,
+            reachability,
+            move |fluid_id, app| {
+                let name = app.preset.fluids[&fluid_id].name.clone();
+                then(ItemOrFluidId::Fluid(name), app);
+            },
+        ));
+    }
+
+    fn open_item_explorer(&mut self, pos: Point2) {
+        self.selector = Some(Selector::new_item(
+            &self.preset,
+            &FxHashMap::default(),
+            None,
+            move |item_id, app| {
+                let item = ItemOrFluidId::Item(app.preset.items[&item_id].name.clone());
+                app.explorer = Some(Explorer::new(&app.preset, item, pos));
+            },
+        ));
+    }
+
+    fn open_fluid_explorer(&mut self, pos: Point2) {
+        self.selector = Some(Selector::new_fluid(
+            &self.preset,
+            &FxHashMap::default(),
+            None,
+            move |fluid_id, app| {
+                let fluid = ItemOrFluidId::Fluid(app.preset.fluids[&fluid_id].name.clone());
+                app.explorer = Some(Explorer::new(&app.preset, fluid, pos));
+            },
+        ));
+    }
+
+    fn open_cost_table(&mut self) {
+        let costs = self.research_cache.raw_costs(&self.preset, &self.research);
+        self.cost_table = Some(CostTable::new(costs.clone()));
+    }
 }
 
 impl super::App for App {
@@ -141,40 +1397,143 @@ impl super::App for App {
 
         let line_color = Paint::color(self.theme.layer_color(3)).with_line_width(5);
 
-        for connection in &self.connections {
+        self.update_graph_cache();
+        let SocketTotals { outputs, supplied } = &self.totals;
+        let mismatched = |needed: f32, got: f32| needed - got > 1e-3 * needed.max(1.0);
+        let elapsed = self.started.elapsed().as_secs_f32();
+
+        let cache = self.graph_cache.as_ref().unwrap();
+        let (mut visible_connections, mut visible_nodes) = (Vec::new(), Vec::new());
+        for indexed in cache.spatial.overlapping(ctx.visible_world()) {
+            match indexed {
+                Indexed::Connection(index) => visible_connections.push(index),
+                Indexed::Node(node) => visible_nodes.push(&self.nodes[&node]),
+            }
+        }
+        for index in visible_connections {
+            let connection = &self.connections[index];
             let src_node = &self.nodes[&connection.src.node_id];
             let dst_node = &self.nodes[&connection.dst.node_id];
-            let src = get_node_socket_position(
-                src_node,
-                connection.src.socket_index,
-                connection.src.input,
-            );
-            let dst = get_node_socket_position(
-                dst_node,
-                connection.dst.socket_index,
-                connection.dst.input,
-            );
-            let from = src.center();
-            let to = dst.center();
-            ctx.draw_bezier(
-                &line_color,
-                from,
-                src_node.direction.line_direction(
-                    connection.src.input,
-                    from.distance(to) * BEZIER_CURVE_FACTOR,
+            let (from, from_direction, to, to_direction) = self.connection_curve(connection);
+            let src_socket = src_node.get_socket(false, connection.src.socket_index);
+            let dst_socket = dst_node.get_socket(true, connection.dst.socket_index);
+            let under_supplied = mismatched(dst_socket.rate, supplied[&connection.dst]);
+            let over_supplied =
+                !src_socket.allow_void && mismatched(src_socket.rate, outputs[&connection.src].1);
+            let flow_paint = Paint::color(if under_supplied || over_supplied {
+                self.theme.error.color
+            } else {
+                self.preset
+                    .color_for_item_or_fluid(&src_socket.item_or_fluid)
+            })
+            .with_line_width(5);
+            let route = cache.routes.as_ref().map(|routes| routes[index].as_slice());
+            let along = |t| match route {
+                Some(route) => routing::point_along(route, t),
+                None => (
+                    bezier_point(from, from_direction, to, to_direction, t),
+                    bezier_tangent(from, from_direction, to, to_direction, t),
                 ),
-                to,
-                Some(dst_node.direction.line_direction(
-                    connection.dst.input,
-                    from.distance(to) * BEZIER_CURVE_FACTOR,
-                )),
-            );
+            };
+            match route {
+                Some(route) => {
+                    for segment in route.windows(2) {
+                        ctx.draw_line(segment[0], segment[1], flow_paint);
+                    }
+                    for (i, waypoint) in connection.waypoints.iter().enumerate() {
+                        let hovered = self.hover
+                            == Hover::Waypoint {
+                                connection: index,
+                                index: i,
+                            };
+                        let size = if hovered { 12. } else { 8. };
+                        let square = Rectangle::centered_square(*waypoint, size);
+                        ctx.draw_rounded(square, flow_paint, flow_paint, 2.);
+                    }
+                }
+                None => ctx.draw_bezier(&flow_paint, from, from_direction, to, Some(to_direction)),
+            }
+            for t in [0.25, 0.75] {
+                let (at, direction) = along(t);
+                draw_arrow(ctx, at, direction, flow_paint);
+            }
+            if self.animate_flows && connection.rate > 1e-6 {
+                draw_flow_dots(ctx, |t| along(t).0, connection.rate, elapsed, flow_paint);
+            }
+
+            let mut labels = Vec::new();
+            if connection.rate > 1e-6 {
+                labels.push(self.rate_unit.format(connection.rate));
+            }
+            if let Some(temperature) = src_socket.temperature {
+                labels.push(temperature.to_string());
+            }
+            let (count, total) = outputs[&connection.src];
+            if count > 1 && total > 1e-6 {
+                labels.push(format!("{:.0}%", connection.rate / total * 100.));
+            }
+            if connection.priority != 0 {
+                labels.push(format!("priority {}", connection.priority));
+            }
+            if self.rounding != MachineRounding::Exact && connection.idle > 0.005 {
+                labels.push(format!("{:.0}% idle", connection.idle * 100.));
+            }
+            let transport = match connection.train {
+                Some(_) => Transport::None,
+                None => Transport::new(
+                    &self.preset,
+                    &self.logistics,
+                    &src_socket.item_or_fluid,
+                    connection.rate,
+                ),
+            };
+            if connection.rate > 1e-6 && transport != Transport::None {
+                labels.push(transport.to_string());
+            }
+            let mut middle = match route {
+                Some(_) => along(0.5).0,
+                None => bezier_midpoint(from, from_direction, to, to_direction),
+            };
+            if let Some(train) = &connection.train {
+                draw_train(
+                    ctx,
+                    &self.theme,
+                    &self.preset,
+                    train,
+                    &src_socket.item_or_fluid,
+                    connection.rate,
+                    middle,
+                );
+                middle.y += 40.;
+            }
+            if !labels.is_empty() && ctx.zoom() >= LABEL_MIN_ZOOM {
+                let color = if transport.overloaded() {
+                    self.theme.error.color
+                } else {
+                    self.theme.background.text
+                };
+                ctx.fill_text_centered(
+                    Rectangle::centered_square(middle, 40.),
+                    &labels.join(", "),
+                    Paint::color(color).with_font_size(12),
+                );
+            }
         }
 
-        for node in self.nodes.values() {
+        // Notes are drawn below the other nodes
+        let (notes, nodes): (Vec<&Node>, Vec<&Node>) = visible_nodes
+            .into_iter()
+            .partition(|node| matches!(node.kind, NodeKind::Note { .. }));
+        for node in notes.into_iter().chain(nodes) {
+            let style = NodeStyle {
+                theme: &self.theme,
+                preset: &self.preset,
+                rate_unit: self.rate_unit,
+                lock: self.node_lock(node),
+            };
             draw_node(
                 ctx,
-                &self.theme,
+                style,
                 node,
                 self.hover.should_highlight_node(node) || self.dragging.should_highlight_node(node),
                 self.hover
@@ -201,17 +1560,80 @@ impl super::App for App {
             );
         }
 
+        let open = self.breadcrumbs.len();
+        for (rect, depth, name) in self.breadcrumb_layout() {
+            let background = if depth == open {
+                self.theme.layer_color(3)
+            } else if rect.contains(ctx.ui_mouse) {
+                self.theme.layer_color(2)
+            } else {
+                self.theme.layer_color(1)
+            };
+            ctx.draw_fill_border(
+                rect,
+                Paint::color(background),
+                Paint::color(self.theme.layer_color(3)),
+            );
+            ctx.fill_text_centered(
+                rect,
+                name,
+                Paint::color(self.theme.background.text).with_font_size(14),
+            );
+        }
+
+        if let Some(warning) = self.solve_warning {
+            ctx.fill_text_centered(
+                Rectangle::new(0., 40., ctx.window_size.x, 24.),
+                &warning.to_string(),
+                Paint::color(self.theme.error.color).with_font_size(14),
+            );
+        }
+
+        if self.show_minimap {
+            self.minimap.draw(
+                ctx,
+                &self.nodes,
+                &self.connections,
+                &self.preset,
+                &self.theme,
+            );
+        }
+
+        if let Some(explorer) = &self.explorer {
+            explorer.draw(ctx, &self.preset, &self.theme);
+        }
         if let Some(context_menu) = &self.context_menu {
             context_menu.draw(ctx, &self.theme);
         }
         if let Some(selector) = &self.selector {
             selector.draw(ctx, &self.theme);
         }
+        if let Some(cost_table) = &self.cost_table {
+            cost_table.draw(ctx, &self.theme);
+        }
+        if let Some(templates) = &self.templates {
+            templates.draw(ctx, &self.theme);
+        }
+        if let Some(prompt) = &self.prompt {
+            prompt.draw(ctx, &self.theme);
+        }
     }
 
-    fn mouse_down(&mut self, _ctx: &mut EventCtx, button: MouseButton) {
+    fn mouse_down(&mut self, ctx: &mut EventCtx, button: MouseButton) {
         if button == MouseButton::Left {
-            if self.selector.is_some() || self.context_menu.is_some() {
+            if self.selector.is_some()
+                || self.context_menu.is_some()
+                || self.prompt.is_some()
+                || self.cost_table.is_some()
+                || self.explorer.is_some()
+                || self.templates.is_some()
+            {
+                return;
+            }
+            if self.breadcrumb_at(ctx.ui_mouse).is_some() {
+                return;
+            }
+            if self.show_minimap && self.minimap.mouse_down(ctx, &self.nodes) {
                 return;
             }
 
@@ -222,27 +1644,72 @@ impl super::App for App {
             } = self.hover
             {
                 self.dragging.start_drag_line(
-                    _ctx.world_mouse,
+                    ctx.world_mouse,
                     (node, socket, input).into(),
                     get_socket_initial_direction(&self.nodes, self.hover),
                 );
+            } else if let Hover::Waypoint { connection, index } = self.hover {
+                self.dragging
+                    .start_drag_waypoint(ctx.world_mouse, connection, index);
             } else if let Hover::Node(node_id) = self.hover {
-                self.dragging.start_drag_node(_ctx.world_mouse, node_id);
+                self.dragging.start_drag_node(ctx.world_mouse, node_id);
             } else {
-                self.dragging.start_drag_background(_ctx.world_mouse);
+                self.dragging.start_drag_background(ctx.world_mouse);
             }
         }
     }
 
     fn mouse_up(&mut self, ctx: &mut EventCtx, button: MouseButton) {
+        if let Some(prompt) = &self.prompt {
+            if !prompt.contains(ctx.window_size, ctx.ui_mouse) {
+                self.prompt = None;
+                ctx.redraw();
+            }
+            return;
+        }
         if let Some(menu) = std::mem::take(&mut self.context_menu) {
             menu.try_click(self);
             self.dragging.clear();
             ctx.redraw();
             return;
         }
+        if let Some(explorer) = &mut self.explorer {
+            match explorer.try_click(&self.preset) {
+                ExplorerClick::None => {}
+                ExplorerClick::Close => self.explorer = None,
+                ExplorerClick::Pick(recipe) => {
+                    let pos = explorer.position;
+                    self.explorer = None;
+                    self.add_recipe(pos, recipe);
+                }
+            }
+            self.dragging.clear();
+            ctx.redraw();
+            return;
+        }
+        if let Some(cost_table) = &mut self.cost_table {
+            if cost_table.try_click() == PopupClickResult::Close {
+                self.cost_table = None;
+            }
+            self.dragging.clear();
+            ctx.redraw();
+            return;
+        }
+        if let Some(browser) = std::mem::take(&mut self.templates) {
+            match browser.try_click() {
+                BrowserClick::Close => {}
+                BrowserClick::Insert(index) => {
+                    self.insert_template(browser.position, &browser.templates[index])
+                }
+            }
+            self.dragging.clear();
+            ctx.redraw();
+            return;
+        }
         if let Some(mut selector) = std::mem::take(&mut self.selector) {
-            if selector.try_click(self) != PopupClickResult::Close {
+            let result = selector.try_click(self);
+            self.chain_settings.filter = selector.filter;
+            if result != PopupClickResult::Close {
                 // If the selector is still open, put it back
                 self.selector = Some(selector);
             }
@@ -250,7 +1717,29 @@ impl super::App for App {
             ctx.redraw();
             return;
         }
+        if button == MouseButton::Left
+            && let Some(depth) = self.breadcrumb_at(ctx.ui_mouse)
+        {
+            while self.breadcrumbs.len() > depth {
+                self.leave_factory();
+            }
+            ctx.redraw();
+            return;
+        }
+        if self.minimap.is_dragging() {
+            self.minimap.mouse_up();
+            return;
+        }
+        if button == MouseButton::Right
+            && self.show_minimap
+            && self.minimap.contains(ctx.window_size, ctx.ui_mouse)
+        {
+            return;
+        }
 
+        if self.graph_cache.as_ref().is_some_and(|cache| cache.dragged) {
+            self.graph_changed();
+        }
         if button == MouseButton::Left {
             match (self.dragging.mouse_up_was_click(), self.dragging.state()) {
                 (true, DragState::LineFromNodeSocket { pos, .. }) => {
@@ -283,18 +1772,146 @@ impl super::App for App {
                 Hover::NodeSocket {
                     node,
                     socket,
-                    input,
+                    input: false,
                 } => {
-                    println!(
-                        "TODO right click node {:?} socket {} input {}",
-                        node, socket, input
+                    let pos: SocketPos = (node, socket, false).into();
+                    let output = self.nodes[&node].get_socket(false, socket);
+                    let mut items = Vec::new();
+                    let surplus = self.surplus(pos);
+                    if surplus > 1e-6
+                        && self.preset.void_recipe_for(&output.item_or_fluid).is_some()
+                    {
+                        items.push(ContextMenuItem::new(
+                            format!("Void surplus ({surplus:.2}/s)"),
+                            move |app| app.void_surplus(pos),
+                        ));
+                    }
+                    items.push(ContextMenuItem::new(
+                        if output.allow_void {
+                            "Don't allow voiding"
+                        } else {
+                            "Allow voiding"
+                        },
+                        move |app| {
+                            let output = &mut app.nodes.get_mut(&node).unwrap().outputs[socket];
+                            output.allow_void = !output.allow_void;
+                            app.solve();
+                        },
+                    ));
+                    items.push(ContextMenuItem::new("Split", move |app| {
+                        app.attach_node(pos, |id, position, item_or_fluid, temperature| {
+                            nodes::splitter_node(
+                                id,
+                                position,
+                                item_or_fluid,
+                                temperature,
+                                vec![1.0, 1.0],
+                            )
+                        })
+                    }));
+                    items.push(ContextMenuItem::new(
+                        "Send out of the factory",
+                        move |app| app.attach_node(pos, nodes::sink_node),
+                    ));
+                    items.extend(self.distribution_menu_items(pos));
+                    items.extend(self.waypoint_menu_items(pos));
+                    items.extend(self.train_menu_items(pos));
+                    self.set_right_click_menu(ctx, items);
+                }
+                Hover::NodeSocket {
+                    node,
+                    socket,
+                    input: true,
+                } => {
+                    let pos: SocketPos = (node, socket, true).into();
+                    let mut items = vec![
+                        ContextMenuItem::new("Merge", move |app| {
+                            app.attach_node(pos, |id, position, item_or_fluid, temperature| {
+                                nodes::merger_node(
+                                    id,
+                                    position,
+                                    item_or_fluid,
+                                    temperature,
+                                    vec![1.0, 1.0],
+                                )
+                            })
+                        }),
+                        ContextMenuItem::new("Supply from outside the factory", move |app| {
+                            app.attach_node(pos, nodes::source_node)
+                        }),
+                    ];
+                    items.extend(self.waypoint_menu_items(pos));
+                    items.extend(self.train_menu_items(pos));
+                    self.set_right_click_menu(ctx, items);
+                }
+                Hover::Waypoint { connection, index } => {
+                    self.set_right_click_menu(
+                        ctx,
+                        [ContextMenuItem::new("Remove waypoint", move |app| {
+                            app.connections[connection].waypoints.remove(index);
+                            app.graph_changed();
+                        })],
                     );
                 }
                 Hover::Node(node) => {
-                    println!("TODO right click node {:?}", node);
+                    let mut items = Vec::new();
+                    if self.nodes[&node].kind == NodeKind::PowerDemand {
+                        for chain in self.preset.power_chains() {
+                            items.push(ContextMenuItem::new(
+                                format!("Supply with {}", chain.label()),
+                                move |app| app.supply_power_demand(node, &chain),
+                            ));
+                        }
+                    }
+                    if let NodeKind::Recipe { recipe, .. } = &self.nodes[&node].kind {
+                        let recipe = *recipe;
+                        for product in &self.preset.recipes[&recipe].results {
+                            let product = product.item_or_fluid.clone();
+                            if self.chain_settings.pinned.get(&product) == Some(&recipe) {
+                                items.push(ContextMenuItem::new(
+                                    format!("Unpin for {}", product.name()),
+                                    move |app| {
+                                        app.chain_settings.pinned.remove(&product);
+                                    },
+                                ));
+                            } else {
+                                items.push(ContextMenuItem::new(
+                                    format!("Pin for {}", product.name()),
+                                    move |app| {
+                                        app.chain_settings.pinned.insert(product, recipe);
+                                    },
+                                ));
+                            }
+                        }
+                    }
+                    if matches!(self.nodes[&node].kind, NodeKind::Factory { .. }) {
+                        items.push(ContextMenuItem::new("Open sub-factory", move |app| {
+                            app.enter_factory(node)
+                        }));
+                        items.push(ContextMenuItem::new("Save as template", move |app| {
+                            app.prompt = Some(Prompt::new(
+                                "Template tags (comma separated)",
+                                "",
+                                move |tags, app| app.save_template(node, tags),
+                            ));
+                        }));
+                    }
+                    items.extend(self.node_kind_menu_items(node));
+                    if self.nodes[&node].fixed_machines().is_none() {
+                        items.extend(self.constraint_menu_items(node));
+                    }
+                    items.push(ContextMenuItem::new("Rotate", move |app| {
+                        let node = app.nodes.get_mut(&node).unwrap();
+                        node.direction = node.direction.rotate_clockwise();
+                        app.graph_changed();
+                    }));
+                    items.push(ContextMenuItem::new("Remove", move |app| {
+                        app.remove_node(node)
+                    }));
+                    self.set_right_click_menu(ctx, items);
                 }
                 Hover::None => {
-                    let pos = ctx.ui_mouse;
+                    let pos = ctx.world_mouse;
                     self.set_right_click_menu(
                         ctx,
                         [
@@ -302,10 +1919,133 @@ impl super::App for App {
                                 app.open_recipe_selector(pos)
                             }),
                             ContextMenuItem::new("Add item", move |app| {
-                                app.open_item_selector(pos)
+                                app.open_item_selector(move |item, app| {
+                                    app.open_chain_rate_prompt(pos, item)
+                                })
                             }),
                             ContextMenuItem::new("Add fluid", move |app| {
-                                app.open_fluid_selector(pos)
+                                app.open_fluid_selector(move |fluid, app| {
+                                    app.open_chain_rate_prompt(pos, fluid)
+                                })
+                            }),
+                            ContextMenuItem::new("Add bus", move |app| {
+                                app.add_node(nodes::bus_node(app.next_node_id(), pos, Vec::new()));
+                            }),
+                            ContextMenuItem::new("Add note", move |app| {
+                                app.prompt = Some(Prompt::new("Note", "", move |text, app| {
+                                    let id = app.next_node_id();
+                                    app.add_node(nodes::note_node(id, pos, text.to_string()));
+                                }));
+                            }),
+                            ContextMenuItem::new("Add resource", move |app| {
+                                app.open_resource_selector(pos)
+                            }),
+                            ContextMenuItem::new("Add power demand", move |app| {
+                                app.open_power_demand_prompt(pos)
+                            }),
+                            ContextMenuItem::new("Insert template", move |app| {
+                                app.open_template_browser(pos)
+                            }),
+                            ContextMenuItem::new("Add sub-factory", move |app| {
+                                app.prompt = Some(Prompt::new(
+                                    "Sub-factory name",
+                                    "Sub-factory",
+                                    move |name, app| app.add_factory(pos, name.trim().to_string()),
+                                ));
+                            }),
+                            ContextMenuItem::new(
+                                format!("Recipe preference: {:?}", self.chain_settings.preference),
+                                |app| {
+                                    app.chain_settings.preference =
+                                        app.chain_settings.preference.next()
+                                },
+                            ),
+                            ContextMenuItem::new(format!("Machines: {:?}", self.rounding), |app| {
+                                app.rounding = app.rounding.next();
+                                app.solve();
+                            }),
+                            ContextMenuItem::new(format!("Rates: {:?}", self.rate_unit), |app| {
+                                app.rate_unit = app.rate_unit.next()
+                            }),
+                            ContextMenuItem::new(
+                                format!("Connections: {:?}", self.routing),
+                                |app| {
+                                    app.routing = app.routing.next();
+                                    app.graph_changed();
+                                },
+                            ),
+                            ContextMenuItem::new(
+                                if self.animate_flows {
+                                    "Stop flow animation"
+                                } else {
+                                    "Animate flows"
+                                },
+                                |app| app.animate_flows = !app.animate_flows,
+                            ),
+                            ContextMenuItem::new(
+                                if self.show_minimap {
+                                    "Hide minimap"
+                                } else {
+                                    "Show minimap"
+                                },
+                                |app| app.show_minimap = !app.show_minimap,
+                            ),
+                            ContextMenuItem::new(
+                                format!(
+                                    "Belt: {}",
+                                    self.logistics.belt.as_deref().unwrap_or("none")
+                                ),
+                                |app| app.logistics.next_belt(&app.preset),
+                            ),
+                            ContextMenuItem::new(
+                                format!(
+                                    "Inserter: {}",
+                                    self.logistics.inserter.as_deref().unwrap_or("none")
+                                ),
+                                |app| app.logistics.next_inserter(&app.preset),
+                            ),
+                            ContextMenuItem::new("Explore item", move |app| {
+                                app.open_item_explorer(pos)
+                            }),
+                            ContextMenuItem::new("Explore fluid", move |app| {
+                                app.open_fluid_explorer(pos)
+                            }),
+                            ContextMenuItem::new("Raw cost table", |app| app.open_cost_table()),
+                            ContextMenuItem::new(
+                                if self.show_reachability {
+                                    "Hide reachability"
+                                } else {
+                                    "Show reachability"
+                                },
+                                |app| app.show_reachability = !app.show_reachability,
+                            ),
+                            ContextMenuItem::new("Plan research goal", move |app| {
+                                app.open_research_goal_prompt(pos)
+                            }),
+                            ContextMenuItem::new(
+                                format!(
+                                    "Mining productivity: {}",
+                                    self.research.mining_productivity
+                                ),
+                                |app| app.open_mining_productivity_prompt(),
+                            ),
+                            ContextMenuItem::new(
+                                if self.research.is_everything() {
+                                    "Research (all)".to_string()
+                                } else {
+                                    format!("Research ({})", self.research.researched_count())
+                                },
+                                |app| app.open_research_prompt(),
+                            ),
+                            ContextMenuItem::new("Forget research", |app| {
+                                app.open_forget_research_prompt()
+                            }),
+                            ContextMenuItem::new("Import research", |app| {
+                                app.open_import_research_prompt()
+                            }),
+                            ContextMenuItem::new("Research everything", |app| {
+                                app.research.research_everything();
+                                app.research_changed();
                             }),
                         ],
                     );
@@ -327,9 +2067,38 @@ impl super::App for App {
                 return;
             }
         }
-        self.dragging.mouse_move(delta, ctx, &mut self.nodes);
+        if let Some(explorer) = &mut self.explorer {
+            explorer.mouse_move(&self.preset, ctx.window_size, ctx.ui_mouse);
+            ctx.redraw();
+            return;
+        }
+        if let Some(cost_table) = &mut self.cost_table {
+            cost_table.mouse_move(ctx.window_size, ctx.ui_mouse);
+            ctx.redraw();
+            return;
+        }
+        if let Some(templates) = &mut self.templates {
+            templates.mouse_move(ctx.window_size, ctx.ui_mouse);
+            ctx.redraw();
+            return;
+        }
+        if self.minimap.is_dragging() {
+            self.minimap.mouse_move(ctx, &self.nodes);
+            return;
+        }
+        if let Some(moved) =
+            self.dragging
+                .mouse_move(delta, ctx, &mut self.nodes, &mut self.connections)
+        {
+            self.dragged(moved);
+        }
 
-        let hover = self.find_hover(ctx.world_mouse);
+        self.update_graph_cache();
+        let hover = if self.show_minimap && self.minimap.contains(ctx.window_size, ctx.ui_mouse) {
+            Hover::None
+        } else {
+            self.find_hover(ctx.world_mouse)
+        };
         if hover != self.hover {
             self.hover = hover;
             ctx.redraw();
@@ -337,19 +2106,132 @@ impl super::App for App {
     }
 
     fn key_down(&mut self, ctx: &mut EventCtx, key: KeyCode) {
+        if let Some(prompt) = &mut self.prompt {
+            match key {
+                KeyCode::Escape => self.prompt = None,
+                KeyCode::Backspace => prompt.backspace(),
+                KeyCode::Return => {
+                    if let Some(prompt) = std::mem::take(&mut self.prompt) {
+                        prompt.submit(self);
+                    }
+                }
+                _ => {}
+            }
+            ctx.redraw();
+            return;
+        }
+        if self.explorer.is_some() {
+            if key == KeyCode::Escape {
+                self.explorer = None;
+            }
+            ctx.redraw();
+            return;
+        }
+        if let Some(cost_table) = &mut self.cost_table {
+            match key {
+                KeyCode::Escape => self.cost_table = None,
+                KeyCode::Up => cost_table.scroll_by(-1),
+                KeyCode::Down => cost_table.scroll_by(1),
+                KeyCode::PageUp => cost_table.scroll_by(-10),
+                KeyCode::PageDown => cost_table.scroll_by(10),
+                _ => {}
+            }
+            ctx.redraw();
+            return;
+        }
+        if let Some(templates) = &mut self.templates {
+            match key {
+                KeyCode::Escape => self.templates = None,
+                KeyCode::Up => templates.scroll_by(-1),
+                KeyCode::Down => templates.scroll_by(1),
+                _ => {}
+            }
+            ctx.redraw();
+            return;
+        }
         if key == KeyCode::Escape {
-            ctx.exit();
+            if self.breadcrumbs.is_empty() {
+                ctx.exit();
+            } else {
+                self.leave_factory();
+                ctx.redraw();
+            }
+        }
+    }
+
+    fn mouse_double_click(&mut self, ctx: &mut EventCtx, button: MouseButton) {
+        let popup_open = self.selector.is_some()
+            || self.context_menu.is_some()
+            || self.prompt.is_some()
+            || self.cost_table.is_some()
+            || self.explorer.is_some()
+            || self.templates.is_some();
+        if button == MouseButton::Left
+            && !popup_open
+            && let Hover::Node(node) = self.hover
+        {
+            self.enter_factory(node);
+            ctx.redraw();
+        }
+    }
+
+    fn text_input(&mut self, ctx: &mut EventCtx, text: &str) {
+        if let Some(prompt) = &mut self.prompt {
+            prompt.text_input(text);
+            ctx.redraw();
         }
     }
 
     fn mouse_scroll(&mut self, ctx: &mut EventCtx, delta: Vec2) {
+        if let Some(explorer) = &mut self.explorer {
+            explorer.scroll_by(-delta.y * 30.0);
+            ctx.redraw();
+            return;
+        }
+        if let Some(cost_table) = &mut self.cost_table {
+            cost_table.scroll_by(-delta.y.round() as isize * 3);
+            ctx.redraw();
+            return;
+        }
+        if let Some(templates) = &mut self.templates {
+            templates.scroll_by(-delta.y.round() as isize);
+            ctx.redraw();
+            return;
+        }
         ctx.zoom_at_mouse(delta.y);
     }
 }
 
 impl App {
+    /// What is under `point`. Nothing until the graph cache is updated after a change.
     pub fn find_hover(&self, point: Point2) -> Hover {
-        for node in self.nodes.values() {
+        let Some(cache) = &self.graph_cache else {
+            return Hover::None;
+        };
+        let (mut connections, mut nodes) = (Vec::new(), Vec::new());
+        for indexed in cache.spatial.at(point) {
+            match indexed {
+                Indexed::Connection(index) => connections.push(index),
+                Indexed::Node(node) => nodes.extend(self.nodes.get(&node)),
+            }
+        }
+        if self.routing == EdgeRouting::Orthogonal {
+            for connection in connections {
+                let Some(c) = self.connections.get(connection) else {
+                    continue;
+                };
+                for (index, waypoint) in c.waypoints.iter().enumerate() {
+                    if Rectangle::centered_square(*waypoint, 12.).contains(point) {
+                        return Hover::Waypoint { connection, index };
+                    }
+                }
+            }
+        }
+        // Notes are below the other nodes, so they are only hovered where there's nothing else
+        let (notes, nodes): (Vec<&Node>, Vec<&Node>) = nodes
+            .into_iter()
+            .partition(|node| matches!(node.kind, NodeKind::Note { .. }));
+        for node in nodes.into_iter().chain(notes) {
             for (index, _input) in node.inputs.iter().enumerate() {
                 if get_node_socket_position(node, index, true).contains(point) {
                     return Hover::NodeSocket {
@@ -378,18 +2260,291 @@ impl App {
     }
 }
 
-fn draw_node(
+/// Things in the spatial index of the world
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+enum Indexed {
+    Node(NodeId),
+    /// Index into the connections
+    Connection(usize),
+}
+
+/// The area of a node with its sockets, which stick out of it
+fn node_bounds(node: &Node) -> Rectangle {
+    let rect = get_node_position(node);
+    let sockets = (0..node.inputs.len())
+        .map(|index| get_node_socket_position(node, index, true))
+        .chain((0..node.outputs.len()).map(|index| get_node_socket_position(node, index, false)));
+    let corners = std::iter::once(rect).chain(sockets).flat_map(|r| {
+        [
+            Point2::new(r.x, r.y),
+            Point2::new(r.right(), r.y + r.height),
+        ]
+    });
+    bounding_box(corners, 0.)
+}
+
+/// The smallest rectangle around `points`, grown by `padding` on every side
+fn bounding_box(points: impl Iterator<Item = Point2>, padding: f32) -> Rectangle {
+    let (mut min, mut max) = (
+        Point2::new(f32::INFINITY, f32::INFINITY),
+        Point2::new(f32::NEG_INFINITY, f32::NEG_INFINITY),
+    );
+    for point in points {
+        min = Point2::new(min.x.min(point.x), min.y.min(point.y));
+        max = Point2::new(max.x.max(point.x), max.y.max(point.y));
+    }
+    Rectangle::new(
+        min.x - padding,
+        min.y - padding,
+        max.x - min.x + 2. * padding,
+        max.y - min.y + 2. * padding,
+    )
+}
+
+/// An arrowhead at `at`, pointing along `direction`
+fn draw_arrow(ctx: &mut DrawWorldCtx, at: Point2, direction: Vec2, paint: Paint) {
+    let length = direction.length();
+    if length < 1e-6 {
+        return;
+    }
+    let forward = direction * (8. / length);
+    let side = Vec2::new(-forward.y, forward.x) * 0.6;
+    let tip = at + forward;
+    ctx.draw_line(tip, at - forward + side, paint);
+    ctx.draw_line(tip, at - forward + -side, paint);
+}
+
+/// Dots that travel along a connection, faster for higher rates. `along` gives the point at a
+/// fraction of the connection.
+fn draw_flow_dots(
+    ctx: &mut DrawWorldCtx,
+    along: impl Fn(f32) -> Point2,
+    rate: f32,
+    elapsed: f32,
+    paint: Paint,
+) {
+    const SPACING: f32 = 40.;
+    // World units per second for every unit per second that flows
+    const SPEED_PER_RATE: f32 = 10.;
+    let length: f32 = (0..8)
+        .map(|i| along(i as f32 / 8.).distance(along((i + 1) as f32 / 8.)))
+        .sum();
+    if length < 1. {
+        return;
+    }
+    let speed = (rate * SPEED_PER_RATE).clamp(20., 400.);
+    let dots = (length / SPACING).max(1.) as usize;
+    let offset = (elapsed * speed / length).fract();
+    for dot in 0..dots {
+        let t = (dot as f32 / dots as f32 + offset).fract();
+        ctx.draw_rounded(Rectangle::centered_square(along(t), 6.), paint, paint, 3.);
+    }
+}
+
+/// Trains are drawn like a small node in the middle of their connection
+fn draw_train(
     ctx: &mut DrawWorldCtx,
     theme: &Theme,
+    preset: &Preset,
+    train: &Train,
+    item_or_fluid: &ItemOrFluidId,
+    rate: f32,
+    middle: Point2,
+) {
+    let rectangle = Rectangle::new(middle.x - 70., middle.y - 22., 140., 44.);
+    let bg_paint = Paint::color(theme.layer_color(1));
+    let border_paint = Paint::color(theme.layer_color(3));
+    ctx.draw_rounded(rectangle, bg_paint, border_paint, 5.);
+    if ctx.zoom() < LABEL_MIN_ZOOM {
+        return;
+    }
+
+    let text_paint = Paint::color(theme.background.text).with_font_size(12);
+    let mut text_rect = rectangle.shrink(2.);
+    text_rect.height = 20.;
+    match train.plan(preset, item_or_fluid, rate) {
+        Some(plan) => ctx.fill_text_centered(text_rect, &format!("Trains: {plan}"), text_paint),
+        None => ctx.fill_text_centered(
+            text_rect,
+            "No wagon fits this",
+            Paint::color(theme.error.color).with_font_size(12),
+        ),
+    }
+    text_rect.y += 20.;
+    ctx.fill_text_centered(
+        text_rect,
+        &format!("{}s round trip", train.round_trip),
+        text_paint,
+    );
+}
+
+/// What drawing a node needs to know besides the node itself
+struct NodeStyle<'a> {
+    theme: &'a Theme,
+    preset: &'a Preset,
+    rate_unit: RateUnit,
+    /// Why the recipe of the node can't be used yet
+    lock: Option<RecipeLock>,
+}
+
+fn draw_node(
+    ctx: &mut DrawWorldCtx,
+    style: NodeStyle,
     node: &Node,
     hover: bool,
     hover_socket: Option<(usize, bool)>,
 ) {
+    let NodeStyle {
+        theme,
+        preset,
+        rate_unit,
+        lock,
+    } = style;
     let rectangle = get_node_position(node);
+    let text_paint = Paint::color(theme.background.text).with_font_size(12);
+    if let NodeKind::Note { text } = &node.kind {
+        // Notes look like a sheet of paper, without a border
+        let bg_paint = Paint::color(theme.layer_color(if hover { 3 } else { 2 }));
+        ctx.draw_rounded(rectangle, bg_paint, bg_paint, 0.);
+        if ctx.zoom() < LABEL_MIN_ZOOM {
+            return;
+        }
+        let mut line_rect = rectangle.shrink(5.);
+        line_rect.height = 16.;
+        for line in wrap_text(text, 30).iter().take(5) {
+            ctx.fill_text_centered(line_rect, line, text_paint);
+            line_rect.y += 16.;
+        }
+        return;
+    }
+
     let bg_paint = Paint::color(theme.layer_color(if hover { 2 } else { 1 }));
-    let border_paint = Paint::color(theme.layer_color(if hover { 3 } else { 2 }));
-    ctx.draw_rounded(rectangle, bg_paint, border_paint, 5.);
+    let border_paint = if node.violated {
+        Paint::color(theme.error.color)
+    } else {
+        Paint::color(theme.layer_color(if hover { 3 } else { 2 }))
+    };
+    let radius = match node.kind {
+        NodeKind::Splitter { .. } | NodeKind::Merger { .. } => 30.,
+        _ => 5.,
+    };
+    ctx.draw_rounded(rectangle, bg_paint, border_paint, radius);
 
+    if node.kind == NodeKind::Bus {
+        // Every lane is drawn as a line through the bus
+        let lane_paint = Paint::color(theme.layer_color(3));
+        for lane in 0..node.inputs.len() {
+            let from = get_node_socket_position(node, lane, true).center();
+            let to = get_node_socket_position(node, lane, false).center();
+            ctx.draw_bezier(&lane_paint, from, Vec2::ZERO, to, Some(Vec2::ZERO));
+        }
+    }
+    let show_labels = ctx.zoom() >= LABEL_MIN_ZOOM;
+    let name = node_title(preset, node);
+    let icon = match &node.kind {
+        NodeKind::Recipe { recipe, .. } => Some(preset.icon_for_recipe(&preset.recipes[recipe])),
+        NodeKind::Resource { .. } | NodeKind::Source => node
+            .outputs
+            .first()
+            .and_then(|output| preset.icon_for_item_or_fluid(&output.item_or_fluid)),
+        NodeKind::Demand | NodeKind::Sink => node
+            .inputs
+            .first()
+            .and_then(|input| preset.icon_for_item_or_fluid(&input.item_or_fluid)),
+        _ => None,
+    };
+    let machine = match &node.kind {
+        NodeKind::Recipe { machine, .. } => machine.as_deref(),
+        NodeKind::Resource { drill, .. } => drill.as_deref(),
+        NodeKind::Boiler { boiler: name }
+        | NodeKind::Generator {
+            generator: name, ..
+        }
+        | NodeKind::Reactor { reactor: name } => Some(name.as_str()),
+        NodeKind::ScienceDemand { lab, .. } => lab.as_deref(),
+        _ => None,
+    };
+    let machine_icon = machine
+        .and_then(|machine| preset.item_by_name(machine))
+        .map(|item| preset.icon_for_item(item));
+    let machines = match &node.kind {
+        NodeKind::Recipe { .. } | NodeKind::Resource { .. } | NodeKind::ScienceDemand { .. } => {
+            let count = match &node.kind {
+                NodeKind::ScienceDemand { labs, .. } => *labs,
+                _ => node.machines,
+            };
+            machine.map(|m| match machine_icon {
+                Some(_) => format!("{count:.2}x"),
+                None => format!("{count:.2}x {m}"),
+            })
+        }
+        NodeKind::Boiler { .. }
+        | NodeKind::Generator { .. }
+        | NodeKind::Reactor { .. }
+        | NodeKind::Factory { .. } => Some(format!("{:.2}x", node.machines)),
+        NodeKind::PowerDemand | NodeKind::Demand => None,
+        NodeKind::Source | NodeKind::Sink | NodeKind::Splitter { .. } | NodeKind::Merger { .. } => {
+            Some(rate_unit.format(node.machines))
+        }
+        NodeKind::Bus => Some(format!("{} lanes", node.inputs.len())),
+        NodeKind::Note { .. } => unreachable!("notes are drawn above"),
+    };
+    let mut text_rect = rectangle.shrink(5.);
+    text_rect.height = 20.;
+    if let Some(icon) = &icon {
+        let icon_rect = Rectangle::new(rectangle.center().x - 16., text_rect.y, 32., 32.);
+        ctx.draw_image(icon, icon_rect);
+        text_rect.y += 32.;
+    }
+    if show_labels {
+        ctx.fill_text_centered(text_rect, &name, text_paint);
+    }
+    if let Some(machines) = machines {
+        text_rect.y += 20.;
+        let mut machines_rect = text_rect;
+        if let Some(icon) = &machine_icon {
+            ctx.draw_image(
+                icon,
+                Rectangle::new(text_rect.x, text_rect.y + 2., 16., 16.),
+            );
+            machines_rect.x += 16.;
+            machines_rect.width -= 16.;
+        }
+        if show_labels {
+            ctx.fill_text_centered(machines_rect, &machines, text_paint);
+        }
+    }
+    if show_labels && node.idle() > 0.005 {
+        text_rect.y += 20.;
+        ctx.fill_text_centered(
+            text_rect,
+            &format!("{}x built, {:.0}% idle", node.built, node.idle() * 100.),
+            text_paint,
+        );
+        if let Some((bonus, machines)) = node.speed_bonus_for_fewer_machines() {
+            text_rect.y += 20.;
+            ctx.fill_text_centered(
+                text_rect,
+                &format!("+{}% speed for {machines}x", (bonus * 100.).ceil()),
+                text_paint,
+            );
+        }
+    }
+    if show_labels && let Some(lock) = lock {
+        text_rect.y += 20.;
+        ctx.fill_text_centered(
+            text_rect,
+            &lock.to_string(),
+            Paint::color(theme.error.color).with_font_size(12),
+        );
+    } else if show_labels && node.violated {
+        text_rect.y += 20.;
+        ctx.fill_text_centered(
+            text_rect,
+            "Constraints not met",
+            Paint::color(theme.error.color).with_font_size(12),
+        );
+    }
     let bg_paint = Paint::color(theme.layer_color(2));
     let border_paint = Paint::color(theme.layer_color(3));
 
@@ -397,7 +2552,7 @@ fn draw_node(
     let highlight_border_paint = Paint::color(theme.layer_color(4));
 
     if !node.inputs.is_empty() {
-        for (i, _input) in node.inputs.iter().enumerate() {
+        for (i, input) in node.inputs.iter().enumerate() {
             let rectangle = get_node_socket_position(node, i, true);
             let is_hover = hover_socket == Some((i, true));
 
@@ -412,11 +2567,16 @@ fn draw_node(
                 border_paint
             };
             ctx.draw_rounded(rectangle, bg_paint, border_paint, 2.);
+            if ctx.zoom() >= SOCKET_ICON_MIN_ZOOM
+                && let Some(icon) = preset.icon_for_item_or_fluid(&input.item_or_fluid)
+            {
+                ctx.draw_image(&icon, rectangle.shrink(2.));
+            }
         }
     }
 
     if !node.outputs.is_empty() {
-        for (i, _output) in node.outputs.iter().enumerate() {
+        for (i, output) in node.outputs.iter().enumerate() {
             let rectangle = get_node_socket_position(node, i, false);
             let is_hover = hover_socket == Some((i, false));
 
@@ -431,10 +2591,76 @@ fn draw_node(
                 border_paint
             };
             ctx.draw_rounded(rectangle, bg_paint, border_paint, 2.);
+            if ctx.zoom() >= SOCKET_ICON_MIN_ZOOM
+                && let Some(icon) = preset.icon_for_item_or_fluid(&output.item_or_fluid)
+            {
+                ctx.draw_image(&icon, rectangle.shrink(2.));
+            }
         }
     }
 }
 
+/// The first line of a node, also used to refer to the node in menus
+fn node_title(preset: &Preset, node: &Node) -> String {
+    match &node.kind {
+        NodeKind::Recipe { recipe, .. } => preset.recipes[recipe].name.clone(),
+        NodeKind::Resource { resource: name, .. }
+        | NodeKind::Boiler { boiler: name }
+        | NodeKind::Generator {
+            generator: name, ..
+        }
+        | NodeKind::Reactor { reactor: name }
+        | NodeKind::Factory { name } => name.clone(),
+        NodeKind::PowerDemand => format!("{} MW", node.inputs[0].per_machine),
+        NodeKind::Demand => format!(
+            "{}/s {}",
+            node.inputs[0].per_machine,
+            node.inputs[0].item_or_fluid.name()
+        ),
+        NodeKind::ScienceDemand { .. } => "Research".to_string(),
+        NodeKind::Source => format!("From {}", node.outputs[0].item_or_fluid.name()),
+        NodeKind::Sink => format!("To {}", node.inputs[0].item_or_fluid.name()),
+        NodeKind::Splitter { ratios } => format!("Split {}", format_ratios(ratios)),
+        NodeKind::Merger { ratios } => format!("Merge {}", format_ratios(ratios)),
+        NodeKind::Bus => "Bus".to_string(),
+        NodeKind::Note { text } => wrap_text(text, 30).swap_remove(0),
+    }
+}
+
+/// Ratios like `1:1:2`
+fn format_ratios(ratios: &[f32]) -> String {
+    let ratios: Vec<String> = ratios.iter().map(|r| r.to_string()).collect();
+    ratios.join(":")
+}
+
+/// Parses ratios like `1:1:2`. There have to be at least two, and none can be negative.
+fn parse_ratios(text: &str) -> Option<Vec<f32>> {
+    let ratios: Vec<f32> = text
+        .split(':')
+        .map(|r| r.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    nodes::valid_ratios(&ratios).then_some(ratios)
+}
+
+/// Break `text` into lines of at most `width` characters, at spaces where possible
+fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
 fn get_socket_initial_direction(nodes: &FxHashMap<NodeId, Node>, hover: Hover) -> Vec2 {
     match hover {
         Hover::NodeSocket { node, input, .. } => {
@@ -444,7 +2670,3 @@ fn get_socket_initial_direction(nodes: &FxHashMap<NodeId, Node>, hover: Hover) -
         _ => Vec2::ZERO,
     }
 }
-
-fn get_node_position(node: &Node) -> Rectangle {
-    Rectangle::centered_square(node.position, 100.)
-}
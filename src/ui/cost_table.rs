@@ -0,0 +1,258 @@
+use super::PopupClickResult;
+use crate::{
+    ItemOrFluidId,
+    cost::RawBreakdown,
+    gfx::{DrawUiCtx, Paint},
+    state::Theme,
+    utils::{Point2, Rectangle},
+};
+use rustc_hash::FxHashMap;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CostColumn {
+    Name,
+    Cost,
+    CraftingTime,
+}
+
+impl CostColumn {
+    const ALL: [CostColumn; 3] = [CostColumn::Name, CostColumn::Cost, CostColumn::CraftingTime];
+
+    fn label(self) -> &'static str {
+        match self {
+            CostColumn::Name => "Name",
+            CostColumn::Cost => "Raw cost",
+            CostColumn::CraftingTime => "Crafting time",
+        }
+    }
+
+    fn width(self) -> f32 {
+        match self {
+            CostColumn::Name => 260.0,
+            CostColumn::Cost | CostColumn::CraftingTime => 120.0,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Hover {
+    None,
+    Header(CostColumn),
+    /// Index into the sorted rows
+    Row(usize),
+}
+
+/// A popup that lists the raw cost of every item and fluid, sortable by clicking the headers
+pub struct CostTable {
+    /// The rows and their tooltips both come from here
+    costs: FxHashMap<ItemOrFluidId, RawBreakdown>,
+    /// The rows in their sorted order
+    rows: Vec<ItemOrFluidId>,
+    sort: CostColumn,
+    descending: bool,
+    /// Index of the first visible row
+    scroll: usize,
+    hover: Hover,
+}
+
+impl CostTable {
+    const ROW_HEIGHT: f32 = 25.0;
+    const VISIBLE_ROWS: usize = 20;
+    const PADDING: f32 = 5.0;
+
+    pub fn new(costs: FxHashMap<ItemOrFluidId, RawBreakdown>) -> Self {
+        let mut table = Self {
+            rows: costs.keys().cloned().collect(),
+            costs,
+            sort: CostColumn::Cost,
+            descending: true,
+            scroll: 0,
+            hover: Hover::None,
+        };
+        table.sort();
+        table
+    }
+
+    fn rect(window_size: Point2) -> Rectangle {
+        let width: f32 = CostColumn::ALL.iter().map(|c| c.width()).sum();
+        let height = (Self::VISIBLE_ROWS + 1) as f32 * Self::ROW_HEIGHT;
+        Rectangle::new(
+            (window_size.x - width) / 2.0,
+            (window_size.y - height) / 2.0,
+            width,
+            height,
+        )
+    }
+
+    fn sort(&mut self) {
+        let costs = &self.costs;
+        self.rows.sort_by(|a, b| {
+            let ordering = match self.sort {
+                CostColumn::Name => a.name().cmp(b.name()),
+                CostColumn::Cost => costs[a].cost.cost.total_cmp(&costs[b].cost.cost),
+                CostColumn::CraftingTime => {
+                    (costs[a].cost.crafting_time).total_cmp(&costs[b].cost.crafting_time)
+                }
+            };
+            let ordering = if self.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            };
+            ordering.then_with(|| a.name().cmp(b.name()))
+        });
+    }
+
+    /// Scroll by a number of rows, negative values scroll up
+    pub fn scroll_by(&mut self, rows: isize) {
+        let max = self.rows.len().saturating_sub(Self::VISIBLE_ROWS);
+        self.scroll = self.scroll.saturating_add_signed(rows).min(max);
+    }
+
+    pub(crate) fn mouse_move(&mut self, window_size: Point2, mouse: Point2) {
+        let rect = Self::rect(window_size);
+        if !rect.contains(mouse) {
+            self.hover = Hover::None;
+            return;
+        }
+
+        let row = ((mouse.y - rect.y) / Self::ROW_HEIGHT) as usize;
+        self.hover = if row == 0 {
+            let mut x = rect.x;
+            let column = CostColumn::ALL.into_iter().find(|c| {
+                x += c.width();
+                mouse.x < x
+            });
+            column.map_or(Hover::None, Hover::Header)
+        } else if self.scroll + row - 1 < self.rows.len() {
+            Hover::Row(self.scroll + row - 1)
+        } else {
+            Hover::None
+        };
+    }
+
+    pub(crate) fn try_click(&mut self) -> PopupClickResult {
+        match self.hover {
+            Hover::None => PopupClickResult::Close,
+            Hover::Header(column) => {
+                if self.sort == column {
+                    self.descending = !self.descending;
+                } else {
+                    self.sort = column;
+                    self.descending = column != CostColumn::Name;
+                }
+                self.sort();
+                PopupClickResult::None
+            }
+            Hover::Row(_) => PopupClickResult::None,
+        }
+    }
+
+    pub fn draw(&self, ctx: &mut DrawUiCtx, theme: &Theme) {
+        let rect = Self::rect(ctx.window_size);
+        let text = Paint::color(theme.background.text).with_font_size(16);
+
+        ctx.draw_fill_border(
+            rect,
+            Paint::color(theme.layer_color(1)),
+            Paint::color(theme.layer_color(2)),
+        );
+
+        let mut x = rect.x;
+        for column in CostColumn::ALL {
+            let header = Rectangle::new(x, rect.y, column.width(), Self::ROW_HEIGHT);
+            let background = if self.hover == Hover::Header(column) {
+                theme.layer_color(3)
+            } else {
+                theme.layer_color(2)
+            };
+            ctx.draw_fill(header, Paint::color(background));
+            let label = match (self.sort == column, self.descending) {
+                (true, true) => format!("{} v", column.label()),
+                (true, false) => format!("{} ^", column.label()),
+                (false, _) => column.label().to_string(),
+            };
+            ctx.fill_text_centered(header, &label, text);
+            x += column.width();
+        }
+
+        let visible = self.rows.iter().enumerate().skip(self.scroll);
+        for (row, (index, item_or_fluid)) in visible.take(Self::VISIBLE_ROWS).enumerate() {
+            let y = rect.y + (row + 1) as f32 * Self::ROW_HEIGHT;
+            if self.hover == Hover::Row(index) {
+                ctx.draw_fill(
+                    Rectangle::new(rect.x, y, rect.width, Self::ROW_HEIGHT),
+                    Paint::color(theme.layer_color(2)),
+                );
+            }
+            let cost = self.costs[item_or_fluid].cost;
+            let mut x = rect.x + Self::PADDING;
+            for column in CostColumn::ALL {
+                let label = match column {
+                    CostColumn::Name => item_or_fluid.name().to_string(),
+                    CostColumn::Cost => format!("{:.2}", cost.cost),
+                    CostColumn::CraftingTime => format!("{:.1}s", cost.crafting_time),
+                };
+                ctx.fill_text(x, y + Self::PADDING, &label, text).ok();
+                x += column.width();
+            }
+        }
+
+        if let Hover::Row(index) = self.hover {
+            let item_or_fluid = &self.rows[index];
+            self.draw_breakdown(
+                ctx,
+                theme,
+                item_or_fluid,
+                rect.x + rect.width + Self::PADDING,
+            );
+        }
+    }
+
+    /// The tooltip for a hovered row, listing the exact raw resources that go into it
+    fn draw_breakdown(
+        &self,
+        ctx: &mut DrawUiCtx,
+        theme: &Theme,
+        item_or_fluid: &ItemOrFluidId,
+        x: f32,
+    ) {
+        let lines: Vec<(String, Paint)> = match self.costs.get(item_or_fluid) {
+            Some(breakdown) => {
+                let paint = Paint::color(theme.background.text).with_font_size(16);
+                let crafting_time = breakdown.cost.crafting_time;
+                breakdown
+                    .resources
+                    .iter()
+                    .map(|(resource, amount)| (format!("{amount:.2} {}", resource.name()), paint))
+                    .chain([(format!("{crafting_time:.1}s crafting"), paint)])
+                    .collect()
+            }
+            None => vec![(
+                "No breakdown".to_string(),
+                Paint::color(theme.error.color).with_font_size(16),
+            )],
+        };
+
+        let y = ctx.ui_mouse.y;
+        ctx.draw_fill_border(
+            Rectangle::new(
+                x,
+                y,
+                280.0,
+                lines.len() as f32 * Self::ROW_HEIGHT + Self::PADDING * 2.0,
+            ),
+            Paint::color(theme.layer_color(1)),
+            Paint::color(theme.layer_color(2)),
+        );
+        for (i, (line, paint)) in lines.into_iter().enumerate() {
+            ctx.fill_text(
+                x + Self::PADDING,
+                y + Self::PADDING + i as f32 * Self::ROW_HEIGHT,
+                &line,
+                paint,
+            )
+            .ok();
+        }
+    }
+}
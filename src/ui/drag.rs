@@ -1,6 +1,6 @@
 use super::{EventCtx, utils::get_node_socket_position};
 use crate::{
-    Node, NodeId, SocketPos,
+    Connection, Node, NodeId, SocketPos,
     gfx::{DrawWorldCtx, Paint},
     utils::{Point2, Vec2},
 };
@@ -25,6 +25,17 @@ pub enum DragState {
         pos: SocketPos,
         initial_direction: Vec2,
     },
+    Waypoint {
+        connection: usize,
+        index: usize,
+    },
+}
+
+/// Something in the graph that a drag moved
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Moved {
+    Node(NodeId),
+    Waypoint { connection: usize },
 }
 
 impl Drag {
@@ -92,6 +103,12 @@ impl Drag {
         self.overcame_min_distance = false;
     }
 
+    pub fn start_drag_waypoint(&mut self, start_drag: Point2, connection: usize, index: usize) {
+        self.state = DragState::Waypoint { connection, index };
+        self.start_drag = Some(start_drag);
+        self.overcame_min_distance = false;
+    }
+
     pub fn start_drag_background(&mut self, start_drag: Point2) {
         self.state = DragState::Background;
         self.start_drag = Some(start_drag);
@@ -104,12 +121,14 @@ impl Drag {
         self.overcame_min_distance = false;
     }
 
+    /// Returns the node or waypoint that was moved, if any
     pub(crate) fn mouse_move(
         &mut self,
         delta: Vec2,
         ctx: &mut EventCtx,
         nodes: &mut FxHashMap<NodeId, Node>,
-    ) {
+        connections: &mut [Connection],
+    ) -> Option<Moved> {
         if let Some(start_point) = self.start_drag {
             if (ctx.world_mouse - start_point).length() > 10. {
                 self.overcame_min_distance = true;
@@ -119,16 +138,31 @@ impl Drag {
             DragState::Background { .. } => {
                 ctx.translate_by(delta);
                 ctx.redraw();
-                return;
+                None
             }
             DragState::Node { node_id, .. } => {
                 let node = nodes.get_mut(&node_id).unwrap();
-                node.position += delta;
+                node.position += ctx.ui_to_world_delta(delta);
+                ctx.redraw();
+                Some(Moved::Node(*node_id))
+            }
+            DragState::Waypoint { connection, index } => {
+                if let Some(waypoint) = connections
+                    .get_mut(*connection)
+                    .and_then(|c| c.waypoints.get_mut(*index))
+                {
+                    *waypoint += ctx.ui_to_world_delta(delta);
+                }
                 ctx.redraw();
-                return;
+                Some(Moved::Waypoint {
+                    connection: *connection,
+                })
             }
-            DragState::LineFromNodeSocket { .. } => ctx.redraw(),
-            _ => {}
+            DragState::LineFromNodeSocket { .. } => {
+                ctx.redraw();
+                None
+            }
+            _ => None,
         }
     }
 
@@ -138,6 +172,7 @@ impl Drag {
             DragState::Background { .. }
                 | DragState::LineFromNodeSocket { .. }
                 | DragState::Node { .. }
+                | DragState::Waypoint { .. }
         ) && !self.overcame_min_distance
     }
 }
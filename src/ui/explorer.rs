@@ -0,0 +1,209 @@
+use crate::{
+    ItemOrFluidId,
+    gfx::{DrawUiCtx, Paint},
+    state::{Preset, RecipeId, Theme},
+    utils::{Point2, Rectangle},
+};
+
+/// Something in the explorer that can be clicked
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Target {
+    /// Adds the recipe to the factory
+    Recipe(RecipeId),
+    /// Re-centres the explorer on the item
+    Item(ItemOrFluidId),
+}
+
+pub enum ExplorerClick {
+    None,
+    Close,
+    /// Add the recipe to the factory, at the position of the explorer
+    Pick(RecipeId),
+}
+
+/// A view of the recipes around an item: the ones that make it on the left, and the ones that use
+/// it on the right
+pub struct Explorer {
+    item: ItemOrFluidId,
+    producers: Vec<RecipeId>,
+    consumers: Vec<RecipeId>,
+    scroll: f32,
+    hover: Option<Target>,
+    /// Where picked recipes are placed in the factory
+    pub position: Point2,
+}
+
+impl Explorer {
+    const ENTRY_WIDTH: f32 = 300.0;
+    const ENTRY_HEIGHT: f32 = 60.0;
+    const ICON_SIZE: f32 = 26.0;
+    const CENTER_WIDTH: f32 = 200.0;
+    const GAP: f32 = 40.0;
+    const TOP: f32 = 40.0;
+
+    pub fn new(preset: &Preset, item: ItemOrFluidId, position: Point2) -> Self {
+        let mut explorer = Self {
+            item: item.clone(),
+            producers: Vec::new(),
+            consumers: Vec::new(),
+            scroll: 0.0,
+            hover: None,
+            position,
+        };
+        explorer.center_on(preset, item);
+        explorer
+    }
+
+    fn center_on(&mut self, preset: &Preset, item: ItemOrFluidId) {
+        self.producers = preset.recipes_producing(&item).map(|r| r.id).collect();
+        self.consumers = preset.recipes_consuming(&item).map(|r| r.id).collect();
+        self.item = item;
+        self.scroll = 0.0;
+        self.hover = None;
+    }
+
+    pub fn scroll_by(&mut self, amount: f32) {
+        let rows = self.producers.len().max(self.consumers.len());
+        let max = (rows as f32 * Self::ENTRY_HEIGHT - Self::ENTRY_HEIGHT).max(0.0);
+        self.scroll = (self.scroll + amount).clamp(0.0, max);
+    }
+
+    fn center_rect(window_size: Point2) -> Rectangle {
+        Rectangle::new(
+            (window_size.x - Self::CENTER_WIDTH) / 2.0,
+            (window_size.y - Self::ENTRY_HEIGHT) / 2.0,
+            Self::CENTER_WIDTH,
+            Self::ENTRY_HEIGHT,
+        )
+    }
+
+    /// Everything that can be clicked, with where it is drawn. Recipes come before their
+    /// ingredients and products.
+    fn layout(&self, preset: &Preset, window_size: Point2) -> Vec<(Rectangle, Target)> {
+        let center = Self::center_rect(window_size);
+        let columns = [
+            (center.x - Self::GAP - Self::ENTRY_WIDTH, &self.producers),
+            (center.x + center.width + Self::GAP, &self.consumers),
+        ];
+
+        let mut layout = Vec::new();
+        for (x, recipes) in columns {
+            for (row, recipe) in recipes.iter().enumerate() {
+                let y = Self::TOP + row as f32 * Self::ENTRY_HEIGHT - self.scroll;
+                let rect = Rectangle::new(x, y, Self::ENTRY_WIDTH, Self::ENTRY_HEIGHT);
+                layout.push((rect, Target::Recipe(*recipe)));
+
+                // Ingredients from the left, products from the right
+                let recipe = &preset.recipes[recipe];
+                let icons_y = y + Self::ENTRY_HEIGHT - Self::ICON_SIZE - 2.0;
+                for (i, ingredient) in recipe.ingredients.iter().enumerate() {
+                    let icon = Rectangle::new(
+                        x + 2.0 + i as f32 * Self::ICON_SIZE,
+                        icons_y,
+                        Self::ICON_SIZE,
+                        Self::ICON_SIZE,
+                    );
+                    layout.push((icon, Target::Item(ingredient.item_or_fluid.clone())));
+                }
+                for (i, product) in recipe.results.iter().rev().enumerate() {
+                    let icon = Rectangle::new(
+                        x + Self::ENTRY_WIDTH - 2.0 - (i + 1) as f32 * Self::ICON_SIZE,
+                        icons_y,
+                        Self::ICON_SIZE,
+                        Self::ICON_SIZE,
+                    );
+                    layout.push((icon, Target::Item(product.item_or_fluid.clone())));
+                }
+            }
+        }
+        layout
+    }
+
+    pub(crate) fn mouse_move(&mut self, preset: &Preset, window_size: Point2, mouse: Point2) {
+        // Icons are laid out after the recipe they belong to, so they win
+        self.hover = self
+            .layout(preset, window_size)
+            .into_iter()
+            .rev()
+            .find(|(rect, _)| rect.contains(mouse))
+            .map(|(_, target)| target);
+    }
+
+    pub(crate) fn try_click(&mut self, preset: &Preset) -> ExplorerClick {
+        match self.hover.clone() {
+            None => ExplorerClick::Close,
+            Some(Target::Item(item)) => {
+                self.center_on(preset, item);
+                ExplorerClick::None
+            }
+            Some(Target::Recipe(recipe)) => ExplorerClick::Pick(recipe),
+        }
+    }
+
+    pub fn draw(&self, ctx: &mut DrawUiCtx, preset: &Preset, theme: &Theme) {
+        let text = Paint::color(theme.background.text).with_font_size(16);
+        ctx.draw_fill(
+            Rectangle::new(0.0, 0.0, ctx.window_size.x, ctx.window_size.y),
+            Paint::color(theme.layer_color(0)),
+        );
+
+        let center = Self::center_rect(ctx.window_size);
+        ctx.draw_fill_border(
+            center,
+            Paint::color(theme.layer_color(2)),
+            Paint::color(theme.layer_color(3)),
+        );
+        if let Some(icon) = preset.icon_for_item_or_fluid(&self.item) {
+            ctx.draw_image(
+                &icon,
+                Rectangle::new(center.x + 5.0, center.y + 5.0, 50.0, 50.0),
+            );
+        }
+        ctx.fill_text(center.x + 60.0, center.y + 20.0, self.item.name(), text)
+            .ok();
+
+        let producers_x = center.x - Self::GAP - Self::ENTRY_WIDTH;
+        let consumers_x = center.x + center.width + Self::GAP;
+        ctx.fill_text(producers_x, 10.0, "Made by", text).ok();
+        ctx.fill_text(consumers_x, 10.0, "Used by", text).ok();
+
+        for (rect, target) in self.layout(preset, ctx.window_size) {
+            let hovered = self.hover.as_ref() == Some(&target);
+            match &target {
+                Target::Recipe(recipe) => {
+                    let background = if hovered {
+                        theme.layer_color(3)
+                    } else {
+                        theme.layer_color(1)
+                    };
+                    ctx.draw_fill_border(
+                        rect,
+                        Paint::color(background),
+                        Paint::color(theme.layer_color(2)),
+                    );
+                    ctx.fill_text(
+                        rect.x + 4.0,
+                        rect.y + 4.0,
+                        &preset.recipes[recipe].name,
+                        text,
+                    )
+                    .ok();
+                }
+                Target::Item(item) => {
+                    if hovered {
+                        ctx.draw_fill(rect, Paint::color(theme.layer_color(4)));
+                    }
+                    match preset.icon_for_item_or_fluid(item) {
+                        Some(icon) => ctx.draw_image(&icon, rect.shrink(1.)),
+                        None => ctx.fill_text_centered(rect, &item.name()[..1], text),
+                    }
+                }
+            }
+        }
+
+        if let Some(Target::Item(item)) = &self.hover {
+            ctx.fill_text(center.x, center.y + center.height + 10.0, item.name(), text)
+                .ok();
+        }
+    }
+}
@@ -9,6 +9,11 @@ pub enum Hover {
         socket: usize,
         input: bool,
     },
+    /// A waypoint of an orthogonally routed connection
+    Waypoint {
+        connection: usize,
+        index: usize,
+    },
 }
 
 impl Hover {
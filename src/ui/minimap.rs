@@ -0,0 +1,196 @@
+use super::utils::{get_node_position, get_node_socket_position};
+use crate::{
+    Connection, Node, NodeId, NodeKind,
+    gfx::{DrawUiCtx, EventCtx, Paint},
+    state::{Preset, Theme},
+    utils::{Point2, Rectangle, Vec2},
+};
+use rustc_hash::FxHashMap;
+
+/// An overview of the whole factory in a corner of the window, with the part that is on screen
+/// outlined. Clicking or dragging on it pans the view there.
+#[derive(Default)]
+pub struct Minimap {
+    /// The mouse went down on the minimap and hasn't been released yet
+    dragging: bool,
+}
+
+impl Minimap {
+    const WIDTH: f32 = 220.0;
+    const HEIGHT: f32 = 160.0;
+    const MARGIN: f32 = 10.0;
+    /// World space left around the nodes
+    const PADDING: f32 = 100.0;
+
+    fn rect(window_size: Point2) -> Rectangle {
+        Rectangle::new(
+            Self::MARGIN,
+            window_size.y - Self::HEIGHT - Self::MARGIN,
+            Self::WIDTH,
+            Self::HEIGHT,
+        )
+    }
+
+    pub fn contains(&self, window_size: Point2, mouse: Point2) -> bool {
+        Self::rect(window_size).contains(mouse)
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.dragging
+    }
+
+    /// The world area that is shown, the scale it is shown at, and where its top left corner is
+    /// drawn. `None` without nodes.
+    fn layout(window_size: Point2, nodes: &FxHashMap<NodeId, Node>) -> Option<Layout> {
+        let mut rects = nodes.values().map(get_node_position);
+        let first = rects.next()?;
+        let (mut min, mut max) = (
+            Point2::new(first.x, first.y),
+            Point2::new(first.right(), first.y + first.height),
+        );
+        for rect in rects {
+            min = Point2::new(min.x.min(rect.x), min.y.min(rect.y));
+            max = Point2::new(max.x.max(rect.right()), max.y.max(rect.y + rect.height));
+        }
+        min -= Vec2::new(Self::PADDING, Self::PADDING);
+        max += Vec2::new(Self::PADDING, Self::PADDING);
+
+        let area = Self::rect(window_size);
+        let scale = (area.width / (max.x - min.x)).min(area.height / (max.y - min.y));
+        // Centered in the minimap along the axis that has space left
+        let origin = Point2::new(
+            area.x + (area.width - (max.x - min.x) * scale) / 2.0,
+            area.y + (area.height - (max.y - min.y) * scale) / 2.0,
+        );
+        Some(Layout { min, scale, origin })
+    }
+
+    pub fn draw(
+        &self,
+        ctx: &mut DrawUiCtx,
+        nodes: &FxHashMap<NodeId, Node>,
+        connections: &[Connection],
+        preset: &Preset,
+        theme: &Theme,
+    ) {
+        let area = Self::rect(ctx.window_size);
+        ctx.draw_fill_border(
+            area,
+            Paint::color(theme.layer_color(1)),
+            Paint::color(theme.layer_color(3)),
+        );
+        let Some(layout) = Self::layout(ctx.window_size, nodes) else {
+            return;
+        };
+
+        for connection in connections {
+            let from = get_node_socket_position(
+                &nodes[&connection.src.node_id],
+                connection.src.socket_index,
+                false,
+            );
+            let to = get_node_socket_position(
+                &nodes[&connection.dst.node_id],
+                connection.dst.socket_index,
+                true,
+            );
+            let item_or_fluid =
+                &nodes[&connection.src.node_id].outputs[connection.src.socket_index].item_or_fluid;
+            ctx.draw_line(
+                layout.to_minimap(from.center()),
+                layout.to_minimap(to.center()),
+                Paint::color(preset.color_for_item_or_fluid(item_or_fluid)),
+            );
+        }
+        for node in nodes.values() {
+            let rect = get_node_position(node);
+            let top_left = layout.to_minimap(Point2::new(rect.x, rect.y));
+            // At least a pixel, so small nodes don't vanish
+            let rect = Rectangle::new(
+                top_left.x,
+                top_left.y,
+                (rect.width * layout.scale).max(1.0),
+                (rect.height * layout.scale).max(1.0),
+            );
+            let color = if matches!(node.kind, NodeKind::Note { .. }) {
+                theme.layer_color(2)
+            } else {
+                theme.layer_color(4)
+            };
+            ctx.draw_fill(rect, Paint::color(color));
+        }
+
+        // The part of the world that is on screen, cut to the minimap
+        let top_left = layout.to_minimap(ctx.canvas.ui_to_world(Point2::ZERO));
+        let bottom_right = layout.to_minimap(ctx.canvas.ui_to_world(ctx.window_size));
+        let (left, top) = (top_left.x.max(area.x), top_left.y.max(area.y));
+        let (right, bottom) = (
+            bottom_right.x.min(area.right()),
+            bottom_right.y.min(area.y + area.height),
+        );
+        if left < right && top < bottom {
+            ctx.draw_border(
+                Rectangle::new(left, top, right - left, bottom - top),
+                Paint::color(theme.primary.color),
+            );
+        }
+    }
+
+    /// Start panning if the mouse is on the minimap. Returns whether it was.
+    pub fn mouse_down(&mut self, ctx: &mut EventCtx, nodes: &FxHashMap<NodeId, Node>) -> bool {
+        if !self.contains(ctx.window_size, ctx.ui_mouse) {
+            return false;
+        }
+        self.dragging = true;
+        Self::center_view(ctx, nodes);
+        true
+    }
+
+    pub fn mouse_move(&mut self, ctx: &mut EventCtx, nodes: &FxHashMap<NodeId, Node>) {
+        if self.dragging {
+            Self::center_view(ctx, nodes);
+        }
+    }
+
+    pub fn mouse_up(&mut self) {
+        self.dragging = false;
+    }
+
+    /// Move the view so the point of the world under the mouse is in the middle of the window
+    fn center_view(ctx: &mut EventCtx, nodes: &FxHashMap<NodeId, Node>) {
+        let Some(layout) = Self::layout(ctx.window_size, nodes) else {
+            return;
+        };
+        let target = layout.to_world(ctx.ui_mouse);
+        let middle = ctx.canvas.ui_to_world(Point2::new(
+            ctx.window_size.x / 2.0,
+            ctx.window_size.y / 2.0,
+        ));
+        ctx.translate_by((middle - target) * ctx.canvas.zoom);
+    }
+}
+
+struct Layout {
+    /// The top left corner of the world area that is shown
+    min: Point2,
+    /// Minimap pixels per world unit
+    scale: f32,
+    /// Where `min` is drawn
+    origin: Point2,
+}
+
+impl Layout {
+    fn to_minimap(&self, world: Point2) -> Point2 {
+        Point2::new(
+            self.origin.x + (world.x - self.min.x) * self.scale,
+            self.origin.y + (world.y - self.min.y) * self.scale,
+        )
+    }
+
+    fn to_world(&self, minimap: Point2) -> Point2 {
+        Point2::new(
+            self.min.x + (minimap.x - self.origin.x) / self.scale,
+            self.min.y + (minimap.y - self.origin.y) / self.scale,
+        )
+    }
+}
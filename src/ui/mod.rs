@@ -1,8 +1,16 @@
 pub mod app;
 pub mod context_menu;
+pub mod cost_table;
 pub mod drag;
+pub mod explorer;
 pub mod hover;
+pub mod minimap;
+pub mod prompt;
+pub mod rate_unit;
+pub mod routing;
 pub mod selector;
+pub mod spatial;
+pub mod templates;
 pub mod utils;
 
 use crate::{
@@ -16,9 +24,11 @@ pub trait App {
     fn resize(&mut self, _ctx: &mut EventCtx, _width: u32, _height: u32) {}
     fn key_down(&mut self, _ctx: &mut EventCtx, _key: KeyCode) {}
     fn key_up(&mut self, _ctx: &mut EventCtx, _key: KeyCode) {}
+    fn text_input(&mut self, _ctx: &mut EventCtx, _text: &str) {}
     fn mouse_move(&mut self, _ctx: &mut EventCtx, _delta: Vec2) {}
     fn mouse_down(&mut self, _ctx: &mut EventCtx, _button: MouseButton) {}
     fn mouse_up(&mut self, _ctx: &mut EventCtx, _button: MouseButton) {}
+    fn mouse_double_click(&mut self, _ctx: &mut EventCtx, _button: MouseButton) {}
     fn mouse_scroll(&mut self, _ctx: &mut EventCtx, _delta: Vec2) {}
 }
 
@@ -0,0 +1,96 @@
+use super::app::App;
+use crate::{
+    gfx::{DrawUiCtx, Paint},
+    state::Theme,
+    utils::{Point2, Rectangle},
+};
+
+/// A popup that asks the user to type in a value
+pub struct Prompt {
+    pub label: String,
+    pub text: String,
+    on_submit: Box<dyn FnOnce(&str, &mut App)>,
+}
+
+impl Prompt {
+    const WIDTH: f32 = 300.0;
+    const HEIGHT: f32 = 30.0;
+
+    pub fn new(
+        label: impl Into<String>,
+        text: impl Into<String>,
+        on_submit: impl FnOnce(&str, &mut App) + 'static,
+    ) -> Self {
+        Self {
+            label: label.into(),
+            text: text.into(),
+            on_submit: Box::new(on_submit),
+        }
+    }
+
+    /// A prompt that only submits if the text is a valid number
+    pub fn new_number(
+        label: impl Into<String>,
+        value: f32,
+        on_submit: impl FnOnce(f32, &mut App) + 'static,
+    ) -> Self {
+        Self::new(label, value.to_string(), move |text, app| {
+            match text.trim().parse::<f32>() {
+                Ok(value) if value.is_finite() => on_submit(value, app),
+                _ => println!("Invalid number {text:?}"),
+            }
+        })
+    }
+
+    pub fn text_input(&mut self, text: &str) {
+        self.text.push_str(text);
+    }
+
+    pub fn backspace(&mut self) {
+        self.text.pop();
+    }
+
+    pub fn submit(self, app: &mut App) {
+        (self.on_submit)(&self.text, app);
+    }
+
+    fn rect(window_size: Point2) -> Rectangle {
+        Rectangle::new(
+            (window_size.x - Self::WIDTH) / 2.0,
+            (window_size.y - Self::HEIGHT * 2.0) / 2.0,
+            Self::WIDTH,
+            Self::HEIGHT * 2.0,
+        )
+    }
+
+    pub fn contains(&self, window_size: Point2, mouse: Point2) -> bool {
+        Self::rect(window_size).contains(mouse)
+    }
+
+    pub fn draw(&self, ctx: &mut DrawUiCtx, theme: &Theme) {
+        let rect = Self::rect(ctx.window_size);
+        let label_rect = Rectangle::new(rect.x, rect.y, rect.width, Self::HEIGHT);
+        let text_rect = Rectangle::new(rect.x, rect.y + Self::HEIGHT, rect.width, Self::HEIGHT);
+
+        ctx.draw_fill_border(
+            rect,
+            Paint::color(theme.layer_color(1)),
+            Paint::color(theme.layer_color(2)),
+        );
+        ctx.fill_text_centered(
+            label_rect,
+            &self.label,
+            Paint::color(theme.background.text).with_font_size(16),
+        );
+        ctx.draw_fill_border(
+            text_rect.shrink(3.),
+            Paint::color(theme.layer_color(2)),
+            Paint::color(theme.layer_color(3)),
+        );
+        ctx.fill_text_centered(
+            text_rect,
+            &format!("{}_", self.text),
+            Paint::color(theme.background.text).with_font_size(16),
+        );
+    }
+}
@@ -0,0 +1,28 @@
+/// The time unit that rates are shown in. The solver always works per second.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum RateUnit {
+    #[default]
+    Second,
+    Minute,
+    Hour,
+}
+
+impl RateUnit {
+    pub fn next(self) -> Self {
+        match self {
+            RateUnit::Second => RateUnit::Minute,
+            RateUnit::Minute => RateUnit::Hour,
+            RateUnit::Hour => RateUnit::Second,
+        }
+    }
+
+    /// `rate` is per second
+    pub fn format(self, rate: f32) -> String {
+        let (factor, suffix) = match self {
+            RateUnit::Second => (1.0, "/s"),
+            RateUnit::Minute => (60.0, "/min"),
+            RateUnit::Hour => (3600.0, "/h"),
+        };
+        format!("{:.2}{suffix}", rate * factor)
+    }
+}
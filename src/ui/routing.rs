@@ -0,0 +1,357 @@
+//! Orthogonal routing of connections around nodes. Routes are searched on a sparse grid, made of
+//! the edges of the nodes that are near the connection, so they hug the nodes they go around.
+
+use crate::{
+    SocketPos,
+    utils::{Point2, Rectangle, Vec2},
+};
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+/// How connections are drawn
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum EdgeRouting {
+    /// A single curve from socket to socket, straight through anything in between
+    #[default]
+    Bezier,
+    /// Horizontal and vertical lines that go around nodes and through waypoints
+    Orthogonal,
+}
+
+impl EdgeRouting {
+    pub fn next(self) -> Self {
+        match self {
+            EdgeRouting::Bezier => EdgeRouting::Orthogonal,
+            EdgeRouting::Orthogonal => EdgeRouting::Bezier,
+        }
+    }
+}
+
+/// Space kept between routes and the nodes they go around
+const MARGIN: f32 = 10.0;
+/// How far routes go straight out of their sockets before turning
+const STUB: f32 = 20.0;
+/// Extra length that every bend costs, so routes don't zigzag
+const BEND_COST: f32 = 40.0;
+/// Nodes further than this from the straight path between two points can't be in the way
+pub const SEARCH_PADDING: f32 = 150.0;
+/// Distance between parallel routes of different outputs
+const LANE_SPACING: f32 = 6.0;
+
+/// One connection to route. The directions point out of the sockets.
+pub struct RouteRequest<'a> {
+    pub src: SocketPos,
+    pub from: Point2,
+    pub from_direction: Vec2,
+    pub to: Point2,
+    pub to_direction: Vec2,
+    pub waypoints: &'a [Point2],
+}
+
+/// Route every connection around `obstacles`, then spread out routes of different outputs that
+/// run along the same line. Routes from the same output stay together as a bundle.
+pub fn route_all(requests: &[RouteRequest], obstacles: &[Rectangle]) -> Vec<Vec<Point2>> {
+    let obstacles: Vec<Rectangle> = obstacles
+        .iter()
+        .map(|o| {
+            Rectangle::new(
+                o.x - MARGIN,
+                o.y - MARGIN,
+                o.width + 2.0 * MARGIN,
+                o.height + 2.0 * MARGIN,
+            )
+        })
+        .collect();
+    let mut routes: Vec<Vec<Point2>> = requests.iter().map(|r| route(r, &obstacles)).collect();
+    separate(requests, &mut routes);
+    routes
+}
+
+fn route(request: &RouteRequest, obstacles: &[Rectangle]) -> Vec<Point2> {
+    let stub = |point: Point2, direction: Vec2| {
+        let length = direction.length().max(f32::EPSILON);
+        point + direction * (STUB / length)
+    };
+    let start = stub(request.from, request.from_direction);
+    let end = stub(request.to, request.to_direction);
+
+    let mut points = vec![request.from, start];
+    let mut stops: Vec<Point2> = request.waypoints.to_vec();
+    stops.push(end);
+    let mut at = start;
+    for stop in stops {
+        let leg = search(at, stop, obstacles)
+            .unwrap_or_else(|| vec![at, Point2::new(stop.x, at.y), stop]);
+        points.extend(leg.into_iter().skip(1));
+        at = stop;
+    }
+    points.push(request.to);
+    simplify(points)
+}
+
+/// The shortest route from `start` to `end` with few bends, that doesn't cross `obstacles`.
+/// `None` if there is none, for example when `start` is inside a node.
+fn search(start: Point2, end: Point2, obstacles: &[Rectangle]) -> Option<Vec<Point2>> {
+    let area = Rectangle::new(
+        start.x.min(end.x) - SEARCH_PADDING,
+        start.y.min(end.y) - SEARCH_PADDING,
+        (start.x - end.x).abs() + 2.0 * SEARCH_PADDING,
+        (start.y - end.y).abs() + 2.0 * SEARCH_PADDING,
+    );
+    let nearby: Vec<&Rectangle> = obstacles.iter().filter(|o| o.overlaps(&area)).collect();
+
+    let mut xs = vec![start.x, end.x];
+    let mut ys = vec![start.y, end.y];
+    for obstacle in &nearby {
+        xs.extend([obstacle.x, obstacle.x + obstacle.width]);
+        ys.extend([obstacle.y, obstacle.y + obstacle.height]);
+    }
+    for coordinates in [&mut xs, &mut ys] {
+        coordinates.sort_by(f32::total_cmp);
+        coordinates.dedup_by(|a, b| (*a - *b).abs() < 0.5);
+    }
+    let index_of = |coordinates: &[f32], value: f32| {
+        coordinates
+            .iter()
+            .position(|c| (c - value).abs() < 0.5)
+            .unwrap()
+    };
+    let (start_cell, end_cell) = (
+        (index_of(&xs, start.x), index_of(&ys, start.y)),
+        (index_of(&xs, end.x), index_of(&ys, end.y)),
+    );
+    let point = |(x, y): (usize, usize)| Point2::new(xs[x], ys[y]);
+    let blocked = |a: Point2, b: Point2| nearby.iter().any(|o| crosses(o, a, b));
+
+    // Dijkstra over (cell, direction of the last step), with the manhattan distance to the end
+    // as heuristic. Costs are rounded to whole units so they can be ordered.
+    const DIRECTIONS: [(isize, isize); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+    let state = |(x, y): (usize, usize), direction: usize| (y * xs.len() + x) * 5 + direction;
+    let heuristic = |cell| {
+        let p: Point2 = point(cell);
+        ((p.x - end.x).abs() + (p.y - end.y).abs()) as u64
+    };
+    let mut best = vec![u64::MAX; xs.len() * ys.len() * 5];
+    let mut previous: Vec<Option<((usize, usize), usize)>> = vec![None; best.len()];
+    let mut queue = BinaryHeap::new();
+    // Direction 4 is the start, where any direction can be taken without a bend
+    best[state(start_cell, 4)] = 0;
+    queue.push(Reverse((heuristic(start_cell), 0, start_cell, 4)));
+
+    while let Some(Reverse((_, cost, cell, direction))) = queue.pop() {
+        if cost > best[state(cell, direction)] {
+            continue;
+        }
+        if cell == end_cell {
+            let mut path = vec![point(cell)];
+            let mut at = (cell, direction);
+            while let Some(before) = previous[state(at.0, at.1)] {
+                path.push(point(before.0));
+                at = before;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for (next_direction, (dx, dy)) in DIRECTIONS.iter().enumerate() {
+            let (Some(x), Some(y)) = (
+                cell.0.checked_add_signed(*dx),
+                cell.1.checked_add_signed(*dy),
+            ) else {
+                continue;
+            };
+            if x >= xs.len() || y >= ys.len() {
+                continue;
+            }
+            let next = (x, y);
+            if blocked(point(cell), point(next)) {
+                continue;
+            }
+            let bend = if direction != 4 && direction != next_direction {
+                BEND_COST
+            } else {
+                0.0
+            };
+            let length = (point(cell) - point(next)).length();
+            let next_cost = cost + (length + bend) as u64;
+            if next_cost < best[state(next, next_direction)] {
+                best[state(next, next_direction)] = next_cost;
+                previous[state(next, next_direction)] = Some((cell, direction));
+                queue.push(Reverse((
+                    next_cost + heuristic(next),
+                    next_cost,
+                    next,
+                    next_direction,
+                )));
+            }
+        }
+    }
+    None
+}
+
+/// Whether the horizontal or vertical segment goes through the inside of `rect`. Running along
+/// its border is fine.
+fn crosses(rect: &Rectangle, a: Point2, b: Point2) -> bool {
+    let (min_x, max_x) = (a.x.min(b.x), a.x.max(b.x));
+    let (min_y, max_y) = (a.y.min(b.y), a.y.max(b.y));
+    let (right, bottom) = (rect.x + rect.width, rect.y + rect.height);
+    if min_y == max_y {
+        min_y > rect.y && min_y < bottom && min_x < right && max_x > rect.x
+    } else {
+        min_x > rect.x && min_x < right && min_y < bottom && max_y > rect.y
+    }
+}
+
+/// Drop points that are in the middle of a straight line
+fn simplify(points: Vec<Point2>) -> Vec<Point2> {
+    let mut simplified: Vec<Point2> = Vec::with_capacity(points.len());
+    for point in points {
+        if let Some(last) = simplified.last()
+            && (last.x - point.x).abs() < 0.01
+            && (last.y - point.y).abs() < 0.01
+        {
+            continue;
+        }
+        if let [.., a, b] = simplified.as_slice() {
+            let horizontal = (a.y - b.y).abs() < 0.01 && (b.y - point.y).abs() < 0.01;
+            let vertical = (a.x - b.x).abs() < 0.01 && (b.x - point.x).abs() < 0.01;
+            if horizontal || vertical {
+                simplified.pop();
+            }
+        }
+        simplified.push(point);
+    }
+    simplified
+}
+
+/// Give every output its own lane on lines that routes of several outputs run along. The points
+/// at the sockets never move.
+fn separate(requests: &[RouteRequest], routes: &mut [Vec<Point2>]) {
+    // (horizontal, coordinate) of a line, with the outputs whose routes run along it
+    let mut lines: Vec<((bool, i32), Vec<SocketPos>)> = Vec::new();
+    for (request, route) in requests.iter().zip(routes.iter()) {
+        for (_, line) in segments(route) {
+            match lines.iter_mut().find(|(l, _)| *l == line) {
+                Some((_, outputs)) if !outputs.contains(&request.src) => outputs.push(request.src),
+                Some(_) => {}
+                None => lines.push((line, vec![request.src])),
+            }
+        }
+    }
+
+    for (request, route) in requests.iter().zip(routes.iter_mut()) {
+        let shifts: Vec<(usize, bool, f32)> = segments(route)
+            .filter_map(|(i, line)| {
+                let outputs = &lines.iter().find(|(l, _)| *l == line)?.1;
+                let lane = outputs.iter().position(|o| *o == request.src)?;
+                let offset = (lane as f32 - (outputs.len() - 1) as f32 / 2.0) * LANE_SPACING;
+                (offset != 0.0).then_some((i, line.0, offset))
+            })
+            .collect();
+        for (i, horizontal, offset) in shifts {
+            for point in &mut route[i..=i + 1] {
+                if horizontal {
+                    point.y += offset;
+                } else {
+                    point.x += offset;
+                }
+            }
+        }
+    }
+}
+
+/// The inner segments of a route, with the line they run along. The first and last segments,
+/// at the sockets, are left out.
+fn segments(route: &[Point2]) -> impl Iterator<Item = (usize, (bool, i32))> + '_ {
+    (1..route.len().saturating_sub(2)).map(move |i| {
+        let (a, b) = (route[i], route[i + 1]);
+        let horizontal = (a.y - b.y).abs() < 0.01;
+        let coordinate = if horizontal { a.y } else { a.x };
+        (i, (horizontal, coordinate.round() as i32))
+    })
+}
+
+/// The total length of a route
+pub fn length(points: &[Point2]) -> f32 {
+    points.windows(2).map(|w| (w[1] - w[0]).length()).sum()
+}
+
+/// The point at `fraction` of the length of a route, and the direction it goes there
+pub fn point_along(points: &[Point2], fraction: f32) -> (Point2, Vec2) {
+    let mut remaining = length(points) * fraction.clamp(0.0, 1.0);
+    for w in points.windows(2) {
+        let segment = w[1] - w[0];
+        let segment_length = segment.length();
+        if remaining <= segment_length && segment_length > 0.0 {
+            return (w[0] + segment * (remaining / segment_length), segment);
+        }
+        remaining -= segment_length;
+    }
+    let last = points.last().copied().unwrap_or(Point2::ZERO);
+    (last, Vec2::ZERO)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RouteRequest, crosses, route_all};
+    use crate::{
+        NodeId,
+        utils::{Point2, Rectangle, Vec2},
+    };
+
+    fn request(waypoints: &[Point2]) -> RouteRequest<'_> {
+        RouteRequest {
+            src: (NodeId(0), 0, false).into(),
+            from: Point2::new(0.0, 0.0),
+            from_direction: Vec2::new(1.0, 0.0),
+            to: Point2::new(400.0, 0.0),
+            to_direction: Vec2::new(-1.0, 0.0),
+            waypoints,
+        }
+    }
+
+    fn assert_orthogonal(route: &[Point2]) {
+        for w in route.windows(2) {
+            assert!(
+                (w[0].x - w[1].x).abs() < 0.01 || (w[0].y - w[1].y).abs() < 0.01,
+                "diagonal segment in {route:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn around_an_obstacle() {
+        let obstacle = Rectangle::new(150.0, -50.0, 100.0, 100.0);
+        let routes = route_all(&[request(&[])], &[obstacle]);
+        let route = &routes[0];
+
+        assert_eq!(route.first(), Some(&Point2::new(0.0, 0.0)));
+        assert_eq!(route.last(), Some(&Point2::new(400.0, 0.0)));
+        assert_orthogonal(route);
+        for w in route.windows(2) {
+            assert!(
+                !crosses(&obstacle, w[0], w[1]),
+                "{route:?} crosses the obstacle"
+            );
+        }
+    }
+
+    #[test]
+    fn straight_without_obstacles() {
+        let routes = route_all(&[request(&[])], &[]);
+        assert_eq!(routes[0], [Point2::new(0.0, 0.0), Point2::new(400.0, 0.0)]);
+    }
+
+    #[test]
+    fn through_waypoints() {
+        let waypoints = [Point2::new(200.0, 200.0), Point2::new(300.0, -100.0)];
+        let routes = route_all(&[request(&waypoints)], &[]);
+        let route = &routes[0];
+
+        assert_orthogonal(route);
+        let first = route.iter().position(|p| *p == waypoints[0]);
+        let second = route.iter().position(|p| *p == waypoints[1]);
+        assert!(
+            matches!((first, second), (Some(a), Some(b)) if a < b),
+            "{route:?} doesn't go through the waypoints in order"
+        );
+    }
+}
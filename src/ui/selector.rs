@@ -1,9 +1,16 @@
 use super::{PopupClickResult, app::App};
 use crate::{
+    ItemOrFluidId,
+    cost::{self, RawBreakdown, RawCost},
     gfx::{DrawUiCtx, Paint},
-    state::{FluidId, GroupRow, ItemId, Preset, RecipeId, Theme},
-    utils::{Point2, Vec2},
+    reachability::Reachability,
+    state::{
+        FluidId, GroupRow, ItemId, Preset, RecipeClass, RecipeFilter, RecipeId, ResearchState,
+        Theme,
+    },
+    utils::{Point2, Rectangle, Vec2},
 };
+use rustc_hash::FxHashMap;
 
 pub struct Selector {
     pub tabs: Vec<SelectorTab>,
@@ -11,6 +18,9 @@ pub struct Selector {
     pub hover: Hover,
     pub scroll_offset: Vec2,
     pub size: Vec2,
+    pub filter: RecipeFilter,
+    /// Whether the filter toggles are shown, only recipe selectors have them
+    filterable: bool,
 }
 
 impl Selector {
@@ -20,6 +30,51 @@ impl Selector {
     const ITEM_HEIGHT: f32 = 30.0;
     const ITEM_WIDTH: f32 = 30.0;
     const TOP_LEFT: Point2 = Point2::new(50., 50.);
+    const FILTER_WIDTH: f32 = 80.0;
+    const FILTER_HEIGHT: f32 = 25.0;
+    const FILTERS: [&str; 3] = ["Hidden", "Barrels", "Voids"];
+
+    fn filter_enabled(&self, filter_idx: usize) -> bool {
+        match filter_idx {
+            0 => self.filter.hidden,
+            1 => self.filter.barreling,
+            _ => self.filter.void,
+        }
+    }
+
+    fn toggle_filter(&mut self, filter_idx: usize) {
+        let enabled = match filter_idx {
+            0 => &mut self.filter.hidden,
+            1 => &mut self.filter.barreling,
+            _ => &mut self.filter.void,
+        };
+        *enabled = !*enabled;
+    }
+
+    fn filter_rect(filter_idx: usize) -> Rectangle {
+        Rectangle::new(
+            Self::TOP_LEFT.x + filter_idx as f32 * Self::FILTER_WIDTH,
+            Self::TOP_LEFT.y - Self::FILTER_HEIGHT,
+            Self::FILTER_WIDTH,
+            Self::FILTER_HEIGHT,
+        )
+    }
+
+    /// The rows of the active tab without the items that are filtered out. Rows that end up empty
+    /// are left out.
+    fn visible_rows(&self) -> Vec<Vec<&SelectorItem>> {
+        self.tabs[self.active_tab]
+            .rows
+            .iter()
+            .map(|row| {
+                row.items
+                    .iter()
+                    .filter(|i| self.filter.allows(i.hidden, i.class))
+                    .collect::<Vec<_>>()
+            })
+            .filter(|row| !row.is_empty())
+            .collect()
+    }
 
     pub(crate) fn try_click(&mut self, _app: &mut App) -> PopupClickResult {
         match self.hover {
@@ -28,8 +83,16 @@ impl Selector {
                 self.active_tab = tab_idx;
                 return PopupClickResult::None;
             }
+            Hover::Filter { filter_idx } => {
+                self.toggle_filter(filter_idx);
+                self.hover = Hover::None;
+                return PopupClickResult::None;
+            }
             Hover::Item { row_idx, item_idx } => {
-                let item = &self.tabs[self.active_tab].rows[row_idx].items[item_idx];
+                let item = self.visible_rows()[row_idx][item_idx];
+                if item.locked.is_some() {
+                    return PopupClickResult::None;
+                }
                 (item.on_click)(_app);
                 return PopupClickResult::Close;
             }
@@ -57,23 +120,89 @@ impl Selector {
             ctx.draw_image(&tab.icon, rect.shrink(1.));
         }
 
+        if self.filterable {
+            for (idx, label) in Self::FILTERS.iter().enumerate() {
+                let rect = Self::filter_rect(idx);
+                let background = if self.filter_enabled(idx)
+                    || self.hover == (Hover::Filter { filter_idx: idx })
+                {
+                    theme.layer_color(4)
+                } else {
+                    theme.layer_color(3)
+                };
+                ctx.draw_fill_border(
+                    rect,
+                    Paint::color(background),
+                    Paint::color(theme.layer_color(2)),
+                );
+                ctx.fill_text_centered(
+                    rect,
+                    label,
+                    Paint::color(theme.background.text).with_font_size(14),
+                );
+            }
+        }
+
+        let rows = self.visible_rows();
         let row_position = Self::TOP_LEFT + Point2::new(0.0, Self::TAB_HEIGHT);
-        for (row_idx, row) in self.tabs[self.active_tab].rows.iter().enumerate() {
+        for (row_idx, row) in rows.iter().enumerate() {
             let row_position = row_position + Vec2::new(0.0, row_idx as f32 * Self::ITEM_HEIGHT);
-            for (item_idx, item) in row.items.iter().enumerate() {
+            for (item_idx, item) in row.iter().enumerate() {
                 let item_position =
                     row_position + Vec2::new(Self::ITEM_WIDTH * item_idx as f32, 0.0);
                 let rect = item_position.with_size((Self::ITEM_WIDTH, Self::ITEM_HEIGHT).into());
 
-                ctx.draw_image(&item.icon, rect.shrink(1.));
+                if self.hover == (Hover::Item { row_idx, item_idx }) {
+                    ctx.draw_fill(rect, Paint::color(theme.layer_color(4)));
+                }
+                if item.locked.is_some() {
+                    ctx.draw_image_greyed(&item.icon, rect.shrink(1.));
+                } else {
+                    ctx.draw_image(&item.icon, rect.shrink(1.));
+                }
+                if item.problem.is_some() {
+                    ctx.draw_border(rect, Paint::color(theme.error.color));
+                }
             }
         }
+
+        if let Hover::Item { row_idx, item_idx } = self.hover {
+            let item = rows[row_idx][item_idx];
+            let label = match (&item.locked, &item.problem, item.cost) {
+                (Some(reason), _, _) | (None, Some(reason), _) => {
+                    format!("{} ({reason})", item.name)
+                }
+                (None, None, Some(cost)) => format!("{} ({cost})", item.name),
+                (None, None, None) => item.name.clone(),
+            };
+            let color = if item.locked.is_some() || item.problem.is_some() {
+                theme.error.color
+            } else {
+                theme.background.text
+            };
+            ctx.fill_text(
+                Self::TOP_LEFT.x,
+                Self::TOP_LEFT.y + self.size.y + Self::PADDING,
+                &label,
+                Paint::color(color).with_font_size(16),
+            )
+            .ok();
+        }
     }
 
     pub(crate) fn mouse_move(&mut self, mut mouse: Point2) -> bool {
+        if self.filterable {
+            if let Some(filter_idx) =
+                (0..Self::FILTERS.len()).find(|&i| Self::filter_rect(i).contains(mouse))
+            {
+                self.hover = Hover::Filter { filter_idx };
+                return true;
+            }
+        }
         mouse -= Self::TOP_LEFT;
 
         if mouse.y < 0. || mouse.x < 0. {
+            self.hover = Hover::None;
             return false;
         }
         if mouse.y < Self::TAB_HEIGHT {
@@ -85,8 +214,16 @@ impl Selector {
             }
             true
         } else {
-            // self.hover_idx = None;
-            false
+            let row_idx = ((mouse.y - Self::TAB_HEIGHT) / Self::ITEM_HEIGHT) as usize;
+            let item_idx = (mouse.x / Self::ITEM_WIDTH) as usize;
+            let rows = self.visible_rows();
+            if rows.get(row_idx).is_some_and(|r| item_idx < r.len()) {
+                self.hover = Hover::Item { row_idx, item_idx };
+                true
+            } else {
+                self.hover = Hover::None;
+                false
+            }
         }
     }
 
@@ -139,16 +276,24 @@ impl Selector {
             active_tab: 0,
             hover: Hover::None,
             scroll_offset: Vec2::ZERO,
+            filter: RecipeFilter::default(),
+            filterable: false,
         };
 
         result
     }
 
+    /// Recipes that aren't unlocked by `research` are shown, but can't be picked. The raw cost
+    /// shown for a recipe is that of its main product.
     pub fn new_recipe(
         preset: &Preset,
+        research: &ResearchState,
+        costs: &FxHashMap<ItemOrFluidId, RawBreakdown>,
+        reachability: Option<&Reachability>,
+        filter: RecipeFilter,
         onclick: impl Fn(RecipeId, &mut App) + Clone + 'static,
     ) -> Self {
-        Self::new(preset, move |row| {
+        let mut selector = Self::new(preset, move |row| {
             let onclick = onclick.clone();
             row.recipes.iter().cloned().map(move |recipe_id| {
                 let recipe = &preset.recipes[&recipe_id];
@@ -156,20 +301,45 @@ impl Selector {
                 SelectorItem {
                     name: recipe.name.clone(),
                     icon: preset.icon_for_recipe(recipe),
+                    locked: research
+                        .recipe_lock(preset, recipe_id)
+                        .map(|lock| lock.to_string()),
+                    cost: recipe.results.first().and_then(|p| {
+                        cost::recipe_cost(recipe, &p.item_or_fluid, |i| {
+                            costs.get(i).map(|b| b.cost)
+                        })
+                    }),
+                    problem: reachability.and_then(|r| r.recipe_problem(recipe_id)),
+                    hidden: recipe.hidden,
+                    class: recipe.class,
                     on_click: Box::new(move |app| onclick(recipe_id, app)),
                 }
             })
-        })
+        });
+        selector.filter = filter;
+        selector.filterable = true;
+        selector
     }
-    pub fn new_item(preset: &Preset, onclick: impl Fn(ItemId, &mut App) + Clone + 'static) -> Self {
+    pub fn new_item(
+        preset: &Preset,
+        costs: &FxHashMap<ItemOrFluidId, RawBreakdown>,
+        reachability: Option<&Reachability>,
+        onclick: impl Fn(ItemId, &mut App) + Clone + 'static,
+    ) -> Self {
         Self::new(preset, move |row| {
             let onclick = onclick.clone();
             row.items.iter().cloned().map(move |item_id| {
                 let item = &preset.items[&item_id];
+                let item_or_fluid = ItemOrFluidId::Item(item.name.clone());
                 let onclick = onclick.clone();
                 SelectorItem {
                     name: item.name.clone(),
                     icon: preset.icon_for_item(item),
+                    locked: None,
+                    cost: costs.get(&item_or_fluid).map(|b| b.cost),
+                    problem: reachability.and_then(|r| r.item_problem(&item_or_fluid)),
+                    hidden: false,
+                    class: RecipeClass::Normal,
                     on_click: Box::new(move |app| onclick(item_id, app)),
                 }
             })
@@ -178,28 +348,91 @@ impl Selector {
 
     pub fn new_fluid(
         preset: &Preset,
+        costs: &FxHashMap<ItemOrFluidId, RawBreakdown>,
+        reachability: Option<&Reachability>,
         onclick: impl Fn(FluidId, &mut App) + Clone + 'static,
     ) -> Self {
         Self::new(preset, move |row| {
             let onclick = onclick.clone();
             row.fluids.iter().cloned().map(move |fluid_id| {
                 let fluid = &preset.fluids[&fluid_id];
+                let item_or_fluid = ItemOrFluidId::Fluid(fluid.name.clone());
                 let onclick = onclick.clone();
                 SelectorItem {
                     name: fluid.name.clone(),
                     icon: preset.icon_for_fluid(fluid),
+                    locked: None,
+                    cost: costs.get(&item_or_fluid).map(|b| b.cost),
+                    problem: reachability.and_then(|r| r.item_problem(&item_or_fluid)),
+                    hidden: false,
+                    class: RecipeClass::Normal,
                     on_click: Box::new(move |app| onclick(fluid_id, app)),
                 }
             })
         })
     }
+
+    /// Lists the items and fluids that can be mined, calling `onclick` with the name of the
+    /// resource that produces them
+    pub fn new_resource(
+        preset: &Preset,
+        onclick: impl Fn(String, &mut App) + Clone + 'static,
+    ) -> Self {
+        Self::new(preset, move |row| {
+            let items = row.items.iter().map(|item_id| {
+                let item = &preset.items[item_id];
+                (
+                    ItemOrFluidId::Item(item.name.clone()),
+                    preset.icon_for_item(item),
+                )
+            });
+            let fluids = row.fluids.iter().map(|fluid_id| {
+                let fluid = &preset.fluids[fluid_id];
+                (
+                    ItemOrFluidId::Fluid(fluid.name.clone()),
+                    preset.icon_for_fluid(fluid),
+                )
+            });
+            let onclick = onclick.clone();
+            items
+                .chain(fluids)
+                .flat_map(move |(item_or_fluid, icon)| {
+                    preset
+                        .resources_producing(&item_or_fluid)
+                        .map(move |resource| (resource.name.clone(), icon.clone()))
+                        .collect::<Vec<_>>()
+                })
+                .map(move |(name, icon)| {
+                    let onclick = onclick.clone();
+                    SelectorItem {
+                        name: name.clone(),
+                        icon,
+                        locked: None,
+                        cost: None,
+                        problem: None,
+                        hidden: false,
+                        class: RecipeClass::Normal,
+                        on_click: Box::new(move |app| onclick(name.clone(), app)),
+                    }
+                })
+        })
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Hover {
     None,
-    Tab { tab_idx: usize },
-    Item { row_idx: usize, item_idx: usize },
+    Tab {
+        tab_idx: usize,
+    },
+    Filter {
+        filter_idx: usize,
+    },
+    /// Indices into the visible rows
+    Item {
+        row_idx: usize,
+        item_idx: usize,
+    },
 }
 impl Hover {
     fn is_tab(&self, idx: usize) -> bool {
@@ -223,10 +456,17 @@ pub struct SelectorRow {
 }
 
 pub struct SelectorItem {
-    #[allow(dead_code)]
     pub name: String,
-    #[allow(dead_code)]
     pub icon: String,
+    /// Why the item can't be picked, if it can't
+    pub locked: Option<String>,
+    /// Shown next to the name when hovered
+    pub cost: Option<RawCost>,
+    /// Why the item can't be made from raw resources, when the reachability overlay is shown
+    pub problem: Option<String>,
+    /// Used by the filter toggles, only recipes can be hidden or have a class other than normal
+    pub hidden: bool,
+    pub class: RecipeClass,
     #[allow(dead_code)]
     pub on_click: Box<dyn Fn(&mut App)>,
 }
@@ -236,6 +476,11 @@ impl std::fmt::Debug for SelectorItem {
         f.debug_struct("SelectorItem")
             .field("name", &self.name)
             .field("icon", &self.icon)
+            .field("locked", &self.locked)
+            .field("cost", &self.cost)
+            .field("problem", &self.problem)
+            .field("hidden", &self.hidden)
+            .field("class", &self.class)
             .finish()
     }
 }
@@ -0,0 +1,171 @@
+//! A grid over the world that finds the things near a point or in an area without looking at
+//! everything, for hover tests and for skipping what is off screen.
+
+use crate::utils::{Point2, Rectangle};
+use rustc_hash::FxHashMap;
+use std::hash::Hash;
+
+/// Side of a grid cell in world units. About the size of a node, so most things are in a few
+/// cells.
+const CELL_SIZE: f32 = 256.0;
+
+pub struct SpatialIndex<T> {
+    entries: Vec<(Rectangle, T)>,
+    /// Where every value is in `entries`
+    positions: FxHashMap<T, usize>,
+    /// Indices into `entries` of everything that overlaps each cell
+    cells: FxHashMap<(i32, i32), Vec<usize>>,
+}
+
+impl<T> Default for SpatialIndex<T> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+            positions: FxHashMap::default(),
+            cells: FxHashMap::default(),
+        }
+    }
+}
+
+impl<T: Copy + Eq + Hash> SpatialIndex<T> {
+    /// Add `value` at `rect`, or move it there if it is in the index already
+    pub fn insert(&mut self, rect: Rectangle, value: T) {
+        let index = match self.positions.get(&value) {
+            Some(&index) => {
+                for cell in cells(self.entries[index].0) {
+                    if let Some(indices) = self.cells.get_mut(&cell) {
+                        indices.retain(|&i| i != index);
+                    }
+                }
+                self.entries[index].0 = rect;
+                index
+            }
+            None => {
+                self.positions.insert(value, self.entries.len());
+                self.entries.push((rect, value));
+                self.entries.len() - 1
+            }
+        };
+        for cell in cells(rect) {
+            self.cells.entry(cell).or_default().push(index);
+        }
+    }
+
+    /// Everything whose rectangle contains `point`
+    pub fn at(&self, point: Point2) -> impl Iterator<Item = T> + '_ {
+        let cell = cell_of(point);
+        self.cells
+            .get(&cell)
+            .into_iter()
+            .flatten()
+            .map(|&index| &self.entries[index])
+            .filter(move |(rect, _)| rect.contains(point))
+            .map(|(_, value)| *value)
+    }
+
+    /// Everything whose rectangle overlaps `area`, each once
+    pub fn overlapping(&self, area: Rectangle) -> Vec<T> {
+        let mut indices: Vec<usize> = cells(area)
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .filter(|&index| self.entries[index].0.overlaps(&area))
+            .collect();
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+            .into_iter()
+            .map(|index| self.entries[index].1)
+            .collect()
+    }
+}
+
+fn cell_of(point: Point2) -> (i32, i32) {
+    (
+        (point.x / CELL_SIZE).floor() as i32,
+        (point.y / CELL_SIZE).floor() as i32,
+    )
+}
+
+fn cells(rect: Rectangle) -> impl Iterator<Item = (i32, i32)> {
+    let (min_x, min_y) = cell_of(Point2::new(rect.x, rect.y));
+    let (max_x, max_y) = cell_of(Point2::new(rect.right(), rect.y + rect.height));
+    (min_x..=max_x).flat_map(move |x| (min_y..=max_y).map(move |y| (x, y)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CELL_SIZE, SpatialIndex};
+    use crate::utils::{Point2, Rectangle};
+
+    #[test]
+    fn across_cell_borders() {
+        let mut index = SpatialIndex::default();
+        // Spans four cells around the origin
+        index.insert(Rectangle::new(-50.0, -50.0, 100.0, 100.0), 1);
+        index.insert(Rectangle::new(CELL_SIZE + 10.0, 0.0, 20.0, 20.0), 2);
+
+        for point in [(-40.0, -40.0), (40.0, -40.0), (-40.0, 40.0), (40.0, 40.0)] {
+            assert_eq!(
+                index.at(Point2::new(point.0, point.1)).collect::<Vec<_>>(),
+                [1]
+            );
+        }
+        assert_eq!(index.at(Point2::new(60.0, 0.0)).count(), 0);
+        assert_eq!(
+            index
+                .at(Point2::new(CELL_SIZE + 15.0, 5.0))
+                .collect::<Vec<_>>(),
+            [2]
+        );
+    }
+
+    #[test]
+    fn negative_coordinates() {
+        let mut index = SpatialIndex::default();
+        index.insert(
+            Rectangle::new(-3.0 * CELL_SIZE, -2.5 * CELL_SIZE, 10.0, 10.0),
+            1,
+        );
+        index.insert(Rectangle::new(-10.0, -10.0, 5.0, 5.0), 2);
+
+        let far = Point2::new(-3.0 * CELL_SIZE + 5.0, -2.5 * CELL_SIZE + 5.0);
+        assert_eq!(index.at(far).collect::<Vec<_>>(), [1]);
+        assert_eq!(index.at(Point2::new(-7.0, -7.0)).collect::<Vec<_>>(), [2]);
+        assert_eq!(index.at(Point2::new(7.0, 7.0)).count(), 0);
+    }
+
+    #[test]
+    fn overlapping_reports_each_once() {
+        let mut index = SpatialIndex::default();
+        index.insert(Rectangle::new(-300.0, -300.0, 600.0, 600.0), 1);
+        index.insert(Rectangle::new(1000.0, 1000.0, 10.0, 10.0), 2);
+
+        let area = Rectangle::new(-500.0, -500.0, 1000.0, 1000.0);
+        assert_eq!(index.overlapping(area), [1]);
+        let everything = Rectangle::new(-500.0, -500.0, 2000.0, 2000.0);
+        assert_eq!(index.overlapping(everything), [1, 2]);
+        // Touching counts
+        assert_eq!(
+            index.overlapping(Rectangle::new(300.0, 0.0, 10.0, 10.0)),
+            [1]
+        );
+    }
+
+    #[test]
+    fn moving() {
+        let mut index = SpatialIndex::default();
+        index.insert(Rectangle::new(0.0, 0.0, 10.0, 10.0), 1);
+        index.insert(Rectangle::new(2.0 * CELL_SIZE, 0.0, 10.0, 10.0), 1);
+
+        assert_eq!(index.at(Point2::new(5.0, 5.0)).count(), 0);
+        assert_eq!(
+            index
+                .at(Point2::new(2.0 * CELL_SIZE + 5.0, 5.0))
+                .collect::<Vec<_>>(),
+            [1]
+        );
+        let everything = Rectangle::new(-CELL_SIZE, -CELL_SIZE, 4.0 * CELL_SIZE, 4.0 * CELL_SIZE);
+        assert_eq!(index.overlapping(everything), [1]);
+    }
+}
@@ -0,0 +1,192 @@
+use crate::{
+    gfx::{DrawUiCtx, Paint},
+    state::{Preset, Theme},
+    template::{Template, TemplateGraph},
+    utils::{Point2, Rectangle},
+};
+
+pub enum BrowserClick {
+    Close,
+    /// Insert the template with this index as a sub-factory
+    Insert(usize),
+}
+
+/// A popup that lists the saved templates, with a preview of the hovered one
+pub struct TemplateBrowser {
+    pub templates: Vec<Template>,
+    /// What each template misses from the current preset
+    problems: Vec<Vec<String>>,
+    /// Index of the first visible row
+    scroll: usize,
+    hover: Option<usize>,
+    /// Where the inserted sub-factory is placed in the factory
+    pub position: Point2,
+}
+
+impl TemplateBrowser {
+    const WIDTH: f32 = 400.0;
+    const ROW_HEIGHT: f32 = 44.0;
+    const VISIBLE_ROWS: usize = 12;
+    const PADDING: f32 = 5.0;
+    const PREVIEW_SIZE: f32 = 240.0;
+
+    pub fn new(preset: &Preset, templates: Vec<Template>, position: Point2) -> Self {
+        Self {
+            problems: templates.iter().map(|t| t.graph.problems(preset)).collect(),
+            templates,
+            scroll: 0,
+            hover: None,
+            position,
+        }
+    }
+
+    fn rect(window_size: Point2) -> Rectangle {
+        let height = Self::VISIBLE_ROWS as f32 * Self::ROW_HEIGHT;
+        Rectangle::new(
+            (window_size.x - Self::WIDTH - Self::PREVIEW_SIZE) / 2.0,
+            (window_size.y - height) / 2.0,
+            Self::WIDTH,
+            height,
+        )
+    }
+
+    /// Scroll by a number of rows, negative values scroll up
+    pub fn scroll_by(&mut self, rows: isize) {
+        let max = self.templates.len().saturating_sub(Self::VISIBLE_ROWS);
+        self.scroll = self.scroll.saturating_add_signed(rows).min(max);
+    }
+
+    pub(crate) fn mouse_move(&mut self, window_size: Point2, mouse: Point2) {
+        let rect = Self::rect(window_size);
+        self.hover = None;
+        if rect.contains(mouse) {
+            let index = self.scroll + ((mouse.y - rect.y) / Self::ROW_HEIGHT) as usize;
+            if index < self.templates.len() {
+                self.hover = Some(index);
+            }
+        }
+    }
+
+    pub(crate) fn try_click(&self) -> BrowserClick {
+        match self.hover {
+            Some(index) => BrowserClick::Insert(index),
+            None => BrowserClick::Close,
+        }
+    }
+
+    pub fn draw(&self, ctx: &mut DrawUiCtx, theme: &Theme) {
+        let rect = Self::rect(ctx.window_size);
+        let text = Paint::color(theme.background.text).with_font_size(16);
+        let small = Paint::color(theme.layer_color(4)).with_font_size(12);
+        let error = Paint::color(theme.error.color).with_font_size(12);
+
+        ctx.draw_fill_border(
+            rect,
+            Paint::color(theme.layer_color(1)),
+            Paint::color(theme.layer_color(2)),
+        );
+        if self.templates.is_empty() {
+            ctx.fill_text_centered(rect, "No templates saved yet", text);
+        }
+
+        let visible = self.templates.iter().enumerate().skip(self.scroll);
+        for (row, (index, template)) in visible.take(Self::VISIBLE_ROWS).enumerate() {
+            let y = rect.y + row as f32 * Self::ROW_HEIGHT;
+            if self.hover == Some(index) {
+                ctx.draw_fill(
+                    Rectangle::new(rect.x, y, rect.width, Self::ROW_HEIGHT),
+                    Paint::color(theme.layer_color(2)),
+                );
+            }
+            let x = rect.x + Self::PADDING;
+            ctx.fill_text(x, y + Self::PADDING, &template.name, text)
+                .ok();
+            let tags = if template.tags.is_empty() {
+                "no tags".to_string()
+            } else {
+                template.tags.join(", ")
+            };
+            ctx.fill_text(x, y + Self::ROW_HEIGHT / 2.0 + 2.0, &tags, small)
+                .ok();
+            if !self.problems[index].is_empty() {
+                let label = format!("{} missing", self.problems[index].len());
+                ctx.fill_text(
+                    rect.x + rect.width - 100.0,
+                    y + Self::PADDING,
+                    &label,
+                    error,
+                )
+                .ok();
+            }
+        }
+
+        if let Some(index) = self.hover {
+            let preview = Rectangle::new(
+                rect.x + rect.width + Self::PADDING,
+                rect.y,
+                Self::PREVIEW_SIZE,
+                Self::PREVIEW_SIZE,
+            );
+            ctx.draw_fill_border(
+                preview,
+                Paint::color(theme.layer_color(1)),
+                Paint::color(theme.layer_color(2)),
+            );
+            draw_preview(
+                ctx,
+                theme,
+                &self.templates[index].graph,
+                preview.shrink(10.0),
+            );
+
+            for (line, problem) in self.problems[index].iter().enumerate() {
+                let y = preview.y + preview.height + Self::PADDING + line as f32 * 16.0;
+                ctx.fill_text(preview.x, y, problem, error).ok();
+            }
+        }
+    }
+}
+
+/// The nodes and connections of the graph, scaled down to fit `rect`
+fn draw_preview(ctx: &mut DrawUiCtx, theme: &Theme, graph: &TemplateGraph, rect: Rectangle) {
+    if graph.nodes.is_empty() {
+        let text = Paint::color(theme.background.text).with_font_size(16);
+        ctx.fill_text_centered(rect, "Empty", text);
+        return;
+    }
+
+    // Node positions are their centres, and nodes are 100 wide
+    let (mut min_x, mut min_y) = (f32::INFINITY, f32::INFINITY);
+    let (mut max_x, mut max_y) = (f32::NEG_INFINITY, f32::NEG_INFINITY);
+    for node in &graph.nodes {
+        min_x = min_x.min(node.position.0 - 50.0);
+        min_y = min_y.min(node.position.1 - 50.0);
+        max_x = max_x.max(node.position.0 + 50.0);
+        max_y = max_y.max(node.position.1 + 50.0);
+    }
+    let scale = (rect.width / (max_x - min_x)).min(rect.height / (max_y - min_y));
+    let to_preview = |(x, y): (f32, f32)| {
+        Point2::new(rect.x + (x - min_x) * scale, rect.y + (y - min_y) * scale)
+    };
+
+    let line = Paint::color(theme.layer_color(3));
+    for connection in &graph.connections {
+        let (Some(src), Some(dst)) = (
+            graph.nodes.get(connection.src),
+            graph.nodes.get(connection.dst),
+        ) else {
+            continue;
+        };
+        ctx.draw_line(to_preview(src.position), to_preview(dst.position), line);
+    }
+
+    let size = (100.0 * scale).max(4.0);
+    for node in &graph.nodes {
+        let center = to_preview(node.position);
+        ctx.draw_fill_border(
+            Rectangle::new(center.x - size / 2.0, center.y - size / 2.0, size, size),
+            Paint::color(theme.layer_color(2)),
+            Paint::color(theme.layer_color(4)),
+        );
+    }
+}
@@ -1,4 +1,34 @@
-use crate::{Node, utils::Rectangle};
+use crate::{
+    Cardinal, Node, NodeKind,
+    utils::{Point2, Rectangle, Vec2},
+};
+
+/// The width and height of a node. Nodes are `thickness` deep between their inputs and outputs,
+/// and long enough along the sockets to fit all of them.
+pub fn get_node_size(node: &Node) -> Vec2 {
+    let (thickness, min_length) = match node.kind {
+        NodeKind::Note { .. } => return Vec2::new(200.0, 100.0),
+        NodeKind::Splitter { .. } | NodeKind::Merger { .. } => (60.0, 60.0),
+        NodeKind::Bus => (40.0, 100.0),
+        _ => (100.0, 100.0),
+    };
+    let sockets = node.inputs.len().max(node.outputs.len());
+    let length = (sockets as f32 * 20.0 + 20.0).max(min_length);
+    match node.direction {
+        Cardinal::North | Cardinal::South => Vec2::new(length, thickness),
+        Cardinal::East | Cardinal::West => Vec2::new(thickness, length),
+    }
+}
+
+pub fn get_node_position(node: &Node) -> Rectangle {
+    let size = get_node_size(node);
+    Rectangle::new(
+        node.position.x - size.x / 2.0,
+        node.position.y - size.y / 2.0,
+        size.x,
+        size.y,
+    )
+}
 
 pub fn get_node_socket_position(node: &Node, socket: usize, is_input: bool) -> Rectangle {
     let (offset, step) = if is_input {
@@ -17,6 +47,9 @@ pub fn get_node_socket_position(node: &Node, socket: usize, is_input: bool) -> R
         (count - 1) as f32 / 2.0
     };
 
+    // The offsets are for nodes that are 100 deep
+    let size = get_node_size(node);
+    let offset = Point2::new(offset.x * size.x / 100.0, offset.y * size.y / 100.0);
     let offset = node.position + offset - (step * half_count);
 
     let pos = offset + step * socket as f32;
@@ -24,6 +57,53 @@ pub fn get_node_socket_position(node: &Node, socket: usize, is_input: bool) -> R
     Rectangle::centered_square(pos, 20.)
 }
 
+/// The point halfway along the bezier curve that `DrawWorldCtx::draw_bezier` draws for the same
+/// arguments
+pub fn bezier_midpoint(
+    from: Point2,
+    from_direction: Vec2,
+    to: Point2,
+    to_direction: Vec2,
+) -> Point2 {
+    bezier_point(from, from_direction, to, to_direction, 0.5)
+}
+
+/// A point along the same curve as `bezier_midpoint`, `t` goes from 0 at `from` to 1 at `to`
+pub fn bezier_point(
+    from: Point2,
+    from_direction: Vec2,
+    to: Point2,
+    to_direction: Vec2,
+    t: f32,
+) -> Point2 {
+    let control_1 = from + from_direction;
+    let control_2 = to + to_direction;
+    let u = 1.0 - t;
+    let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+    Point2::new(
+        a * from.x + b * control_1.x + c * control_2.x + d * to.x,
+        a * from.y + b * control_1.y + c * control_2.y + d * to.y,
+    )
+}
+
+/// The direction of the curve at `t`, not normalized
+pub fn bezier_tangent(
+    from: Point2,
+    from_direction: Vec2,
+    to: Point2,
+    to_direction: Vec2,
+    t: f32,
+) -> Vec2 {
+    let control_1 = from + from_direction;
+    let control_2 = to + to_direction;
+    let u = 1.0 - t;
+    let (a, b, c) = (3.0 * u * u, 6.0 * u * t, 3.0 * t * t);
+    Vec2::new(
+        a * (control_1.x - from.x) + b * (control_2.x - control_1.x) + c * (to.x - control_2.x),
+        a * (control_1.y - from.y) + b * (control_2.y - control_1.y) + c * (to.y - control_2.y),
+    )
+}
+
 // pub fn draw_bezier(
 //     canvas: &mut Canvas,
 //     paint: &Paint,
@@ -1,132 +1,5 @@
-use rustc_hash::FxHashMap;
 use sdl3::render::{FPoint, FRect};
 
-use crate::{Cardinal, InOutput, ItemOrFluidId, Node, NodeId};
-
-pub fn demo_nodes() -> FxHashMap<NodeId, Node> {
-    let mut nodes = FxHashMap::default();
-    nodes.insert(
-        NodeId(0),
-        Node {
-            id: NodeId(0),
-            position: Point2::new(100.0, 100.0),
-            inputs: vec![
-                InOutput {
-                    item_or_fluid: ItemOrFluidId::Item(String::new()),
-                    rate: 1.0,
-                },
-                InOutput {
-                    item_or_fluid: ItemOrFluidId::Fluid(String::new()),
-                    rate: 1.0,
-                },
-            ],
-            outputs: vec![
-                InOutput {
-                    item_or_fluid: ItemOrFluidId::Item(String::new()),
-                    rate: 1.0,
-                },
-                InOutput {
-                    item_or_fluid: ItemOrFluidId::Fluid(String::new()),
-                    rate: 1.0,
-                },
-            ],
-
-            direction: Cardinal::East,
-        },
-    );
-    nodes.insert(
-        NodeId(1),
-        Node {
-            id: NodeId(1),
-            position: Point2::new(200.0, 200.0),
-
-            inputs: vec![
-                InOutput {
-                    item_or_fluid: ItemOrFluidId::Item(String::new()),
-                    rate: 1.0,
-                },
-                InOutput {
-                    item_or_fluid: ItemOrFluidId::Fluid(String::new()),
-                    rate: 1.0,
-                },
-            ],
-            outputs: vec![
-                InOutput {
-                    item_or_fluid: ItemOrFluidId::Item(String::new()),
-                    rate: 1.0,
-                },
-                InOutput {
-                    item_or_fluid: ItemOrFluidId::Fluid(String::new()),
-                    rate: 1.0,
-                },
-            ],
-
-            direction: Cardinal::South,
-        },
-    );
-    nodes.insert(
-        NodeId(2),
-        Node {
-            id: NodeId(2),
-            position: Point2::new(300.0, 300.0),
-
-            inputs: vec![
-                InOutput {
-                    item_or_fluid: ItemOrFluidId::Item(String::new()),
-                    rate: 1.0,
-                },
-                InOutput {
-                    item_or_fluid: ItemOrFluidId::Fluid(String::new()),
-                    rate: 1.0,
-                },
-            ],
-            outputs: vec![
-                InOutput {
-                    item_or_fluid: ItemOrFluidId::Item(String::new()),
-                    rate: 1.0,
-                },
-                InOutput {
-                    item_or_fluid: ItemOrFluidId::Fluid(String::new()),
-                    rate: 1.0,
-                },
-            ],
-
-            direction: Cardinal::West,
-        },
-    );
-    nodes.insert(
-        NodeId(3),
-        Node {
-            id: NodeId(3),
-            position: Point2::new(400.0, 400.0),
-
-            inputs: vec![
-                InOutput {
-                    item_or_fluid: ItemOrFluidId::Item(String::new()),
-                    rate: 1.0,
-                },
-                InOutput {
-                    item_or_fluid: ItemOrFluidId::Fluid(String::new()),
-                    rate: 1.0,
-                },
-            ],
-            outputs: vec![
-                InOutput {
-                    item_or_fluid: ItemOrFluidId::Item(String::new()),
-                    rate: 1.0,
-                },
-                InOutput {
-                    item_or_fluid: ItemOrFluidId::Fluid(String::new()),
-                    rate: 1.0,
-                },
-            ],
-
-            direction: Cardinal::North,
-        },
-    );
-    nodes
-}
-
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
 pub struct Vec2 {
     pub x: f32,
@@ -242,10 +115,6 @@ impl Point2 {
             height: size.y,
         }
     }
-
-    pub(crate) fn spread(n: f32) -> Self {
-        Self { x: n, y: n }
-    }
 }
 
 impl Into<FPoint> for Point2 {
@@ -406,6 +275,14 @@ impl Rectangle {
         self
     }
 
+    /// Whether the rectangles share any point. Touching counts, like in `contains`.
+    pub fn overlaps(&self, other: &Rectangle) -> bool {
+        self.x <= other.right()
+            && other.x <= self.right()
+            && self.y <= other.y + other.height
+            && other.y <= self.y + self.height
+    }
+
     pub(crate) fn right(self) -> f32 {
         self.x + self.width
     }